@@ -0,0 +1,209 @@
+use rustc_serialize::json::{Json, Object};
+
+/// Every render configuration field, in the order they are listed by --help
+static OPTIONS: [(&'static str, &'static str); 30] = [
+    ("name", "built-in scene name (see list-scenes) or path to a .json scene file"),
+    ("size", "image size in pixels, e.g. 640,480"),
+    ("fov", "vertical field of view in degrees"),
+    ("reflect_depth", "maximum reflection recursions, or path length when path tracing"),
+    ("refract_depth", "maximum refraction recursions"),
+    ("shadow_samples", "samples for soft shadows and area lights"),
+    ("gloss_samples", "samples for glossy reflections"),
+    ("pixel_samples", "the square of this is the number of samples per pixel"),
+    ("output_file", "output path, the extension picks the format (png, jpg, tga, ppm, hdr, exr)"),
+    ("animating", "true to render an animation"),
+    ("fps", "animation frames per second"),
+    ("time_slice", "animation start and end time in seconds, e.g. 0,10"),
+    ("starting_frame_number", "number of the first animation frame, for file names"),
+    ("adaptive_threshold", "resample pixels until their relative error is below this"),
+    ("max_pixel_samples", "adaptive: samples per pixel limit, 16 * pixel_samples² by default"),
+    ("sample_map_file", "also write an image of the samples taken per pixel here"),
+    ("filter", "reconstruction filter: Box, Tent, Gaussian, Mitchell or Lanczos"),
+    ("filter_radius", "filter radius in pixels, defaults depend on the filter"),
+    ("sampler", "Independent, Stratified, Halton or Sobol"),
+    ("seed", "renders with the same seed are identical, random by default"),
+    ("integrator", "Whitted or PathTracer"),
+    ("accelerator", "Octree or Bvh"),
+    ("exposure", "exposure adjustment in stops"),
+    ("tone_map", "Clamp, Reinhard or Aces"),
+    ("srgb", "false to write linear 8-bit output"),
+    ("progressive", "true to keep adding passes until a budget below runs out"),
+    ("max_passes", "progressive: stop after this many passes of pixel_samples² samples"),
+    ("time_limit", "progressive: stop after this many seconds"),
+    ("noise_threshold", "progressive: stop once 99% of pixels are below this relative error"),
+    ("update_every", "progressive: write the image every this many passes"),
+];
+
+pub enum Command {
+    Help,
+    ListScenes,
+    Render(RenderArgs),
+}
+
+pub struct RenderArgs {
+    pub config_file: Option<String>, // sample-config.json is used when there is none
+    pub overrides: Vec<(String, Json)>,
+}
+
+pub fn usage(program_name: &str) -> String {
+    let mut usage = format!(
+"Usage:
+    {0} render [config.json] [--field value]...
+    {0} animate [config.json] [--field value]...
+    {0} list-scenes
+    {0} config.json
+
+render and animate read the render configuration from config.json, if given,
+then apply any overrides. Fields missing from config.json (or all of them, if no
+file is given) come from sample-config.json. Every field can be overridden:
+", program_name);
+
+    for &(name, description) in OPTIONS.iter() {
+        usage.push_str(&format!("    --{:<24}{}\n", name.replace("_", "-"), description));
+    }
+
+    usage
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, String> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_ref(), rest),
+        None => return Err("no command given".to_string())
+    };
+
+    match command {
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        "list-scenes" => Ok(Command::ListScenes),
+        "render" => parse_render_args(rest, Some(false)),
+        "animate" => parse_render_args(rest, Some(true)),
+        // Bare config path, as accepted before there were subcommands
+        _ if !command.starts_with("-") => parse_render_args(args, None),
+        _ => Err(format!("unknown command ``{}''", command))
+    }
+}
+
+fn parse_render_args(args: &[String], animating: Option<bool>) -> Result<Command, String> {
+    let mut config_file = None;
+    let mut overrides = Vec::new();
+
+    if let Some(animating) = animating {
+        overrides.push(("animating".to_string(), Json::Boolean(animating)));
+    }
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--help" || arg == "-h" {
+            return Ok(Command::Help);
+        }
+
+        if !arg.starts_with("--") {
+            if config_file.is_some() {
+                return Err(format!("unexpected argument ``{}''", arg));
+            }
+            config_file = Some(arg.clone());
+            continue;
+        }
+
+        // Both --field value and --field=value
+        let (name, value) = match arg[2..].find('=') {
+            Some(i) => (arg[2..2 + i].to_string(), arg[3 + i..].to_string()),
+            None => match args.next() {
+                Some(value) => (arg[2..].to_string(), value.clone()),
+                None => return Err(format!("missing value for ``{}''", arg))
+            }
+        };
+
+        let name = name.replace("-", "_");
+        if !OPTIONS.iter().any(|&(option, _)| option == name) {
+            return Err(format!("unknown option ``{}''", arg));
+        }
+
+        overrides.push((name, parse_value(&value)));
+    }
+
+    Ok(Command::Render(RenderArgs { config_file: config_file, overrides: overrides }))
+}
+
+/// JSON values are used as is. Comma separated lists become arrays, anything
+/// else is taken as a string so scene names and paths need no quoting.
+fn parse_value(value: &str) -> Json {
+    Json::from_str(value)
+        .or_else(|_| Json::from_str(&format!("[{}]", value)))
+        .unwrap_or(Json::String(value.to_string()))
+}
+
+/// Layers the configuration file (if any) and then the overrides on top of the defaults
+pub fn merge_config(defaults: &str, config_json: Option<&str>, overrides: &[(String, Json)])
+        -> Result<Json, String> {
+    let mut config = try!(parse_object(defaults));
+
+    if let Some(config_json) = config_json {
+        config.extend(try!(parse_object(config_json)).into_iter());
+    }
+
+    for &(ref name, ref value) in overrides.iter() {
+        config.insert(name.clone(), value.clone());
+    }
+
+    Ok(Json::Object(config))
+}
+
+fn parse_object(json: &str) -> Result<Object, String> {
+    match Json::from_str(json) {
+        Ok(Json::Object(object)) => Ok(object),
+        Ok(_) => Err("parse failure, the configuration is not an object".to_string()),
+        Err(err) => Err(format!("parse failure: {}", err))
+    }
+}
+
+#[cfg(test)]
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn it_parses_subcommands_and_overrides() {
+    match parse_args(&to_args(&["animate", "config.json", "--size", "64,48", "--pixel-samples=4",
+                                "--name", "box", "--output_file", "out.png"])) {
+        Ok(Command::Render(args)) => {
+            assert_eq!(Some("config.json".to_string()), args.config_file);
+            assert_eq!(vec![
+                ("animating".to_string(), Json::Boolean(true)),
+                ("size".to_string(), Json::Array(vec![Json::U64(64), Json::U64(48)])),
+                ("pixel_samples".to_string(), Json::U64(4)),
+                ("name".to_string(), Json::String("box".to_string())),
+                ("output_file".to_string(), Json::String("out.png".to_string())),
+            ], args.overrides);
+        },
+        _ => panic!("expected a render command")
+    }
+
+    match parse_args(&to_args(&["config.json"])) {
+        Ok(Command::Render(args)) => assert_eq!(0, args.overrides.len()),
+        _ => panic!("expected a render command")
+    }
+
+    assert!(match parse_args(&to_args(&["list-scenes"])) { Ok(Command::ListScenes) => true, _ => false });
+    assert!(match parse_args(&to_args(&["render", "--help"])) { Ok(Command::Help) => true, _ => false });
+}
+
+#[test]
+fn it_rejects_bad_arguments() {
+    assert!(parse_args(&to_args(&[])).is_err());
+    assert!(parse_args(&to_args(&["render", "--colour", "red"])).is_err());
+    assert!(parse_args(&to_args(&["render", "--fov"])).is_err());
+    assert!(parse_args(&to_args(&["render", "a.json", "b.json"])).is_err());
+    assert!(parse_args(&to_args(&["--fov", "30"])).is_err());
+}
+
+#[test]
+fn it_merges_defaults_file_and_overrides() {
+    let defaults = "{\"fov\": 30.0, \"name\": \"box\", \"fps\": 25.0}";
+    let overrides = vec![("fov".to_string(), Json::F64(45.0))];
+    let config = merge_config(defaults, Some("{\"fov\": 60.0, \"fps\": 30.0}"), &overrides).unwrap();
+    assert_eq!(Some(&Json::F64(45.0)), config.find("fov"));
+    assert_eq!(Some(&Json::F64(30.0)), config.find("fps"));
+    assert_eq!(Some(&Json::String("box".to_string())), config.find("name"));
+
+    assert!(merge_config(defaults, Some("[1, 2]"), &overrides).is_err());
+}
@@ -97,6 +97,11 @@ impl BBox {
     }
     
     pub fn intersects(&self, ray: &Ray) -> bool {
+        self.intersection_range(ray).is_some()
+    }
+
+    /// The (t_min, t_max) distances along the ray between which it is inside the box
+    pub fn intersection_range(&self, ray: &Ray) -> Option<(f64, f64)> {
         // Using ray.inverse_dir is an optimisation. Normally, for simplicity we would do
         //
         //     let d = -ray.direction;
@@ -133,7 +138,7 @@ impl BBox {
         let ty_max = (max_y_bound.y - o.y) * ray.inverse_dir.y;
 
         if t_min > ty_max || ty_min > t_max {
-            return false
+            return None
         }
         if ty_min > t_min {
             t_min = ty_min;
@@ -151,7 +156,7 @@ impl BBox {
         let tz_max = (max_z_bound.z - o.z) * ray.inverse_dir.z;
 
         if t_min > tz_max || tz_min > t_max {
-            return false
+            return None
         }
         if tz_min > t_min {
             t_min = tz_min;
@@ -161,7 +166,11 @@ impl BBox {
         }
 
         // tmin < t1 && tmax > t0
-        t_min < ::std::f64::INFINITY && t_max > 0.0
+        if t_min < ::std::f64::INFINITY && t_max > 0.0 {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
     }
 
     pub fn overlaps(&self, other: &BBox) -> bool {
@@ -229,6 +238,15 @@ impl BBox {
         }
     }
 
+    pub fn centroid(&self) -> Vec3 {
+        self.lerp(0.5, 0.5, 0.5)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let diag = self.max - self.min;
+        2.0 * (diag.x * diag.y + diag.y * diag.z + diag.z * diag.x)
+    }
+
     pub fn x_len(&self) -> f64 {
         self.max.x - self.min.x
     }
@@ -270,6 +288,20 @@ fn it_intersects_with_a_ray() {
     assert_eq!(false, bbox.intersects(&non_intersecting_ray));
 }
 
+#[test]
+fn it_returns_the_intersection_range_of_a_ray() {
+    let bbox = BBox {
+        min: Vec3::zero(),
+        max: Vec3::one()
+    };
+
+    let ray = Ray::new(Vec3 { x: 0.5, y: 3.0, z: 0.5 }, Vec3 { x: 0.0, y: -1.0, z: 0.0 });
+    assert_eq!(Some((2.0, 3.0)), bbox.intersection_range(&ray));
+
+    let away = Ray::new(Vec3 { x: 0.5, y: 3.0, z: 0.5 }, Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+    assert_eq!(None, bbox.intersection_range(&away));
+}
+
 #[test]
 fn it_unions_a_bbox_with_a_point() {
     let original_bbox = BBox {
@@ -434,6 +466,17 @@ fn it_returns_offset_length_from_min_corner() {
     assert_eq!(Vec3::one(), offset_point);
 }
 
+#[test]
+fn it_returns_centroid_and_surface_area() {
+    let bbox = BBox {
+        min: Vec3::zero(),
+        max: Vec3 { x: 1.0, y: 2.0, z: 3.0 }
+    };
+
+    assert_eq!(Vec3 { x: 0.5, y: 1.0, z: 1.5 }, bbox.centroid());
+    assert_eq!(22.0, bbox.surface_area());
+}
+
 #[test]
 fn it_returns_side_lengths() {
     let bbox = BBox {
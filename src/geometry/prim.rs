@@ -1,16 +1,167 @@
+use std::f64::consts::PI;
 use geometry::{BBox, PartialBoundingBox};
+use material::Material;
 use raytracer::{Ray, Intersection};
 use mat4::Transform;
+use vec3::Vec3;
 
 pub trait Prim: PartialBoundingBox {
     fn intersects<'a>(&'a self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Intersection<'a>>;
     
     // fn transform(&self, transform: &Transform) -> Box<Prim+Send+Sync>;
     fn mut_transform(&mut self, transform: &Transform);
+
+    /// Surface and emitted radiance of a prim with an emissive material, so it
+    /// can be sampled as a light
+    fn emitter(&self) -> Option<(Emitter, Vec3)> {
+        None
+    }
 }
 
 impl<'a> PartialBoundingBox for Box<Prim+Send+Sync> {
     fn partial_bounding_box(&self) -> Option<BBox> {
         (**self).partial_bounding_box()
     }
-}
\ No newline at end of file
+}
+
+/// Surface of an emissive prim, copied out of it for lights to sample
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emitter {
+    /// Both faces emit
+    Triangle([Vec3; 3]),
+    /// Only the outside emits
+    Sphere { center: Vec3, radius: f64 },
+    /// Points where normal · p + d = 0, `normal` being a unit vector. Both faces emit.
+    Plane { normal: Vec3, d: f64 }
+}
+
+impl Emitter {
+    /// `self` with the radiance `material` gives off at (u, v), taken for the
+    /// whole surface, if it gives off any
+    pub fn emitting(self, material: &Material, u: f64, v: f64) -> Option<(Emitter, Vec3)> {
+        let emission = material.emission(u, v);
+        if emission.x > 0.0 || emission.y > 0.0 || emission.z > 0.0 {
+            Some((self, emission))
+        } else {
+            None
+        }
+    }
+
+    /// Surface area, infinite for planes
+    pub fn area(&self) -> f64 {
+        match *self {
+            Emitter::Triangle(ref vertices) => {
+                (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0])).len() * 0.5
+            },
+            Emitter::Sphere { radius, .. } => 4.0 * PI * radius * radius,
+            Emitter::Plane { .. } => ::std::f64::INFINITY
+        }
+    }
+
+    /// Point on the surface to light `from` with for (u1, u2) in [0, 1)², and
+    /// the probability density of the direction towards it per unit solid
+    /// angle at `from`. None if no emitting part of the surface faces `from`.
+    pub fn sample(&self, from: &Vec3, u1: f64, u2: f64) -> Option<(Vec3, f64)> {
+        match *self {
+            Emitter::Triangle(ref vertices) => {
+                // Uniformly by area
+                let root = u1.sqrt();
+                let (b0, b1) = (1.0 - root, u2 * root);
+                let position = vertices[0].scale(b0) + vertices[1].scale(b1) + vertices[2].scale(1.0 - b0 - b1);
+                let normal = (vertices[1] - vertices[0]).cross(&(vertices[2] - vertices[0])).unit();
+
+                let to_light = position - *from;
+                let distance_squared = to_light.dot(&to_light);
+                let cos_light = normal.dot(&to_light).abs() / distance_squared.sqrt();
+                if !(cos_light > 0.0) {
+                    return None;
+                }
+
+                // Area pdf 1 / area becomes distance² / (cos_light * area) per solid angle
+                Some((position, distance_squared / (cos_light * self.area())))
+            },
+            Emitter::Sphere { center, radius } => {
+                // Uniformly over the cone of directions the sphere covers
+                let to_center = center - *from;
+                let distance_squared = to_center.dot(&to_center);
+                // Points on the sphere itself, up to rounding, see none of it
+                let radius_squared = radius * radius;
+                if distance_squared <= radius_squared * (1.0 + 1e-9) {
+                    return None;
+                }
+
+                // 1 - cos of the cone's half angle, without cancellation for distant spheres
+                let sin_squared_max = radius_squared / distance_squared;
+                let one_minus_cos_max = sin_squared_max / (1.0 + (1.0 - sin_squared_max).sqrt());
+
+                let cos_theta = 1.0 - u1 * one_minus_cos_max;
+                let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+                let phi = 2.0 * PI * u2;
+
+                let distance = distance_squared.sqrt();
+                let w = to_center.scale(1.0 / distance);
+                let (t, b) = w.orthonormal_basis();
+                let direction = t.scale(sin_theta * phi.cos()) + b.scale(sin_theta * phi.sin()) + w.scale(cos_theta);
+
+                // Nearest point on the sphere along the sampled direction
+                let along = distance * cos_theta -
+                    (radius_squared - distance_squared * sin_theta * sin_theta).max(0.0).sqrt();
+
+                Some((*from + direction.scale(along), 1.0 / (2.0 * PI * one_minus_cos_max)))
+            },
+            Emitter::Plane { normal, d } => {
+                // Cosine-weighted about the direction straight at the plane,
+                // every direction of which reaches it
+                let height = normal.dot(from) + d;
+                let towards = if height > 0.0 { -normal } else { normal };
+                let direction = Vec3::cosine_hemisphere(&towards, u1, u2);
+                let cos_theta = direction.dot(&towards);
+                if height == 0.0 || cos_theta <= 0.0 {
+                    return None;
+                }
+
+                Some((*from + direction.scale(height.abs() / cos_theta), cos_theta / PI))
+            }
+        }
+    }
+}
+
+#[test]
+fn it_samples_emitters_on_their_surface() {
+    let from = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
+    let triangle = Emitter::Triangle([
+        Vec3 { x: 0.0, y: 10.0, z: 0.0 },
+        Vec3 { x: 1.0, y: 10.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 10.0, z: 1.0 }]);
+    let sphere = Emitter::Sphere { center: Vec3 { x: 0.0, y: 0.0, z: 5.0 }, radius: 1.0 };
+    let plane = Emitter::Plane { normal: Vec3 { x: 0.0, y: 1.0, z: 0.0 }, d: 3.0 };
+
+    // Dividing by the pdf adds up the solid angle each covers, and for the
+    // plane, cos-weighted, the irradiance it gives off at unit radiance
+    let (mut triangle_angle, mut sphere_angle, mut plane_irradiance) = (0.0, 0.0, 0.0);
+    for i in 0..32 {
+        for j in 0..32 {
+            let (u1, u2) = ((i as f64 + 0.5) / 32.0, (j as f64 + 0.5) / 32.0);
+
+            let (position, pdf) = triangle.sample(&from, u1, u2).unwrap();
+            assert!((position.y - 10.0).abs() < 1e-12 && position.x + position.z <= 1.0 + 1e-12);
+            triangle_angle += 1.0 / pdf / 1024.0;
+
+            let (position, pdf) = sphere.sample(&from, u1, u2).unwrap();
+            assert!(((position - Vec3 { x: 0.0, y: 0.0, z: 5.0 }).len() - 1.0).abs() < 1e-9);
+            sphere_angle += 1.0 / pdf / 1024.0;
+
+            let (position, pdf) = plane.sample(&from, u1, u2).unwrap();
+            assert!((position.y + 3.0).abs() < 1e-9);
+            plane_irradiance += -position.unit().y / pdf / 1024.0;
+        }
+    }
+
+    assert!((sphere_angle - 2.0 * PI * (1.0 - (24.0f64 / 25.0).sqrt())).abs() < 1e-9);
+    assert!((plane_irradiance - PI).abs() < 1e-9);
+    // The triangle is small and distant, so about its area over its distance²
+    assert!((triangle_angle - 0.5 / 100.0).abs() < 1e-4, "triangle covers {}", triangle_angle);
+
+    // Spheres emit outwards only
+    assert!(sphere.sample(&Vec3 { x: 0.0, y: 0.0, z: 5.5 }, 0.5, 0.5).is_none());
+}
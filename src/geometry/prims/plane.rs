@@ -1,4 +1,5 @@
 use geometry::{BBox, PartialBoundingBox, Prim};
+use geometry::prim::Emitter;
 use material::Material;
 use mat4::{Mat4, Transform};
 use raytracer::{Ray, Intersection};
@@ -70,6 +71,12 @@ impl Prim for Plane {
 
         self.d = self.d + trans.len() * add_sub;
     }
+
+    fn emitter(&self) -> Option<(Emitter, Vec3)> {
+        let normal = Vec3 { x: self.a, y: self.b, z: self.c };
+        let length = normal.len();
+        Emitter::Plane { normal: normal.scale(1.0 / length), d: self.d / length }.emitting(&*self.material, 0.5, 0.5)
+    }
 }
 
 #[test]
@@ -1,5 +1,5 @@
 use geometry::bbox::{BBox, PartialBoundingBox};
-use geometry::prim::Prim;
+use geometry::prim::{Emitter, Prim};
 use material::Material;
 use mat4::{Mat4, Transform};
 use raytracer::{Ray, Intersection};
@@ -48,10 +48,16 @@ impl Prim for Sphere {
             let t1 = (-b + disc_sqrt) / 2.0 * a;
             let t2 = (-b - disc_sqrt) / 2.0 * a;
 
-            if t1 >= t_min && t1 <= t_max ||
-               t2 >= t_min && t2 <= t_max {
-                // Valid intersection(s): get nearer intersection
-                let t = if t1.abs() < t2.abs() { t1 } else { t2 };
+            // Nearer valid intersection, the far one when the ray starts inside
+            let t = if t2 >= t_min && t2 <= t_max {
+                Some(t2)
+            } else if t1 >= t_min && t1 <= t_max {
+                Some(t1)
+            } else {
+                None
+            };
+
+            if let Some(t) = t {
                 let intersection_point = ray.origin + ray.direction.scale(t);
                 let n = (intersection_point - self.center).unit();
 
@@ -84,6 +90,10 @@ impl Prim for Sphere {
         self.center = new_center;
         self.radius = new_radius;
     }
+
+    fn emitter(&self) -> Option<(Emitter, Vec3)> {
+        Emitter::Sphere { center: self.center, radius: self.radius }.emitting(&*self.material, 0.5, 0.5)
+    }
 }
 
 #[test]
@@ -135,4 +145,9 @@ fn it_intersects_only_in_tmin_tmax() {
     // Tests tmax
     non_intersection = sphere.intersects(&intersecting_ray, 0.0, 0.0001);
     assert!(non_intersection.is_none());
+
+    // From inside, the far side is the only intersection ahead
+    let inside_ray = Ray::new(Vec3 { x: 0.0, y: 0.0, z: -0.999 }, Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+    let intersection = sphere.intersects(&inside_ray, 0.0, 10.0).unwrap();
+    assert!((intersection.position.z - 1.0).abs() < 1e-12);
 }
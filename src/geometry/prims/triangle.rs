@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use geometry::bbox::{union_point, union_points, BBox, PartialBoundingBox};
-use geometry::prim::Prim;
+use geometry::prim::{Emitter, Prim};
 use material::Material;
 use mat4::{Mat4, Transform};
 use raytracer::{Ray, Intersection};
@@ -172,6 +172,13 @@ impl Prim for Triangle {
         self.normals[1] = n1_t;
         self.normals[2] = n2_t;
     }
+
+    /// Emission is taken at the middle of the texture coordinates
+    fn emitter(&self) -> Option<(Emitter, Vec3)> {
+        let u = (self.texinfo[0].u + self.texinfo[1].u + self.texinfo[2].u) / 3.0;
+        let v = (self.texinfo[0].v + self.texinfo[1].v + self.texinfo[2].v) / 3.0;
+        Emitter::Triangle(self.vertices).emitting(&*self.material, u, v)
+    }
 }
 
 #[test]
@@ -1,8 +1,65 @@
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
 use vec3::Vec3;
 
+/// Stand-in distance for lights at infinity, shadow rays are cast this far
+pub static DISTANT: f64 = 1.0e9;
+
+/// A point on a light picked to light a shaded point with
+pub struct LightSample {
+    pub position: Vec3,
+    /// Light arriving from `position`, divided by the probability density of
+    /// picking it. Multiplied with `Material::eval` to shade with.
+    pub color: Vec3,
+}
+
 pub trait Light {
-    fn position(&self) -> Vec3;
-    fn color(&self) -> Vec3;
-    fn center(&self) -> Vec3;
+    /// Picks a point on the light as seen from `from`
+    fn sample(&self, from: &Vec3, sampler: &mut Sampler) -> LightSample;
+
+    /// Distance along `ray` to the light's surface, if it has one, and the
+    /// radiance it emits back along the ray
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Vec3)>;
+
+    /// Point lights need a single shadow sample
     fn is_point(&self) -> bool;
 }
+
+/// Weight of light with `intensity` from `position`: the irradiance at `from`
+pub fn inverse_square(from: &Vec3, position: &Vec3, intensity: Vec3) -> Vec3 {
+    let to_light = *position - *from;
+    intensity.scale(1.0 / to_light.dot(&to_light))
+}
+
+/// Sample weight for a point picked uniformly on a flat light of the given
+/// `area`, facing along `normal`. Only the front face emits.
+pub fn area_sample(from: &Vec3, position: Vec3, normal: &Vec3, area: f64, radiance: Vec3) -> LightSample {
+    let to_light = position - *from;
+    let distance_squared = to_light.dot(&to_light);
+    let cos_light = -normal.dot(&to_light) / distance_squared.sqrt();
+
+    // Area pdf 1 / area becomes distance² / (cos_light * area) per solid angle
+    let color = if cos_light > 0.0 {
+        radiance.scale(cos_light * area / distance_squared)
+    } else {
+        Vec3::zero()
+    };
+
+    LightSample { position: position, color: color }
+}
+
+/// Distance along `ray` to the front face of the plane through `point` facing
+/// along `normal`, and where it hits
+pub fn intersect_plane(ray: &Ray, point: &Vec3, normal: &Vec3) -> Option<(f64, Vec3)> {
+    let denominator = normal.dot(&ray.direction);
+    if denominator >= 0.0 {
+        return None;
+    }
+
+    let t = normal.dot(&(*point - ray.origin)) / denominator;
+    if t > 0.0 {
+        Some((t, ray.origin + ray.direction.scale(t)))
+    } else {
+        None
+    }
+}
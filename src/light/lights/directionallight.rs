@@ -0,0 +1,79 @@
+use std::f64::consts::PI;
+use light::light::{Light, LightSample, DISTANT};
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Light from far away, like the sun, travelling along the unit `direction`.
+/// `irradiance` is what it gives a surface facing it. A nonzero
+/// `angular_diameter` (radians) spreads it over a disk in the sky for soft
+/// shadows and makes that disk visible.
+#[allow(dead_code)]
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub irradiance: Vec3,
+    pub angular_diameter: f64
+}
+
+impl DirectionalLight {
+    fn one_minus_cos_max(&self) -> f64 {
+        let sin_half = (self.angular_diameter * 0.25).sin();
+        2.0 * sin_half * sin_half
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, from: &Vec3, sampler: &mut Sampler) -> LightSample {
+        let w = -self.direction;
+        let direction = if self.angular_diameter > 0.0 {
+            // Uniformly over the cone of the sun's disk
+            let (u1, u2) = sampler.next_2d();
+            let cos_theta = 1.0 - u1 * self.one_minus_cos_max();
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * u2;
+            let (t, b) = w.orthonormal_basis();
+            t.scale(sin_theta * phi.cos()) + b.scale(sin_theta * phi.sin()) + w.scale(cos_theta)
+        } else {
+            w
+        };
+
+        LightSample { position: *from + direction.scale(DISTANT), color: self.irradiance }
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Vec3)> {
+        if self.angular_diameter <= 0.0 {
+            return None;
+        }
+
+        let one_minus_cos_max = self.one_minus_cos_max();
+        if -ray.direction.unit().dot(&self.direction) >= 1.0 - one_minus_cos_max {
+            Some((DISTANT, self.irradiance.scale(1.0 / (2.0 * PI * one_minus_cos_max))))
+        } else {
+            None
+        }
+    }
+
+    fn is_point(&self) -> bool {
+        self.angular_diameter <= 0.0
+    }
+}
+
+#[test]
+fn it_shines_from_the_sun_disk() {
+    use raytracer::sampler::SamplerKind;
+
+    let down = Vec3 { x: 0.0, y: -1.0, z: 0.0 };
+    let sun = DirectionalLight { direction: down, irradiance: Vec3::one(), angular_diameter: 0.1 };
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+
+    for index in 0..64 {
+        sampler.start_pixel_sample(0, 0, index);
+        let sample = sun.sample(&Vec3::zero(), &mut *sampler);
+        assert_eq!(1.0, sample.color.x);
+        assert!(sample.position.unit().y >= (0.05f64).cos() - 1e-12);
+    }
+
+    assert!(sun.intersect(&Ray::new(Vec3::zero(), -down)).is_some());
+    assert!(sun.intersect(&Ray::new(Vec3::zero(), Vec3 { x: 0.2, y: 1.0, z: 0.0 })).is_none());
+    assert!(!sun.is_point());
+}
@@ -0,0 +1,61 @@
+use std::f64::consts::PI;
+use light::light::{area_sample, intersect_plane, Light, LightSample};
+use raytracer::Ray;
+use raytracer::sampler::{concentric_disk, Sampler};
+use vec3::Vec3;
+
+/// Disk emitting `color` (radiance) from the side its unit `normal` points to
+#[allow(dead_code)]
+pub struct DiskLight {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub color: Vec3,
+    pub radius: f64
+}
+
+impl Light for DiskLight {
+    fn sample(&self, from: &Vec3, sampler: &mut Sampler) -> LightSample {
+        let (u1, u2) = sampler.next_2d();
+        let (x, y) = concentric_disk(u1, u2);
+        let (t, b) = self.normal.orthonormal_basis();
+        let position = self.position + t.scale(x * self.radius) + b.scale(y * self.radius);
+
+        area_sample(from, position, &self.normal, PI * self.radius * self.radius, self.color)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Vec3)> {
+        match intersect_plane(ray, &self.position, &self.normal) {
+            Some((t, position)) if (position - self.position).len() <= self.radius => Some((t, self.color)),
+            _ => None
+        }
+    }
+
+    fn is_point(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn it_samples_points_on_the_disk() {
+    use raytracer::sampler::SamplerKind;
+
+    let light = DiskLight {
+        position: Vec3 { x: 0.0, y: 0.0, z: 5.0 },
+        normal: Vec3 { x: 0.0, y: 0.0, z: -1.0 },
+        color: Vec3::one(),
+        radius: 0.5
+    };
+    let mut sampler = SamplerKind::Sobol.build(4, 0);
+
+    for index in 0..64 {
+        sampler.start_pixel_sample(0, 0, index);
+        let sample = light.sample(&Vec3::zero(), &mut *sampler);
+        assert!((sample.position.z - 5.0).abs() < 1e-12);
+        assert!((sample.position - light.position).len() <= 0.5 + 1e-12);
+        assert!(sample.color.x > 0.0);
+    }
+
+    let forward = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    assert_eq!(Some(5.0), light.intersect(&Ray::new(Vec3::zero(), forward)).map(|(t, _)| t));
+    assert!(light.intersect(&Ray::new(Vec3 { x: 0.6, y: 0.0, z: 0.0 }, forward)).is_none());
+}
@@ -0,0 +1,163 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use light::light::{Light, LightSample, DISTANT};
+use material::Environment;
+use raytracer::Ray;
+use raytracer::compositor::accumulator::luminance;
+use raytracer::sampler::{cdf, pick, Sampler};
+use vec3::Vec3;
+
+/// Light from the scene's environment. Directions are picked in proportion to
+/// the environment's luminance, tabulated on a latitude-longitude grid.
+pub struct EnvironmentLight {
+    environment: Arc<Environment+Send+Sync>,
+    width: usize,
+    height: usize,
+    // Luminance times sin(theta) per cell, normalised to average 1
+    density: Vec<f64>,
+    // Running sums over the rows, and over the cells of each row
+    row_cdf: Vec<f64>,
+    column_cdfs: Vec<Vec<f64>>
+}
+
+/// Direction for grid coordinates (u, v) in [0, 1], the same mapping as EquirectMap
+fn direction(u: f64, v: f64) -> Vec3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let theta = v * PI;
+    Vec3 { x: theta.sin() * phi.sin(), y: theta.cos(), z: -theta.sin() * phi.cos() }
+}
+
+impl EnvironmentLight {
+    /// Tabulates `environment` at `width` by `height` cells
+    pub fn new(environment: Arc<Environment+Send+Sync>, width: usize, height: usize) -> EnvironmentLight {
+        let mut density = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let v = (y as f64 + 0.5) / height as f64;
+            let sin_theta = (v * PI).sin();
+            for x in 0..width {
+                let u = (x as f64 + 0.5) / width as f64;
+                // Texels that are not finite would poison the CDFs, they are left to BSDF sampling
+                let color = environment.color(direction(u, v));
+                let value = luminance(color.x, color.y, color.z) * sin_theta;
+                density.push(if value.is_finite() { value.max(0.0) } else { 0.0 });
+            }
+        }
+
+        let mean = density.iter().fold(0.0, |sum, value| sum + value) / density.len() as f64;
+        for value in density.iter_mut() {
+            *value = if mean > 0.0 { *value / mean } else { 1.0 };
+        }
+
+        let column_cdfs: Vec<Vec<f64>> = density.chunks(width).map(cdf).collect();
+        let row_sums: Vec<f64> = density.chunks(width).map(|row| row.iter().fold(0.0, |sum, value| sum + value)).collect();
+
+        EnvironmentLight {
+            environment: environment,
+            width: width,
+            height: height,
+            density: density,
+            row_cdf: cdf(&row_sums),
+            column_cdfs: column_cdfs
+        }
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn sample(&self, from: &Vec3, sampler: &mut Sampler) -> LightSample {
+        let (u1, u2) = sampler.next_2d();
+        let (y, v_offset) = pick(&self.row_cdf, u1);
+        let (x, u_offset) = pick(&self.column_cdfs[y], u2);
+
+        let u = (x as f64 + u_offset) / self.width as f64;
+        let v = (y as f64 + v_offset) / self.height as f64;
+        let dir = direction(u, v);
+
+        // The grid's pdf is density per unit area of (u, v), which spans
+        // 2 PI² sin(theta) steradians
+        let sin_theta = (v * PI).sin();
+        let pdf = self.density[y * self.width + x] / (2.0 * PI * PI * sin_theta);
+
+        let color = if pdf > 0.0 && sin_theta > 0.0 {
+            self.environment.color(dir).scale(1.0 / pdf)
+        } else {
+            Vec3::zero()
+        };
+
+        LightSample { position: *from + dir.scale(DISTANT), color: color }
+    }
+
+    /// Every ray that gets away sees the environment
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Vec3)> {
+        Some((DISTANT, self.environment.color(ray.direction)))
+    }
+
+    fn is_point(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+struct HalfSky;
+
+#[cfg(test)]
+impl Environment for HalfSky {
+    /// Bright towards +x, dim elsewhere
+    fn color(&self, dir: Vec3) -> Vec3 {
+        if dir.x > 0.0 { Vec3::one().scale(3.0) } else { Vec3::one() }
+    }
+}
+
+#[test]
+fn it_importance_samples_the_environment() {
+    use raytracer::sampler::SamplerKind;
+
+    let light = EnvironmentLight::new(Arc::new(HalfSky), 64, 32);
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+
+    // A surface facing up gets an irradiance of 3 PI / 2 from the bright half
+    // and PI / 2 from the dim one. Three quarters of the samples should come
+    // from the bright half.
+    let up = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+    let (mut total, mut bright) = (0.0, 0);
+    for index in 0..4096 {
+        sampler.start_pixel_sample(0, 0, index);
+        let sample = light.sample(&Vec3::zero(), &mut *sampler);
+        let l = sample.position.unit();
+        total += sample.color.x * l.dot(&up).max(0.0);
+        if l.x > 0.0 { bright += 1; }
+    }
+
+    assert!((total / 4096.0 - 2.0 * PI).abs() < 0.05 * PI);
+    assert!(bright > 2950 && bright < 3200);
+}
+
+#[cfg(test)]
+struct BrokenSky;
+
+#[cfg(test)]
+impl Environment for BrokenSky {
+    /// Infinite towards +x, not a number towards +y, dim elsewhere
+    fn color(&self, dir: Vec3) -> Vec3 {
+        if dir.x > 0.5 {
+            Vec3::one().scale(::std::f64::INFINITY)
+        } else if dir.y > 0.5 {
+            Vec3::one().scale(::std::f64::NAN)
+        } else {
+            Vec3::one()
+        }
+    }
+}
+
+#[test]
+fn it_samples_around_texels_that_are_not_finite() {
+    use raytracer::sampler::SamplerKind;
+
+    let light = EnvironmentLight::new(Arc::new(BrokenSky), 64, 32);
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+    for index in 0..1024 {
+        sampler.start_pixel_sample(0, 0, index);
+        // Within a cell of the edge of the broken parts
+        let l = light.sample(&Vec3::zero(), &mut *sampler).position.unit();
+        assert!(l.x < 0.65 && l.y < 0.65, "{:?}", l);
+    }
+}
@@ -1,26 +1,45 @@
-use light::light::Light;
+use std::f64::consts::PI;
+use light::light::{inverse_square, Light, LightSample};
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
 use vec3::Vec3;
 
+/// Light from a single point, `intensity` per steradian in every direction.
+/// The light reaching a point falls off with the square of the distance.
 #[allow(dead_code)]
 pub struct PointLight {
     pub position: Vec3,
-    pub color: Vec3
+    pub intensity: Vec3
 }
 
-impl Light for PointLight {
-    fn position(&self) -> Vec3 {
-        self.position
+impl PointLight {
+    /// A point light giving off `power` in total
+    pub fn from_power(position: Vec3, power: Vec3) -> PointLight {
+        PointLight { position: position, intensity: power.scale(1.0 / (4.0 * PI)) }
     }
+}
 
-    fn color(&self) -> Vec3 {
-        self.color
+impl Light for PointLight {
+    fn sample(&self, from: &Vec3, _: &mut Sampler) -> LightSample {
+        LightSample { position: self.position, color: inverse_square(from, &self.position, self.intensity) }
     }
 
-    fn center(&self) -> Vec3 {
-        self.position
+    fn intersect(&self, _: &Ray) -> Option<(f64, Vec3)> {
+        None
     }
 
     fn is_point(&self) -> bool {
         true
     }
 }
+
+#[test]
+fn it_falls_off_with_distance() {
+    use raytracer::sampler::SamplerKind;
+
+    let light = PointLight::from_power(Vec3 { x: 0.0, y: 4.0, z: 0.0 }, Vec3::one().scale(4.0 * PI));
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+
+    assert_eq!(1.0 / 16.0, light.sample(&Vec3::zero(), &mut *sampler).color.x);
+    assert_eq!(1.0 / 64.0, light.sample(&Vec3 { x: 0.0, y: -4.0, z: 0.0 }, &mut *sampler).color.x);
+}
@@ -0,0 +1,122 @@
+use geometry::Prim;
+use geometry::prim::Emitter;
+use light::light::{Light, LightSample};
+use raytracer::Ray;
+use raytracer::sampler::{cdf, pick, Sampler};
+use vec3::Vec3;
+
+/// Emissive prims of a scene as one light. Prims are picked in proportion to
+/// their power (area times mean emitted radiance), then a point on the picked
+/// one, see `Emitter::sample`.
+pub struct PrimLight {
+    emitters: Vec<(Emitter, Vec3)>,
+    cdf: Vec<f64>
+}
+
+fn mean(v: &Vec3) -> f64 {
+    (v.x + v.y + v.z) / 3.0
+}
+
+impl PrimLight {
+    /// Lights for the emissive prims among `prims`: one for all those of
+    /// finite area, and one for each plane, whose power is infinite
+    pub fn from_prims(prims: &[Box<Prim+Send+Sync>]) -> Vec<PrimLight> {
+        let mut lights = Vec::new();
+        let mut bounded = Vec::new();
+
+        for (emitter, emission) in prims.iter().filter_map(|prim| prim.emitter()) {
+            let area = emitter.area();
+            if area.is_infinite() {
+                lights.push(PrimLight { emitters: vec![(emitter, emission)], cdf: vec![1.0] });
+            } else if area > 0.0 {
+                bounded.push((emitter, emission));
+            }
+        }
+
+        if !bounded.is_empty() {
+            let powers: Vec<f64> = bounded.iter().map(|&(ref emitter, ref emission)| {
+                emitter.area() * mean(emission)
+            }).collect();
+            lights.push(PrimLight { cdf: cdf(&powers), emitters: bounded });
+        }
+
+        lights
+    }
+}
+
+impl Light for PrimLight {
+    fn sample(&self, from: &Vec3, sampler: &mut Sampler) -> LightSample {
+        let (index, _) = pick(&self.cdf, sampler.next_1d());
+        let (ref emitter, emission) = self.emitters[index];
+        let probability = self.cdf[index] - if index == 0 { 0.0 } else { self.cdf[index - 1] };
+
+        let (u1, u2) = sampler.next_2d();
+        match emitter.sample(from, u1, u2) {
+            Some((position, pdf)) => LightSample { position: position, color: emission.scale(1.0 / (probability * pdf)) },
+            None => LightSample { position: *from, color: Vec3::zero() }
+        }
+    }
+
+    /// The emitters are prims, rays pick up their emission when they hit them
+    fn intersect(&self, _: &Ray) -> Option<(f64, Vec3)> {
+        None
+    }
+
+    fn is_point(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn it_samples_prims_by_power() {
+    use geometry::prims::{Plane, Sphere, TriangleOptions};
+    use material::materials::CookTorranceMaterial;
+    use raytracer::sampler::SamplerKind;
+
+    let emissive = |emission: Vec3| {
+        Box::new(CookTorranceMaterial { emission: emission, ..Default::default() })
+    };
+    let triangle = |x: f64, emission: Vec3| -> Box<Prim+Send+Sync> {
+        let mut triopts = TriangleOptions::new(
+            Vec3 { x: x, y: 4.0, z: 0.0 },
+            Vec3 { x: x + 1.0, y: 4.0, z: 0.0 },
+            Vec3 { x: x, y: 4.0, z: 1.0 });
+        triopts.material(emissive(emission));
+        Box::new(triopts.build())
+    };
+
+    // The left triangle gives off three times the power of the right one
+    let prims = vec![
+        triangle(-1.0, Vec3 { x: 3.0, y: 3.0, z: 3.0 }),
+        triangle(5.0, Vec3::one()),
+        triangle(10.0, Vec3::zero())
+    ];
+    let light = PrimLight::from_prims(&prims).pop().unwrap();
+    assert_eq!(2, light.emitters.len());
+
+    let mut sampler = SamplerKind::Stratified.build(16, 0);
+    let mut left = 0;
+    for index in 0..256 {
+        sampler.start_pixel_sample(0, 0, index);
+        let sample = light.sample(&Vec3::zero(), &mut *sampler);
+        assert_eq!(4.0, sample.position.y);
+        assert!(sample.color.x > 0.0);
+        if sample.position.x < 1.0 { left += 1; }
+    }
+    assert_eq!(192, left);
+
+    // Both faces emit
+    sampler.start_pixel_sample(0, 0, 0);
+    assert!(light.sample(&Vec3 { x: 0.0, y: 8.0, z: 0.0 }, &mut *sampler).color.x > 0.0);
+
+    assert!(PrimLight::from_prims(&prims[2..]).is_empty());
+
+    // Spheres join the triangles, planes get a light of their own
+    let mut prims = prims;
+    prims.push(Box::new(Sphere { center: Vec3 { x: 0.0, y: 10.0, z: 0.0 }, radius: 1.0,
+                                 material: emissive(Vec3::one()) }));
+    prims.push(Box::new(Plane { a: 0.0, b: 2.0, c: 0.0, d: -40.0, material: emissive(Vec3::one()) }));
+    let lights = PrimLight::from_prims(&prims);
+    assert_eq!(vec![1, 3], lights.iter().map(|light| light.emitters.len()).collect::<Vec<_>>());
+    assert_eq!(Emitter::Plane { normal: Vec3 { x: 0.0, y: 1.0, z: 0.0 }, d: -20.0 }, lights[0].emitters[0].0);
+}
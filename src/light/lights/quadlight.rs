@@ -0,0 +1,92 @@
+use light::light::{area_sample, intersect_plane, Light, LightSample};
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Parallelogram spanned by `edge1` and `edge2` from `corner`, emitting `color`
+/// (radiance) from the side edge1 × edge2 points to
+#[allow(dead_code)]
+pub struct QuadLight {
+    pub corner: Vec3,
+    pub edge1: Vec3,
+    pub edge2: Vec3,
+    pub color: Vec3
+}
+
+impl QuadLight {
+    fn normal(&self) -> Vec3 {
+        self.edge1.cross(&self.edge2).unit()
+    }
+
+    fn area(&self) -> f64 {
+        self.edge1.cross(&self.edge2).len()
+    }
+}
+
+impl Light for QuadLight {
+    fn sample(&self, from: &Vec3, sampler: &mut Sampler) -> LightSample {
+        let (u1, u2) = sampler.next_2d();
+        let position = self.corner + self.edge1.scale(u1) + self.edge2.scale(u2);
+        area_sample(from, position, &self.normal(), self.area(), self.color)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Vec3)> {
+        let (t, position) = match intersect_plane(ray, &self.corner, &self.normal()) {
+            Some(hit) => hit,
+            None => return None
+        };
+
+        // Coordinates along the edges, from the dual basis of the plane
+        let offset = position - self.corner;
+        let n = self.edge1.cross(&self.edge2);
+        let n_squared = n.dot(&n);
+        let u = offset.cross(&self.edge2).dot(&n) / n_squared;
+        let v = self.edge1.cross(&offset).dot(&n) / n_squared;
+
+        if u >= 0.0 && u <= 1.0 && v >= 0.0 && v <= 1.0 {
+            Some((t, self.color))
+        } else {
+            None
+        }
+    }
+
+    fn is_point(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn it_samples_and_intersects_quads() {
+    use raytracer::sampler::SamplerKind;
+
+    // 2 x 2 facing down, 4 units above the origin
+    let light = QuadLight {
+        corner: Vec3 { x: -1.0, y: 4.0, z: -1.0 },
+        edge1: Vec3 { x: 2.0, y: 0.0, z: 0.0 },
+        edge2: Vec3 { x: 0.0, y: 0.0, z: 2.0 },
+        color: Vec3::one()
+    };
+    let mut sampler = SamplerKind::Stratified.build(16, 0);
+
+    // Estimates the solid angle. For an a x b rectangle centered d
+    // away that is 4 asin(ab / sqrt((a² + 4d²)(b² + 4d²))).
+    let mut total = 0.0;
+    for index in 0..256 {
+        sampler.start_pixel_sample(0, 0, index);
+        let sample = light.sample(&Vec3::zero(), &mut *sampler);
+        assert_eq!(4.0, sample.position.y);
+        total += sample.color.x;
+    }
+    let expected = 4.0 * (4.0 / 68.0f64).asin();
+    assert!((total / 256.0 - expected).abs() < expected * 0.01);
+
+    // Nothing from behind
+    let above = Vec3 { x: 0.0, y: 8.0, z: 0.0 };
+    sampler.start_pixel_sample(0, 0, 0);
+    assert_eq!(0.0, light.sample(&above, &mut *sampler).color.x);
+
+    let up = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+    assert_eq!(Some(4.0), light.intersect(&Ray::new(Vec3::zero(), up)).map(|(t, _)| t));
+    assert!(light.intersect(&Ray::new(Vec3 { x: 1.5, y: 0.0, z: 0.0 }, up)).is_none());
+    assert!(light.intersect(&Ray::new(above, -up)).is_none());
+}
@@ -1,7 +1,11 @@
-use rand::{thread_rng, Rng};
-use light::light::Light;
+use geometry::prim::Emitter;
+use light::light::{Light, LightSample};
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
 use vec3::Vec3;
 
+/// Sphere emitting `color` (radiance) from its surface. Lit points sample the
+/// cone of directions the sphere covers uniformly.
 #[allow(dead_code)]
 pub struct SphereLight {
     pub position: Vec3,
@@ -10,27 +14,55 @@ pub struct SphereLight {
 }
 
 impl Light for SphereLight {
-    fn position(&self) -> Vec3 {
-        let mut rng = thread_rng();
-
-        let jitter = Vec3 {
-            x: self.radius * (rng.gen::<f64>() - 0.5),
-            y: self.radius * (rng.gen::<f64>() - 0.5),
-            z: self.radius * (rng.gen::<f64>() - 0.5)
-        };
-
-        self.position + jitter
+    fn sample(&self, from: &Vec3, sampler: &mut Sampler) -> LightSample {
+        let (u1, u2) = sampler.next_2d();
+        match (Emitter::Sphere { center: self.position, radius: self.radius }).sample(from, u1, u2) {
+            Some((position, pdf)) => LightSample { position: position, color: self.color.scale(1.0 / pdf) },
+            None => LightSample { position: self.position, color: Vec3::zero() }
+        }
     }
 
-    fn color(&self) -> Vec3 {
-        self.color
-    }
+    fn intersect(&self, ray: &Ray) -> Option<(f64, Vec3)> {
+        let offset = ray.origin - self.position;
+        let b = ray.direction.dot(&offset);
+        let c = offset.dot(&offset) - self.radius * self.radius;
+        let discriminant = b * b - c;
 
-    fn center(&self) -> Vec3 {
-        self.position
+        // Rays from inside see the back of the surface, which does not emit
+        if c <= 0.0 || discriminant < 0.0 {
+            return None;
+        }
+
+        let t = -b - discriminant.sqrt();
+        if t > 0.0 { Some((t, self.color)) } else { None }
     }
 
     fn is_point(&self) -> bool {
         false
     }
 }
+
+#[test]
+fn it_samples_the_visible_cap() {
+    use std::f64::consts::PI;
+    use raytracer::sampler::SamplerKind;
+
+    let light = SphereLight { position: Vec3 { x: 0.0, y: 10.0, z: 0.0 }, color: Vec3::one(), radius: 2.0 };
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+    let from = Vec3::zero();
+
+    // Sampled points sit on the half facing `from`, weighted by the solid
+    // angle of the cap over PI
+    let solid_angle = 2.0 * PI * (1.0 - (1.0 - 0.04f64).sqrt());
+    for index in 0..100 {
+        sampler.start_pixel_sample(0, 0, index);
+        let sample = light.sample(&from, &mut *sampler);
+        assert!(((sample.position - light.position).len() - 2.0).abs() < 1e-9);
+        assert!(sample.position.y <= 10.0 - 2.0 * 0.2 + 1e-9);
+        assert!((sample.color.x - solid_angle).abs() < 1e-12);
+    }
+
+    let ray = Ray::new(from, Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+    assert_eq!(Some(8.0), light.intersect(&ray).map(|(t, _)| t));
+    assert!(light.intersect(&Ray::new(from, Vec3 { x: 1.0, y: 0.0, z: 0.0 })).is_none());
+}
@@ -0,0 +1,83 @@
+use std::f64::consts::PI;
+use light::light::{inverse_square, Light, LightSample};
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Point light shining `intensity` along the unit `direction`. It fades out
+/// smoothly from `falloff_angle` to `cone_angle` off the axis (radians).
+#[allow(dead_code)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub intensity: Vec3,
+    pub cone_angle: f64,
+    pub falloff_angle: f64
+}
+
+impl SpotLight {
+    /// A spotlight giving off `power` in total, with the fading edge counted
+    /// as half as bright
+    pub fn from_power(position: Vec3, direction: Vec3, power: Vec3, cone_angle: f64,
+                      falloff_angle: f64) -> SpotLight {
+        let solid_angle = 2.0 * PI * (1.0 - 0.5 * (cone_angle.cos() + falloff_angle.cos()));
+        SpotLight {
+            position: position,
+            direction: direction,
+            intensity: power.scale(1.0 / solid_angle),
+            cone_angle: cone_angle,
+            falloff_angle: falloff_angle
+        }
+    }
+
+    /// Fraction of the intensity shining towards `to`
+    fn falloff(&self, to: &Vec3) -> f64 {
+        let cos_theta = self.direction.dot(&(*to - self.position).unit());
+        let (cos_cone, cos_falloff) = (self.cone_angle.cos(), self.falloff_angle.cos());
+
+        if cos_theta >= cos_falloff {
+            1.0
+        } else if cos_theta <= cos_cone {
+            0.0
+        } else {
+            let t = (cos_theta - cos_cone) / (cos_falloff - cos_cone);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, from: &Vec3, _: &mut Sampler) -> LightSample {
+        let intensity = self.intensity.scale(self.falloff(from));
+        LightSample { position: self.position, color: inverse_square(from, &self.position, intensity) }
+    }
+
+    fn intersect(&self, _: &Ray) -> Option<(f64, Vec3)> {
+        None
+    }
+
+    fn is_point(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn it_lights_inside_the_cone() {
+    use raytracer::sampler::SamplerKind;
+
+    let light = SpotLight {
+        position: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        direction: Vec3 { x: 0.0, y: -1.0, z: 0.0 },
+        intensity: Vec3::one(),
+        cone_angle: PI / 4.0,
+        falloff_angle: PI / 8.0
+    };
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+    let mut color_at = |x: f64| light.sample(&Vec3 { x: x, y: 0.0, z: 0.0 }, &mut *sampler).color.x;
+
+    assert_eq!(1.0, color_at(0.0));
+    let fading = color_at(0.6);
+    assert!(fading > 0.0 && fading < 1.0 / 1.36);
+    assert_eq!(0.0, color_at(1.1));
+    assert_eq!(0.0, color_at(-1.1));
+}
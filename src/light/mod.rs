@@ -2,9 +2,21 @@ pub use self::light::Light;
 pub mod light;
 
 pub mod lights {
+    pub use self::directionallight::DirectionalLight;
+    pub use self::disklight::DiskLight;
+    pub use self::environmentlight::EnvironmentLight;
     pub use self::pointlight::PointLight;
+    pub use self::primlight::PrimLight;
+    pub use self::quadlight::QuadLight;
     pub use self::spherelight::SphereLight;
+    pub use self::spotlight::SpotLight;
 
+    mod directionallight;
+    mod disklight;
+    mod environmentlight;
     mod pointlight;
+    mod primlight;
+    mod quadlight;
     mod spherelight;
+    mod spotlight;
 }
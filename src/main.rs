@@ -8,6 +8,10 @@ extern crate rustc_serialize;
 extern crate threadpool;
 extern crate time;
 
+use raytracer::{AcceleratorKind, Integrator};
+use raytracer::compositor::tonemap::{ToneMapOperator, ToneMapping};
+use raytracer::filter::FilterKind;
+use raytracer::sampler::SamplerKind;
 use scene::{Camera, Scene};
 
 use std::fs::File;
@@ -15,12 +19,15 @@ use std::io::{self, Read, Write};
 use std::env;
 use std::process;
 use std::sync::Arc;
+use rustc_serialize::Decodable;
 use rustc_serialize::json;
 use rustc_serialize::json::DecoderError::MissingFieldError;
 
+mod cli;
 mod geometry;
 mod light;
 mod material;
+mod medium;
 mod my_scene;
 mod raytracer;
 mod scene;
@@ -28,10 +35,8 @@ mod util;
 mod vec3;
 mod mat4;
 
-// Replace this with argparse eventually
-struct ProgramArgs {
-    config_file: String
-}
+// Defaults for anything the configuration file and command line leave out
+static DEFAULT_CONFIG: &'static str = include_str!("../sample-config.json");
 
 #[derive(RustcDecodable, RustcEncodable)]
 struct SceneConfig {
@@ -47,154 +52,100 @@ struct SceneConfig {
     animating: bool,
     fps: f64,
     time_slice: (f64, f64),
-    starting_frame_number: u32
+    starting_frame_number: u32,
+    integrator: Option<Integrator>,
+    accelerator: Option<AcceleratorKind>,
+    exposure: Option<f64>,
+    tone_map: Option<ToneMapOperator>,
+    srgb: Option<bool>,
+    progressive: Option<bool>,
+    max_passes: Option<u32>,
+    time_limit: Option<f64>,
+    noise_threshold: Option<f64>,
+    update_every: Option<u32>,
+    adaptive_threshold: Option<f64>,
+    max_pixel_samples: Option<u32>,
+    sample_map_file: Option<String>,
+    filter: Option<FilterKind>,
+    filter_radius: Option<f64>,
+    sampler: Option<SamplerKind>,
+    seed: Option<u64>
 }
 
-fn parse_args(args: env::Args) -> Result<ProgramArgs, String> {
-    let args = args.collect::<Vec<String>>();
-    if args.len() == 0 {
-        panic!("Args do not even include a program name");
+fn get_camera_and_scene(config: &SceneConfig) -> Result<(Camera, Scene), String> {
+    let scene_name = config.name.clone();
+    let (image_width, image_height) = config.size;
+    let fov = config.fov;
+    let accelerator_kind = config.accelerator.unwrap_or(AcceleratorKind::Octree);
+
+    // Scene files are described in JSON, see sample-scene.json
+    if scene_name.ends_with(".json") {
+        let description = try!(scene::description::from_file(&scene_name));
+        return description.build(image_width, image_height, fov, config.animating, accelerator_kind);
     }
 
-    let program_name = &args[0];
-    match args.len() {
-        // I wouldn't expect this in the wild
-        0 => unreachable!(),
-        1 => Err(format!("Usage: {} scene_config.json", program_name)),
-        2 => Ok(ProgramArgs { config_file: args[1].clone() }),
-        _ => Err(format!("Usage: {} scene_config.json", program_name)),
+    // Built-in presets. Cameras, scenes created in ./my_scene
+    match my_scene::find_preset(&scene_name) {
+        Some(preset) => Ok(preset.build(image_width, image_height, fov, config.animating, accelerator_kind)),
+        None => Err(format!("unknown scene ``{}''", scene_name))
     }
 }
 
-fn get_camera_and_scene(config: &SceneConfig) -> Option<(Camera, Scene)> {
-    let scene_name = config.name.clone();
-    let (image_width, image_height) = config.size;
-    let fov = config.fov;
+fn main() {
+    let start_time = ::time::get_time().sec;
 
-    // Cameras, scenes created in ./my_scene.rs
-    // Scenes with an octree supplied (see my_scene.rs) will use it.
-    // Lower the render quality (especially shadow_samples) for complex scenes
-    return match scene_name.as_ref() {
-        "box" => {
-            // Box. Simplest scene with 9 primitives, no octree
-            let camera = my_scene::cornell::get_camera(image_width, image_height, fov);
-            let scene = my_scene::cornell::get_scene();
-            Some((camera, scene))
-        },
-        "bunny" => {
-            // Bunny. Around 300 primitives, 2 lights. Uses octree. Has skybox, textures are
-            // in another repository.
-            let camera = my_scene::bunny::get_camera(image_width, image_height, fov);
-            let scene = my_scene::bunny::get_scene();
-            Some((camera, scene))
-        },
-        "teapot" => {
-            // Teapot. Around 2500 polygons. Octree helps a bit. Has skybox.
-            let camera = my_scene::teapot::get_teapot_camera(image_width, image_height, fov);
-            let scene = my_scene::teapot::get_teapot_scene();
-            Some((camera, scene))
-        },
-        "cow" => {
-            // Cow. Around 5000 polygons. Octree helps considerably.
-            let camera = my_scene::cow::get_camera(image_width, image_height, fov);
-            let scene = my_scene::cow::get_scene();
-            Some((camera, scene))
-        },
-        "lucy" => {
-            // Lucy. Around 525814+1 primitives. Octree pretty much required. The model is included
-            // separately, in another repository. Has skybox.
-            let camera = my_scene::lucy::get_camera(image_width, image_height, fov);
-            let scene = my_scene::lucy::get_scene();
-            Some((camera, scene))
-        },
-        "sponza" => {
-            // Sponza. Around 28K triangles, but more complex than Lucy. 2 lights.
-            let camera = my_scene::sponza::get_camera(image_width, image_height, fov);
-            let scene = my_scene::sponza::get_scene();
-            Some((camera, scene))
-        },
-        "sibenik" => {
-            // Sibenik, around 70K triangles, no texture work, 3 lights.
-            let camera = match config.animating {
-                true => my_scene::sibenik::get_animation_camera(image_width, image_height, fov),
-                false => my_scene::sibenik::get_camera(image_width, image_height, fov)
-            };
-            let scene = my_scene::sibenik::get_scene();
-            Some((camera, scene))
-        },
-        "heptoroid-white" => {
-            // Heptoroid, 114688 tris, 57302 verts
-            let camera = my_scene::heptoroid::get_camera(image_width, image_height, fov);
-            let scene = my_scene::heptoroid::get_scene("white");
-            Some((camera, scene))
-        },
-        "heptoroid-shiny" => {
-            // Shiny heptoroid, 114688 tris, 57302 verts
-            let camera = my_scene::heptoroid::get_camera(image_width, image_height, fov);
-            let scene = my_scene::heptoroid::get_scene("shiny");
-            Some((camera, scene))
-        },
-        "heptoroid-refractive" => {
-            // Refractive heptoroid, you want to limit your reflect levels (2/3?)
-            // and up your refract levels (10/16?) for this
-            let camera = my_scene::heptoroid::get_camera(image_width, image_height, fov);
-            let scene = my_scene::heptoroid::get_scene("refractive");
-            Some((camera, scene))
-        },
-        "tachikoma" => {
-            // Shiny heptoroid, 114688 tris, 57302 verts
-            // You can forget about refractions, it's too complex a scene
-            let camera = my_scene::tachikoma::get_camera(image_width, image_height, fov);
-            let scene = my_scene::tachikoma::get_scene();
-            Some((camera, scene))
+    let args = env::args().collect::<Vec<String>>();
+    let program_name = args[0].clone();
+
+    let render_args = match cli::parse_args(&args[1..]) {
+        Ok(cli::Command::Render(render_args)) => render_args,
+        Ok(cli::Command::Help) => {
+            print!("{}", cli::usage(&program_name));
+            return;
         },
-        "sphere" => {
-            // Sphere skybox test scene
-            let camera = match config.animating {
-                true => my_scene::sphere::get_animation_camera(image_width, image_height, fov),
-                false => my_scene::sphere::get_camera(image_width, image_height, fov)
-            };
-            let scene = my_scene::sphere::get_scene();
-            Some((camera, scene))
+        Ok(cli::Command::ListScenes) => {
+            for preset in my_scene::PRESETS.iter() {
+                println!("{:<24}{}", preset.name, preset.description);
+            }
+            println!("Scene files can be rendered by passing their .json path as the name.");
+            return;
         },
-        "fresnel" => {
-            // Fresnel test scene
-            let camera = match config.animating {
-                true => my_scene::fresnel::get_animation_camera(image_width, image_height, fov),
-                false => my_scene::fresnel::get_camera(image_width, image_height, fov)
+        Err(error_str) => {
+            write!(&mut io::stderr(), "{}\n\n{}", error_str, cli::usage(&program_name)).unwrap();
+            process::exit(1);
+        }
+    };
+
+    let config_data = match render_args.config_file {
+        Some(ref config_file) => {
+            let mut file_handle = match File::open(config_file) {
+                Ok(file) => file,
+                Err(err) => {
+                    write!(&mut io::stderr(), "{}\n", err).unwrap();
+                    process::exit(1);
+                }
             };
-            let scene = my_scene::fresnel::get_scene();
-            Some((camera, scene))
+
+            let mut json_data = String::new();
+            if let Err(ref err) = file_handle.read_to_string(&mut json_data) {
+                write!(&mut io::stderr(), "{}\n", err).unwrap();
+                process::exit(1);
+            }
+            Some(json_data)
         },
-        _ => None
+        None => None
     };
-}
-
-fn main() {
-    let start_time = ::time::get_time().sec;
 
-    let program_args = match parse_args(env::args()) {
-        Ok(program_args) => program_args,
+    let config_json = match cli::merge_config(DEFAULT_CONFIG, config_data.as_ref().map(|data| &data[..]),
+                                              &render_args.overrides) {
+        Ok(config_json) => config_json,
         Err(error_str) => {
             write!(&mut io::stderr(), "{}\n", error_str).unwrap();
             process::exit(1);
         }
     };
-    let mut file_handle = match File::open(&program_args.config_file) {
-        Ok(file) => file,
-        Err(err) => {
-            write!(&mut io::stderr(), "{}\n", err).unwrap();
-            process::exit(1);
-        }
-    };
-
-    let mut json_data = String::new();
-    if let Err(ref err) = file_handle.read_to_string(&mut json_data) {
-        write!(&mut io::stderr(), "{}\n", err).unwrap();
-        process::exit(1);
-    }
 
-    let config: SceneConfig = match json::decode(&json_data) {
+    let config: SceneConfig = match Decodable::decode(&mut json::Decoder::new(config_json)) {
         Ok(data) => data,
         Err(err) => {
             let msg = match err {
@@ -214,9 +165,9 @@ fn main() {
 
     let scenepair = get_camera_and_scene(&config);
     let (camera, scene) = match scenepair {
-        Some(pair) => pair,
-        None => {
-            write!(&mut io::stderr(), "unknown scene ``{}''\n", config.name).unwrap();
+        Ok(pair) => pair,
+        Err(error_str) => {
+            write!(&mut io::stderr(), "{}\n", error_str).unwrap();
             process::exit(1);
         }
     };
@@ -226,12 +177,32 @@ fn main() {
     let scene_time = ::time::get_time().sec;
     println!("Scene loaded at {} ({}s)...", scene_time, scene_time - start_time);
 
+    // Without a seed in the config every run differs; print the one picked so it can be reproduced
+    let seed = config.seed.unwrap_or_else(|| rand::random());
+    println!("Seed: {}", seed);
+
+    let filter = config.filter.unwrap_or(FilterKind::Box);
     let render_options = raytracer::RenderOptions {
         reflect_depth: config.reflect_depth,
         refract_depth: config.refract_depth,
         shadow_samples: config.shadow_samples,
         gloss_samples: config.gloss_samples,
         pixel_samples: config.pixel_samples,
+        integrator: config.integrator.unwrap_or(Integrator::Whitted),
+        adaptive_threshold: config.adaptive_threshold,
+        max_pixel_samples: config.max_pixel_samples
+            .unwrap_or(config.pixel_samples * config.pixel_samples * 16),
+        filter: filter,
+        filter_radius: config.filter_radius.unwrap_or(filter.default_radius()),
+        sampler: config.sampler.unwrap_or(SamplerKind::Stratified),
+        seed: seed,
+    };
+
+    let default_tone_mapping: ToneMapping = Default::default();
+    let tone_mapping = ToneMapping {
+        exposure: config.exposure.unwrap_or(default_tone_mapping.exposure),
+        operator: config.tone_map.unwrap_or(default_tone_mapping.operator),
+        srgb: config.srgb.unwrap_or(default_tone_mapping.srgb),
     };
 
     let renderer = raytracer::Renderer {
@@ -247,30 +218,77 @@ fn main() {
             animate_from: animate_from,
             animate_to: animate_to,
             starting_frame_number: config.starting_frame_number,
-            renderer: renderer
+            renderer: renderer,
+            tone_mapping: tone_mapping
         };
 
         println!("Animating - tasks: {}, FPS: {}, start: {}s, end:{}s, starting frame: {}",
                  ::num_cpus::get(), animator.fps, animator.animate_from, animator.animate_to,
                  animator.starting_frame_number);
-        animator.animate(camera, shared_scene, &config.output_file);
+        if let Err(error_str) = animator.animate(camera, shared_scene, &config.output_file) {
+            write!(&mut io::stderr(), "{}\n", error_str).unwrap();
+            process::exit(1);
+        }
         let render_time = ::time::get_time().sec;
         println!("Render done at {} ({}s)",
                  render_time, render_time - scene_time);
     } else {
         // Still frame
+        let (stem, extension) = util::export::split_extension(&config.output_file);
+        let out_file = format!("{}.{}", stem, extension);
+
         println!("Rendering with {} tasks...", ::num_cpus::get());
-        let image_data = renderer.render(camera, shared_scene);
+        let image_data = if config.progressive.unwrap_or(false) {
+            let progressive_options = raytracer::ProgressiveOptions {
+                max_passes: config.max_passes,
+                time_limit: config.time_limit,
+                noise_threshold: config.noise_threshold,
+                update_every: config.update_every.unwrap_or(0),
+            };
+
+            if progressive_options.max_passes.is_none() && progressive_options.time_limit.is_none() &&
+               progressive_options.noise_threshold.is_none() {
+                write!(&mut io::stderr(), "progressive rendering needs one of ``max_passes'', \
+                                           ``time_limit'' or ``noise_threshold''\n").unwrap();
+                process::exit(1);
+            }
+
+            if config.sample_map_file.is_some() {
+                write!(&mut io::stderr(), "``sample_map_file'' cannot be written by progressive rendering\n").unwrap();
+                process::exit(1);
+            }
+
+            renderer.render_progressive(camera, shared_scene, progressive_options, |passes, image| {
+                // Intermediate images overwrite each other, the final one replaces them
+                if let Err(error_str) = util::export::save(image, &tone_mapping, &out_file) {
+                    write!(&mut io::stderr(), "\n{}\n", error_str).unwrap();
+                } else {
+                    print!(" (wrote {} passes)", passes);
+                }
+            })
+        } else if let Some(ref sample_map_file) = config.sample_map_file {
+            let (image_data, sample_map) = renderer.render_with_sample_map(camera, shared_scene);
+            if let Err(error_str) = util::export::save_sample_map(&sample_map, render_options.max_pixel_samples,
+                                                                  sample_map_file) {
+                write!(&mut io::stderr(), "{}\n", error_str).unwrap();
+                process::exit(1);
+            }
+            image_data
+        } else {
+            renderer.render(camera, shared_scene)
+        };
         let render_time = ::time::get_time().sec;
         println!("Render done at {} ({}s)...\nWriting file...",
                  render_time, render_time - scene_time);
 
-        let out_file = format!("{}{}", config.output_file, ".ppm");
-        util::export::to_ppm(image_data, &out_file);
+        if let Err(error_str) = util::export::save(&image_data, &tone_mapping, &out_file) {
+            write!(&mut io::stderr(), "{}\n", error_str).unwrap();
+            process::exit(1);
+        }
         let export_time = ::time::get_time().sec;
 
         println!("Write done: {} ({}s). Written to {}\nTotal: {}s",
                  export_time, export_time - render_time,
-                 config.output_file, export_time - start_time);
+                 out_file, export_time - start_time);
     }
 }
@@ -0,0 +1,294 @@
+use std::f64::consts::PI;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// The parts a material's BSDF is made of. Lights are sampled directly for
+/// everything `Material::eval` returns, the diffuse lobe and glossy reflection.
+/// Mirror reflection and transmission are only followed by sampling them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Lobe {
+    Diffuse,
+    Reflection,
+    Transmission
+}
+
+pub static LOBES: [Lobe; 3] = [Lobe::Diffuse, Lobe::Reflection, Lobe::Transmission];
+
+/// A direction light arrives from, picked by `Material::sample`
+pub struct BsdfSample {
+    pub wi: Vec3,
+    /// BSDF times |cos| of `wi`
+    pub value: Vec3,
+    pub pdf: f64,
+    pub lobe: Lobe,
+    /// `wi` was the only direction the lobe could pick (mirrors, clear glass)
+    pub delta: bool
+}
+
+impl BsdfSample {
+    /// What light arriving along `wi` is multiplied with
+    pub fn weight(&self) -> Vec3 {
+        self.value.scale(1.0 / self.pdf)
+    }
+
+    /// `Material::eval` covers `wi`, so lights were sampled for it already
+    pub fn evaluated(&self) -> bool {
+        !self.delta && self.lobe != Lobe::Transmission
+    }
+}
+
+/// `n` turned to the side of `wo`
+pub fn facing(n: &Vec3, wo: &Vec3) -> Vec3 {
+    if n.dot(wo) < 0.0 { -*n } else { *n }
+}
+
+/// Fraction of light reflected off a surface of index `ior`, by the Fresnel
+/// equations for unpolarized light. `n` points out of the surface, `wo` is
+/// inside it when on the other side. 1 under total internal reflection.
+pub fn fresnel(ior: f64, wo: &Vec3, n: &Vec3) -> f64 {
+    let cos_i = n.dot(wo);
+    let (eta, cos_i) = if cos_i < 0.0 { (1.0 / ior, -cos_i) } else { (ior, cos_i) };
+
+    // Snell's law
+    let sin_t_squared = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin_t_squared >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin_t_squared).sqrt();
+    let r_s = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let r_p = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+
+    0.5 * (r_s * r_s + r_p * r_p)
+}
+
+/// Fraction of light reflected off a metal of complex index of refraction
+/// `eta` + i`k`, at `cos_i` to the normal
+pub fn fresnel_conductor(eta: f64, k: f64, cos_i: f64) -> f64 {
+    let cos_squared = cos_i * cos_i;
+    let sin_squared = 1.0 - cos_squared;
+    let t0 = eta * eta - k * k - sin_squared;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+
+    let t1 = a2_plus_b2 + cos_squared;
+    let t2 = 2.0 * cos_i * a;
+    let r_s = (t1 - t2) / (t1 + t2);
+
+    let t3 = cos_squared * a2_plus_b2 + sin_squared * sin_squared;
+    let t4 = t2 * sin_squared;
+    let r_p = r_s * (t3 - t4) / (t3 + t4);
+
+    0.5 * (r_s + r_p)
+}
+
+/// Schlick's approximation of the reflectance of a surface with reflectance
+/// `f0` at normal incidence
+pub fn schlick(f0: &Vec3, cos_theta: f64) -> Vec3 {
+    let c = (1.0 - cos_theta).max(0.0).powi(5);
+    *f0 + (Vec3::one() - *f0).scale(c)
+}
+
+/// Density of cosine-weighted directions on the side of `n` facing `wo`
+pub fn cosine_pdf(n: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+    facing(n, wo).dot(wi).max(0.0) / PI
+}
+
+/// Picks a cosine-weighted direction for the diffuse lobe, whose BSDF times
+/// |cos| is `eval`
+pub fn sample_diffuse<F>(n: &Vec3, wo: &Vec3, sampler: &mut Sampler, eval: F) -> Option<BsdfSample>
+        where F: Fn(&Vec3) -> Vec3 {
+    let (u1, u2) = sampler.next_2d();
+    let wi = Vec3::cosine_hemisphere(&facing(n, wo), u1, u2);
+    let pdf = cosine_pdf(n, wo, &wi);
+    if pdf <= 0.0 {
+        return None;
+    }
+
+    Some(BsdfSample { wi: wi, value: eval(&wi), pdf: pdf, lobe: Lobe::Diffuse, delta: false })
+}
+
+/// GGX (Trowbridge-Reitz) distribution of microfacet normals with roughness
+/// `alpha`, for the cosine between the microfacet and surface normals
+pub fn ggx(cos_h: f64, alpha: f64) -> f64 {
+    if cos_h <= 0.0 {
+        return 0.0;
+    }
+
+    let alpha_squared = alpha * alpha;
+    let d = cos_h * cos_h * (alpha_squared - 1.0) + 1.0;
+    alpha_squared / (PI * d * d)
+}
+
+/// Smith masking of GGX microfacets seen from a direction at `cos_theta` to the normal
+pub fn smith_g1(cos_theta: f64, alpha: f64) -> f64 {
+    let alpha_squared = alpha * alpha;
+    2.0 * cos_theta / (cos_theta + (alpha_squared + (1.0 - alpha_squared) * cos_theta * cos_theta).sqrt())
+}
+
+/// Microfacet normal around `n` picked in proportion to ggx(cos_h) * cos_h
+pub fn sample_ggx(n: &Vec3, alpha: f64, u1: f64, u2: f64) -> Vec3 {
+    let tan_squared = alpha * alpha * u1 / (1.0 - u1).max(1e-12);
+    let cos_theta = 1.0 / (1.0 + tan_squared).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * u2;
+    let (t, b) = n.orthonormal_basis();
+
+    t.scale(sin_theta * phi.cos()) + b.scale(sin_theta * phi.sin()) + n.scale(cos_theta)
+}
+
+/// Mirror reflection of `wo`, or with a nonzero `roughness` reflection off a
+/// GGX microfacet normal. `reflectance` gives the fraction of light reflected
+/// off a (micro)facet normal, which is on the side of `n`.
+pub fn sample_reflection<F>(n: &Vec3, wo: &Vec3, roughness: f64, reflectance: F,
+                            sampler: &mut Sampler) -> Option<BsdfSample>
+        where F: Fn(&Vec3) -> Vec3 {
+    if roughness <= 0.0 {
+        return Some(BsdfSample {
+            wi: Vec3::reflect(wo, &facing(n, wo)),
+            value: reflectance(n),
+            pdf: 1.0,
+            lobe: Lobe::Reflection,
+            delta: true
+        });
+    }
+
+    let (u1, u2) = sampler.next_2d();
+    let wi = Vec3::reflect(wo, &sample_ggx(&facing(n, wo), roughness, u1, u2));
+    let pdf = reflection_pdf(n, wo, &wi, roughness);
+    if pdf <= 0.0 {
+        return None;
+    }
+
+    Some(BsdfSample {
+        wi: wi,
+        value: eval_reflection(n, wo, &wi, roughness, reflectance),
+        pdf: pdf,
+        lobe: Lobe::Reflection,
+        delta: false
+    })
+}
+
+/// Glossy reflection BSDF times |cos| of `wi`, for `sample_reflection` with
+/// the same arguments. 0 for mirrors.
+pub fn eval_reflection<F>(n: &Vec3, wo: &Vec3, wi: &Vec3, roughness: f64, reflectance: F) -> Vec3
+        where F: Fn(&Vec3) -> Vec3 {
+    let facing_n = facing(n, wo);
+    let n_dot_v = facing_n.dot(wo);
+    let n_dot_l = facing_n.dot(wi);
+    if roughness <= 0.0 || n_dot_v <= 0.0 || n_dot_l <= 0.0 {
+        return Vec3::zero();
+    }
+
+    let h = (*wo + *wi).unit();
+    let g = smith_g1(n_dot_v, roughness) * smith_g1(n_dot_l, roughness);
+    let f = reflectance(&if n.dot(wo) < 0.0 { -h } else { h });
+
+    // D G F / (4 n_dot_v n_dot_l), times n_dot_l
+    f.scale(ggx(facing_n.dot(&h), roughness) * g / (4.0 * n_dot_v))
+}
+
+/// Density `sample_reflection` picks `wi` with, 0 for mirrors
+pub fn reflection_pdf(n: &Vec3, wo: &Vec3, wi: &Vec3, roughness: f64) -> f64 {
+    let n = facing(n, wo);
+    if roughness <= 0.0 || wi.dot(&n) <= 0.0 {
+        return 0.0;
+    }
+
+    let h = (*wo + *wi).unit();
+    let cos_h = n.dot(&h);
+    ggx(cos_h, roughness) * cos_h / (4.0 * wo.dot(&h).abs())
+}
+
+/// Refraction through a smooth surface of index `ior` with `n` pointing out
+/// of it, weighted by `weight` and what Fresnel lets through. Under total
+/// internal reflection `wo` is reflected instead.
+pub fn sample_transmission(n: &Vec3, wo: &Vec3, ior: f64, weight: Vec3) -> BsdfSample {
+    let inside = n.dot(wo) < 0.0;
+    let (wi, value) = match Vec3::refract(wo, n, ior, inside) {
+        Some(t) => (t, weight.scale(1.0 - fresnel(ior, wo, n))),
+        None => (Vec3::reflect(wo, n), weight)
+    };
+
+    BsdfSample { wi: wi, value: value, pdf: 1.0, lobe: Lobe::Transmission, delta: true }
+}
+
+#[test]
+fn it_samples_ggx_in_proportion_to_its_density() {
+    use raytracer::sampler::SamplerKind;
+
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let alpha = 0.3;
+
+    // ggx(cos_h) * cos_h integrates to 1 over the hemisphere, in which the
+    // cosine of uniform directions is uniform
+    let total = (0..1024).fold(0.0, |total, index| {
+        let cos_h = (index as f64 + 0.5) / 1024.0;
+        total + ggx(cos_h, alpha) * cos_h * 2.0 * PI / 1024.0
+    });
+    assert!((total - 1.0).abs() < 1e-3);
+
+    // Half of the sampled normals lie within tan(theta) < alpha
+    let mut sampler = SamplerKind::Stratified.build(32, 0);
+    let mut within = 0;
+    for index in 0..1024 {
+        sampler.start_pixel_sample(0, 0, index);
+        let (u1, u2) = sampler.next_2d();
+        let h = sample_ggx(&n, alpha, u1, u2);
+        if (1.0 - h.z * h.z).sqrt() < alpha * h.z { within += 1; }
+    }
+    assert!(within > 496 && within < 528);
+}
+
+#[test]
+fn it_matches_the_fresnel_equations() {
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+    // 4% off glass head-on, from either side
+    assert!((fresnel(1.5, &n, &n) - 0.04).abs() < 1e-12);
+    assert!((fresnel(1.5, &-n, &n) - 0.04).abs() < 1e-12);
+
+    // Nothing at Brewster's angle is polarized parallel, so half of r_s
+    let brewster = 1.5f64.atan();
+    let wo = Vec3 { x: brewster.sin(), y: 0.0, z: brewster.cos() };
+    let cos_t = (1.0 - wo.x * wo.x / 2.25).sqrt();
+    let r_s = (wo.z - 1.5 * cos_t) / (wo.z + 1.5 * cos_t);
+    assert!((fresnel(1.5, &wo, &n) - 0.5 * r_s * r_s).abs() < 1e-12);
+
+    // Total internal reflection past the critical angle inside glass
+    assert_eq!(fresnel(1.5, &Vec3 { x: 0.8, y: 0.0, z: -0.6 }, &n), 1.0);
+
+    // A conductor with no extinction is a dielectric, and metals reflect
+    // everything at grazing angles
+    assert!((fresnel_conductor(1.5, 0.0, wo.z) - fresnel(1.5, &wo, &n)).abs() < 1e-12);
+    assert!((fresnel_conductor(0.2, 3.0, 1.0) - (0.64 + 9.0) / (1.44 + 9.0)).abs() < 1e-12);
+    assert!(fresnel_conductor(0.2, 3.0, 1e-6) > 0.999);
+}
+
+#[test]
+fn it_samples_glossy_reflection_by_its_brdf() {
+    use raytracer::sampler::SamplerKind;
+
+    // A white glossy mirror loses a little light to masking only
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let wo = Vec3 { x: 0.6, y: 0.0, z: 0.8 };
+    let white = |_: &Vec3| Vec3::one();
+    let mut sampler = SamplerKind::Stratified.build(32, 0);
+    let mut albedo = 0.0;
+    for index in 0..1024 {
+        sampler.start_pixel_sample(0, 0, index);
+        if let Some(sample) = sample_reflection(&n, &wo, 0.2, &white, &mut *sampler) {
+            assert!(!sample.delta && sample.evaluated());
+            assert!((sample.pdf - reflection_pdf(&n, &wo, &sample.wi, 0.2)).abs() < 1e-9);
+            assert!((sample.value - eval_reflection(&n, &wo, &sample.wi, 0.2, &white)).len() < 1e-9);
+            albedo += sample.weight().x / 1024.0;
+        }
+    }
+    assert!(albedo > 0.9 && albedo <= 1.0, "albedo {}", albedo);
+
+    // Mirrors reflect all of `reflectance` along one direction
+    let mirror = sample_reflection(&n, &wo, 0.0, |h: &Vec3| h.scale(0.5), &mut *sampler).unwrap();
+    assert!(mirror.delta && !mirror.evaluated());
+    assert_eq!(mirror.value, n.scale(0.5));
+    assert_eq!(eval_reflection(&n, &wo, &mirror.wi, 0.0, &white), Vec3::zero());
+}
@@ -0,0 +1,67 @@
+use material::bsdf;
+use vec3::Vec3;
+
+/// A metal, by its complex index of refraction `eta` + i`k` at the red,
+/// green and blue wavelengths. Its reflection is tinted by how `k` varies.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Conductor {
+    pub eta: Vec3,
+    pub k: Vec3
+}
+
+pub static GOLD: Conductor = Conductor {
+    eta: Vec3 { x: 0.18299, y: 0.42108, z: 1.37340 },
+    k: Vec3 { x: 3.42420, y: 2.34590, z: 1.77040 }
+};
+
+pub static COPPER: Conductor = Conductor {
+    eta: Vec3 { x: 0.27105, y: 0.67693, z: 1.31640 },
+    k: Vec3 { x: 3.60920, y: 2.62480, z: 2.29210 }
+};
+
+pub static ALUMINIUM: Conductor = Conductor {
+    eta: Vec3 { x: 1.65746, y: 0.88025, z: 0.52123 },
+    k: Vec3 { x: 9.22387, y: 6.26952, z: 4.83700 }
+};
+
+pub static SILVER: Conductor = Conductor {
+    eta: Vec3 { x: 0.15943, y: 0.14512, z: 0.13547 },
+    k: Vec3 { x: 3.92910, y: 3.19000, z: 2.38080 }
+};
+
+impl Conductor {
+    /// "gold", "copper", "aluminium" (or "aluminum") or "silver"
+    pub fn named(name: &str) -> Option<Conductor> {
+        match name {
+            "gold" => Some(GOLD),
+            "copper" => Some(COPPER),
+            "aluminium" | "aluminum" => Some(ALUMINIUM),
+            "silver" => Some(SILVER),
+            _ => None
+        }
+    }
+
+    /// Fraction of light reflected at `cos_theta` to the normal
+    pub fn reflectance(&self, cos_theta: f64) -> Vec3 {
+        let cos_theta = cos_theta.abs().min(1.0);
+        Vec3 {
+            x: bsdf::fresnel_conductor(self.eta.x, self.k.x, cos_theta),
+            y: bsdf::fresnel_conductor(self.eta.y, self.k.y, cos_theta),
+            z: bsdf::fresnel_conductor(self.eta.z, self.k.z, cos_theta)
+        }
+    }
+}
+
+#[test]
+fn it_tints_metals() {
+    let gold = Conductor::named("gold").unwrap().reflectance(1.0);
+    assert!(gold.x > 0.9 && gold.x > gold.y && gold.y > gold.z);
+
+    let copper = COPPER.reflectance(1.0);
+    assert!(copper.x > copper.z);
+
+    let silver = SILVER.reflectance(1.0);
+    assert!(silver.x > 0.9 && silver.z > 0.9);
+
+    assert!(Conductor::named("unobtainium").is_none());
+}
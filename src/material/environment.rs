@@ -0,0 +1,6 @@
+use vec3::Vec3;
+
+/// Radiance arriving from infinitely far away, looked up by direction
+pub trait Environment {
+    fn color(&self, dir: Vec3) -> Vec3;
+}
@@ -1,14 +1,96 @@
+use material::bsdf::{self, BsdfSample, Lobe, LOBES};
+use medium::Medium;
+use raytracer::sampler::Sampler;
 use vec3::Vec3;
 
-/// TODO: Move specular/transmissive properties into traits
+/// A surface's BSDF. `n` is the shading normal, `wo` points back along the
+/// incoming ray and `wi` towards where light arrives from; (u, v) is the
+/// texture coordinate.
 pub trait Material {
-    fn sample(&self, n: Vec3, i: Vec3, l: Vec3, u: f64, v: f64) -> Vec3;
-    fn is_reflective(&self) -> bool;
-    fn is_refractive(&self) -> bool;
-    fn global_specular(&self, color: &Vec3) -> Vec3;
-    fn global_transmissive(&self, color: &Vec3) -> Vec3;
-    fn transmission(&self) -> Vec3;
-    fn ior(&self) -> f64;
-    fn is_glossy(&self) -> bool;
-    fn glossiness(&self) -> f64;
+    /// BSDF times |cos| of `wi`, summed over the diffuse and glossy lobes. Mirror
+    /// reflection and transmission are reached by sampling them.
+    fn eval(&self, n: &Vec3, wo: &Vec3, wi: &Vec3, u: f64, v: f64) -> Vec3;
+
+    fn has_lobe(&self, lobe: Lobe) -> bool;
+
+    /// Picks a direction from one lobe, with the value and pdf of that lobe alone
+    fn sample_lobe(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, u: f64, v: f64,
+                   sampler: &mut Sampler) -> Option<BsdfSample> {
+        match lobe {
+            Lobe::Diffuse => bsdf::sample_diffuse(n, wo, sampler, |wi| self.eval(n, wo, wi, u, v)),
+            _ => None
+        }
+    }
+
+    /// Picks a direction from one of the material's lobes, chosen uniformly.
+    /// Directions `eval` covers get its value and the density of `pdf`, as
+    /// any of those lobes could have picked them.
+    fn sample(&self, n: &Vec3, wo: &Vec3, u: f64, v: f64, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let mut lobes = [Lobe::Diffuse; 3];
+        let mut count = 0;
+        for &lobe in LOBES.iter() {
+            if self.has_lobe(lobe) {
+                lobes[count] = lobe;
+                count += 1;
+            }
+        }
+
+        if count == 0 {
+            return None;
+        }
+
+        let index = ((sampler.next_1d() * count as f64) as usize).min(count - 1);
+        self.sample_lobe(lobes[index], n, wo, u, v, sampler).and_then(|mut sample| {
+            if sample.evaluated() {
+                sample.value = self.eval(n, wo, &sample.wi, u, v);
+                sample.pdf = self.pdf(n, wo, &sample.wi);
+            } else {
+                sample.pdf /= count as f64;
+            }
+
+            if sample.pdf > 0.0 { Some(sample) } else { None }
+        })
+    }
+
+    /// Density one lobe picks `wi` with, 0 for delta lobes
+    fn lobe_pdf(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        match lobe {
+            Lobe::Diffuse => bsdf::cosine_pdf(n, wo, wi),
+            _ => 0.0
+        }
+    }
+
+    /// Density `sample` picks `wi` with, leaving out delta lobes
+    fn pdf(&self, n: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        let lobes: Vec<Lobe> = LOBES.iter().cloned().filter(|&lobe| self.has_lobe(lobe)).collect();
+        if lobes.is_empty() {
+            return 0.0;
+        }
+
+        let total = lobes.iter().fold(0.0, |total, &lobe| total + self.lobe_pdf(lobe, n, wo, wi));
+        total / lobes.len() as f64
+    }
+
+    /// Fraction of light shadow rays let through at (u, v)
+    fn transmission(&self, u: f64, v: f64) -> Vec3;
+
+    /// Ambient light the Whitted integrator adds at (u, v) in place of indirect light
+    fn ambient(&self, _u: f64, _v: f64) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// Radiance the surface gives off on its own at (u, v)
+    fn emission(&self, _u: f64, _v: f64) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// Absorption coefficient per unit distance of what the surface encloses
+    fn absorption(&self) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// Participating medium filling what the surface encloses, if it is closed
+    fn medium(&self) -> Option<&Medium> {
+        None
+    }
 }
@@ -1,47 +1,62 @@
 use std::f64::consts::PI;
-use material::{Material, Texture};
+use std::sync::Arc;
+use material::{Conductor, Material, Texture};
+use material::bsdf::{self, BsdfSample, Lobe};
 use raytracer::compositor::ColorRGBA;
+use raytracer::sampler::Sampler;
 use vec3::Vec3;
 
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct CookTorranceMaterial {
-    pub k_a: f64,            // Ambient coefficient
-    pub k_d: f64,            // Diffuse coefficient
-    pub k_s: f64,            // Local specular coefficient
-    pub k_sg: f64,           // Global specular coefficient (mirror reflection)
-    pub k_tg: f64,           // Global transmissive coefficient (refraction)
-    pub ambient: Vec3,       // Ambient color
-    pub diffuse: Vec3,       // Diffuse color
-    pub transmission: Vec3,  // Transmissive color
-    pub specular: Vec3,      // Specular color
-    pub roughness: f64,      // Smaller = shininer => smaller highlight spot on surface
-    pub glossiness: f64,     // How glossy reflections are. 0 for non-glossy surfaces.
-    pub gauss_constant: f64, // Controls curve of distribution of microfacets
-    pub ior: f64,            // Index of refraction, also used for specular highlights
-    pub diffuse_texture: Option<Box<Texture+Send+Sync>>
+    pub k_a: f64,                     // Ambient coefficient
+    pub k_d: f64,                     // Diffuse coefficient
+    pub k_s: f64,                     // Local specular coefficient
+    pub k_sg: f64,                    // Global specular coefficient (mirror reflection)
+    pub k_tg: f64,                    // Global transmissive coefficient (refraction)
+    pub ambient: Vec3,                // Ambient color
+    pub diffuse: Vec3,                // Diffuse color
+    pub transmission: Vec3,           // Transmissive color
+    pub specular: Vec3,               // Specular color
+    pub roughness: f64,               // Smaller = shininer => smaller highlight spot on surface
+    pub glossiness: f64,              // GGX roughness of reflections. 0 for mirrors.
+    pub gauss_constant: f64,          // Controls curve of distribution of microfacets
+    pub ior: f64,                     // Index of refraction, also used for specular highlights
+    pub conductor: Option<Conductor>, // Metal to reflect like instead of a dielectric of `ior`
+    pub emission: Vec3,               // Radiance emitted from the surface
+    pub diffuse_texture: Option<Arc<Texture+Send+Sync>>
 }
 
-impl Material for CookTorranceMaterial {
-    fn sample(&self, n: Vec3, i: Vec3, l: Vec3, u: f64, v: f64) -> Vec3 {
-        let ambient  = self.ambient.scale(self.k_a);
-        let diffuse  = self.diffuse.scale(self.k_d).scale(n.dot(&l)) * match self.diffuse_texture {
+impl CookTorranceMaterial {
+    /// Fraction of light reflected towards `wo` off a surface with normal `n`
+    fn reflectance(&self, wo: &Vec3, n: &Vec3) -> Vec3 {
+        match self.conductor {
+            Some(ref metal) => metal.reflectance(wo.dot(n)),
+            None => Vec3::one().scale(bsdf::fresnel(self.ior, wo, n))
+        }
+    }
+
+    /// The diffuse lobe: diffuse and local specular shading
+    fn shading(&self, n: &Vec3, wo: &Vec3, wi: &Vec3, u: f64, v: f64) -> Vec3 {
+        let n = bsdf::facing(n, wo);
+        let n_dot_l = n.dot(wi);
+        if n_dot_l <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let diffuse  = self.diffuse.scale(self.k_d).scale(n_dot_l) * match self.diffuse_texture {
             Some(ref x) => x.color(u, v),
             None => ColorRGBA::white()
         }.to_vec3();
 
         // Specular calculations
-        let h = (l + i).unit();
+        let h = (*wi + *wo).unit();
         let n_dot_h = n.dot(&h);
-        let n_dot_l = n.dot(&l);
-        let v_dot_h = i.dot(&h);
-        let n_dot_v = n.dot(&i);
+        let v_dot_h = wo.dot(&h);
+        let n_dot_v = n.dot(wo);
 
-        // Fresnel term (Schlick's approximation)
-        let n1 = 1.0;
-        let n2 = self.ior;
-        let f0 = ((n1 - n2) / (n1 + n2)).powf(2.0);
-        let f = (1.0 - v_dot_h).powf(5.0) * (1.0 - f0) + f0;
+        // Fresnel term off the microfacet
+        let f = self.reflectance(wo, &h);
 
         // Microfacet distribution
         let alpha = n_dot_h.acos();
@@ -52,41 +67,63 @@ impl Material for CookTorranceMaterial {
         let g2 = (2.0 * n_dot_h * n_dot_l) / v_dot_h;
         let g = g1.min(g2);
 
-        let brdf = f * d * g / (n_dot_v * n_dot_l * PI);
+        let brdf = d * g / (n_dot_v * n_dot_l * PI);
 
-        self.specular.scale(self.k_s * brdf) + diffuse + ambient
+        (self.specular.scale(self.k_s * brdf) * f + diffuse).scale(1.0 / PI)
     }
 
-    fn is_reflective(&self) -> bool {
-        self.k_sg > 0.0
+    /// Glossy reflection, mirrors being sampled instead
+    fn glossy(&self, n: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if self.k_sg <= 0.0 {
+            return Vec3::zero();
+        }
+
+        bsdf::eval_reflection(n, wo, wi, self.glossiness, |h| self.reflectance(wo, h).scale(self.k_sg))
     }
+}
 
-    fn is_refractive(&self) -> bool {
-        self.k_tg > 0.0
+impl Material for CookTorranceMaterial {
+    fn eval(&self, n: &Vec3, wo: &Vec3, wi: &Vec3, u: f64, v: f64) -> Vec3 {
+        self.shading(n, wo, wi, u, v) + self.glossy(n, wo, wi)
     }
 
-    fn global_specular(&self, color: &Vec3) -> Vec3 {
-        color.scale(self.k_sg)
+    fn has_lobe(&self, lobe: Lobe) -> bool {
+        match lobe {
+            Lobe::Diffuse => true,
+            Lobe::Reflection => self.k_sg > 0.0,
+            Lobe::Transmission => self.k_tg > 0.0
+        }
     }
 
-    fn global_transmissive(&self, color: &Vec3) -> Vec3 {
-        color.scale(self.k_tg)
+    fn sample_lobe(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, u: f64, v: f64,
+                   sampler: &mut Sampler) -> Option<BsdfSample> {
+        match lobe {
+            Lobe::Diffuse => bsdf::sample_diffuse(n, wo, sampler, |wi| self.shading(n, wo, wi, u, v)),
+            Lobe::Reflection => {
+                bsdf::sample_reflection(n, wo, self.glossiness, |h| self.reflectance(wo, h).scale(self.k_sg), sampler)
+            },
+            Lobe::Transmission => Some(bsdf::sample_transmission(n, wo, self.ior, Vec3::one().scale(self.k_tg)))
+        }
     }
 
-    fn transmission(&self) -> Vec3 {
-        self.transmission
+    fn lobe_pdf(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        match lobe {
+            Lobe::Diffuse => bsdf::cosine_pdf(n, wo, wi),
+            Lobe::Reflection => bsdf::reflection_pdf(n, wo, wi, self.glossiness),
+            Lobe::Transmission => 0.0
+        }
     }
 
-    fn ior(&self) -> f64 {
-        self.ior
+    fn transmission(&self, _u: f64, _v: f64) -> Vec3 {
+        self.transmission
     }
 
-    fn is_glossy(&self) -> bool {
-        self.glossiness > ::std::f64::EPSILON
+    fn ambient(&self, _u: f64, _v: f64) -> Vec3 {
+        self.ambient.scale(self.k_a)
     }
 
-    fn glossiness(&self) -> f64 {
-        self.glossiness
+    fn emission(&self, _u: f64, _v: f64) -> Vec3 {
+        self.emission
     }
 }
 
@@ -102,10 +139,12 @@ impl Default for CookTorranceMaterial {
             roughness: 0.15,
             glossiness: 0.0,
             ior: 1.5,
+            conductor: None,
             ambient: Vec3::one(),
             diffuse: Vec3 { x: 0.5, y: 0.5, z: 0.5 },
             specular: Vec3::one(),
             transmission: Vec3::zero(),
+            emission: Vec3::zero(),
             diffuse_texture: None
         }
     }
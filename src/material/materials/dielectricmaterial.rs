@@ -0,0 +1,149 @@
+use material::Material;
+use material::bsdf::{self, BsdfSample, Lobe};
+use medium::Medium;
+use raytracer::sampler::Sampler;
+use std::sync::Arc;
+use vec3::Vec3;
+
+/// Wavelengths in µm the red, green and blue channels are refracted at
+static WAVELENGTHS: [f64; 3] = [0.65, 0.55, 0.45];
+
+/// Wavelength `ior` is measured at (the sodium d line), in µm
+static D_LINE: f64 = 0.5876;
+
+/// Smooth glass, water or gems. Light is reflected or refracted by Fresnel and
+/// absorbed inside by Beer-Lambert's law, so thick glass is darker than thin.
+/// With a nonzero `cauchy_b` each sample refracts a single color channel at its
+/// own index of refraction, splitting white light into its colors.
+///
+/// A closed surface can hold a `medium` such as smoke or murky water; with an
+/// `ior` of 1 it is an invisible boundary of the medium.
+#[derive(Clone)]
+pub struct DielectricMaterial {
+    pub ior: f64,         // Index of refraction at 587.6 nm
+    pub cauchy_b: f64,    // Cauchy's B coefficient in µm², 0 for no dispersion (crown glass is about 0.004)
+    pub color: Vec3,      // Tint of reflected and refracted light at the surface
+    pub absorption: Vec3, // Absorption coefficient inside, per unit distance
+    pub medium: Option<Arc<Medium+Send+Sync>>
+}
+
+impl DielectricMaterial {
+    /// Index of refraction at `wavelength` µm by Cauchy's equation
+    pub fn ior_at(&self, wavelength: f64) -> f64 {
+        self.ior + self.cauchy_b * (1.0 / (wavelength * wavelength) - 1.0 / (D_LINE * D_LINE))
+    }
+
+    /// The index of refraction to refract with, and the channels it holds for
+    fn pick_ior(&self, sampler: &mut Sampler) -> (f64, Vec3) {
+        if self.cauchy_b == 0.0 {
+            return (self.ior, Vec3::one());
+        }
+
+        // Each channel is picked a third of the time
+        let channel = ((sampler.next_1d() * 3.0) as usize).min(2);
+        let mask = match channel {
+            0 => Vec3 { x: 3.0, y: 0.0, z: 0.0 },
+            1 => Vec3 { x: 0.0, y: 3.0, z: 0.0 },
+            _ => Vec3 { x: 0.0, y: 0.0, z: 3.0 }
+        };
+
+        (self.ior_at(WAVELENGTHS[channel]), mask)
+    }
+
+    fn sample_with(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, ior: f64, mask: Vec3, probability: f64) -> Option<BsdfSample> {
+        let fresnel = bsdf::fresnel(ior, wo, n);
+        let (wi, value) = match lobe {
+            Lobe::Reflection => (Vec3::reflect(wo, n), self.color.scale(fresnel) * mask),
+            Lobe::Transmission => match Vec3::refract(wo, n, ior, n.dot(wo) < 0.0) {
+                Some(t) => (t, self.color.scale(1.0 - fresnel) * mask),
+                None => return None // Total internal reflection, which the reflection lobe covers
+            },
+            Lobe::Diffuse => return None
+        };
+
+        // Dispersed light could have been refracted elsewhere for the other channels
+        let delta = lobe == Lobe::Reflection || self.cauchy_b == 0.0;
+        Some(BsdfSample { wi: wi, value: value, pdf: probability, lobe: lobe, delta: delta })
+    }
+}
+
+impl Material for DielectricMaterial {
+    fn eval(&self, _n: &Vec3, _wo: &Vec3, _wi: &Vec3, _u: f64, _v: f64) -> Vec3 {
+        Vec3::zero()
+    }
+
+    fn has_lobe(&self, lobe: Lobe) -> bool {
+        lobe != Lobe::Diffuse
+    }
+
+    fn sample_lobe(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, _u: f64, _v: f64,
+                   sampler: &mut Sampler) -> Option<BsdfSample> {
+        // All channels reflect the same way
+        let (ior, mask) = if lobe == Lobe::Reflection { (self.ior, Vec3::one()) } else { self.pick_ior(sampler) };
+        self.sample_with(lobe, n, wo, ior, mask, 1.0)
+    }
+
+    /// Reflects or refracts in proportion to the Fresnel reflectance
+    fn sample(&self, n: &Vec3, wo: &Vec3, _u: f64, _v: f64, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let (ior, mask) = self.pick_ior(sampler);
+        let fresnel = bsdf::fresnel(ior, wo, n);
+        if sampler.next_1d() < fresnel {
+            self.sample_with(Lobe::Reflection, n, wo, ior, mask, fresnel)
+        } else {
+            self.sample_with(Lobe::Transmission, n, wo, ior, mask, 1.0 - fresnel)
+        }
+    }
+
+    /// Lets light through to shadow rays, tinted but not absorbed
+    fn transmission(&self, _u: f64, _v: f64) -> Vec3 {
+        self.color
+    }
+
+    fn absorption(&self) -> Vec3 {
+        self.absorption
+    }
+
+    fn medium(&self) -> Option<&Medium> {
+        self.medium.as_ref().map(|medium| &**medium as &Medium)
+    }
+}
+
+impl Default for DielectricMaterial {
+    fn default() -> DielectricMaterial {
+        DielectricMaterial {
+            ior: 1.5,
+            cauchy_b: 0.0,
+            color: Vec3::one(),
+            absorption: Vec3::zero(),
+            medium: None
+        }
+    }
+}
+
+#[test]
+fn it_disperses_white_light() {
+    use raytracer::sampler::SamplerKind;
+
+    let prism = DielectricMaterial { cauchy_b: 0.01, ..Default::default() };
+    assert!(prism.ior_at(0.45) > prism.ior_at(0.55) && prism.ior_at(0.55) > prism.ior_at(0.65));
+    assert_eq!(1.5, prism.ior_at(D_LINE));
+
+    // Blue bends more than red towards the normal on the way in
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let wo = Vec3 { x: 0.6, y: 0.0, z: 0.8 };
+    let mut sampler = SamplerKind::Stratified.build(4, 0);
+    let (mut red, mut blue) = (None, None);
+    let mut total = Vec3::zero();
+    for index in 0..16 {
+        sampler.start_pixel_sample(0, 0, index);
+        let sample = prism.sample_lobe(Lobe::Transmission, &n, &wo, 0.0, 0.0, &mut *sampler).unwrap();
+        assert!(!sample.delta);
+        if sample.value.x > 0.0 { red = Some(sample.wi.x); }
+        if sample.value.z > 0.0 { blue = Some(sample.wi.x); }
+        total = total + sample.weight().scale(1.0 / 16.0);
+    }
+    assert!(blue.unwrap() > red.unwrap());
+
+    // Without absorption or tint all light gets through but what Fresnel reflects
+    assert!((total.x + total.y + total.z) / 3.0 > 0.9);
+}
@@ -1,4 +1,6 @@
+use std::f64::consts::PI;
 use material::Material;
+use material::bsdf::Lobe;
 use vec3::Vec3;
 
 #[allow(dead_code)]
@@ -8,41 +10,17 @@ pub struct FlatMaterial {
 }
 
 impl Material for FlatMaterial {
-    fn sample(&self, _n: Vec3, _i: Vec3, _l: Vec3, _u: f64, _v: f64) -> Vec3 {
-        self.color
+    fn eval(&self, _n: &Vec3, _wo: &Vec3, _wi: &Vec3, _u: f64, _v: f64) -> Vec3 {
+        self.color.scale(1.0 / PI)
     }
 
-    fn is_reflective(&self) -> bool {
-        false
+    fn has_lobe(&self, lobe: Lobe) -> bool {
+        lobe == Lobe::Diffuse
     }
 
-    fn is_refractive(&self) -> bool {
-        false
-    }
-
-    fn global_specular(&self, _color: &Vec3) -> Vec3 {
-        Vec3::zero()
-    }
-
-    fn global_transmissive(&self, _color: &Vec3) -> Vec3 {
-        Vec3::zero()
-    }
-
-    fn transmission(&self) -> Vec3 {
+    fn transmission(&self, _u: f64, _v: f64) -> Vec3 {
         Vec3::zero()
     }
-
-    fn ior(&self) -> f64 {
-        1.0
-    }
-
-    fn is_glossy(&self) -> bool {
-        false
-    }
-
-    fn glossiness(&self) -> f64 {
-        0.0
-    }
 }
 
 impl Default for FlatMaterial {
@@ -0,0 +1,232 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use material::{Conductor, Material, Texture};
+use material::bsdf::{self, BsdfSample, Lobe};
+use raytracer::compositor::ColorRGBA;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Energy conserving microfacet material: a GGX specular layer with Smith
+/// masking-shadowing and Schlick Fresnel, over a Lambertian base that gets the
+/// light the layer does not reflect. Metals have no diffuse base and tint their
+/// reflection with the base color, or reflect exactly like a `conductor`.
+///
+/// Lights are sampled for the whole BSDF, so the specular layer is part of the
+/// diffuse lobe and `sample` picks either cosine or GGX directions for it. A
+/// roughness near 0 makes the layer a mirror, a reflection lobe of its own.
+#[derive(Clone)]
+pub struct GgxMaterial {
+    pub base_color: Vec3,             // Diffuse albedo of dielectrics, reflectance of metals
+    pub metalness: f64,               // 0 for dielectrics, 1 for metals
+    pub roughness: f64,               // Perceptual roughness in [0, 1], squared into the GGX alpha
+    pub ior: f64,                     // Index of refraction, sets the reflectance of dielectrics
+    pub conductor: Option<Conductor>, // Metal to reflect like, instead of `base_color` and `metalness`
+    pub emission: Vec3,               // Radiance emitted from the surface
+    pub base_color_texture: Option<Arc<Texture+Send+Sync>>
+}
+
+/// GGX alpha below which the specular layer is a mirror
+const MIRROR_ALPHA: f64 = 1e-3;
+
+impl GgxMaterial {
+    fn alpha(&self) -> f64 {
+        self.roughness * self.roughness
+    }
+
+    fn mirror(&self) -> bool {
+        self.alpha() < MIRROR_ALPHA
+    }
+
+    fn base_color(&self, u: f64, v: f64) -> Vec3 {
+        self.base_color * match self.base_color_texture {
+            Some(ref x) => x.color(u, v),
+            None => ColorRGBA::white()
+        }.to_vec3()
+    }
+
+    fn metalness(&self) -> f64 {
+        if self.conductor.is_some() { 1.0 } else { self.metalness }
+    }
+
+    /// Reflectance at normal incidence
+    fn f0(&self, base_color: &Vec3) -> Vec3 {
+        let r = (self.ior - 1.0) / (self.ior + 1.0);
+        Vec3::lerp(&Vec3::one().scale(r * r), base_color, self.metalness())
+    }
+
+    /// Reflectance at `cos_theta` to the microfacet normal
+    fn fresnel(&self, f0: &Vec3, cos_theta: f64) -> Vec3 {
+        match self.conductor {
+            Some(ref metal) => metal.reflectance(cos_theta),
+            None => bsdf::schlick(f0, cos_theta)
+        }
+    }
+
+    /// Chance of sampling a GGX direction rather than a cosine-weighted one
+    fn specular_probability(&self) -> f64 {
+        0.5 * (1.0 + self.metalness())
+    }
+
+    /// Picks a cosine-weighted or GGX direction for the diffuse lobe
+    fn sample_layers(&self, n: &Vec3, wo: &Vec3, u: f64, v: f64, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let facing_n = bsdf::facing(n, wo);
+        let specular = sampler.next_1d() < self.specular_probability();
+        let (u1, u2) = sampler.next_2d();
+        let wi = if specular {
+            Vec3::reflect(wo, &bsdf::sample_ggx(&facing_n, self.alpha(), u1, u2))
+        } else {
+            Vec3::cosine_hemisphere(&facing_n, u1, u2)
+        };
+
+        let pdf = self.lobe_pdf(Lobe::Diffuse, n, wo, &wi);
+        if pdf <= 0.0 || facing_n.dot(&wi) <= 0.0 {
+            return None;
+        }
+
+        Some(BsdfSample {
+            wi: wi,
+            value: self.eval(n, wo, &wi, u, v),
+            pdf: pdf,
+            lobe: Lobe::Diffuse,
+            delta: false
+        })
+    }
+}
+
+impl Material for GgxMaterial {
+    fn eval(&self, n: &Vec3, wo: &Vec3, wi: &Vec3, u: f64, v: f64) -> Vec3 {
+        let n = bsdf::facing(n, wo);
+        let n_dot_v = n.dot(wo);
+        let n_dot_l = n.dot(wi);
+        if n_dot_v <= 0.0 || n_dot_l <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let h = (*wo + *wi).unit();
+        let alpha = self.alpha();
+        let base_color = self.base_color(u, v);
+        let f0 = self.f0(&base_color);
+        let f = self.fresnel(&f0, wo.dot(&h));
+        let g = bsdf::smith_g1(n_dot_v, alpha) * bsdf::smith_g1(n_dot_l, alpha);
+
+        // D G F / (4 n_dot_v n_dot_l), times n_dot_l. Mirrors are sampled instead.
+        let specular = if self.mirror() {
+            Vec3::zero()
+        } else {
+            f.scale(bsdf::ggx(n.dot(&h), alpha) * g / (4.0 * n_dot_v))
+        };
+
+        // Light enters and leaves the base through the specular layer
+        let through = (Vec3::one() - bsdf::schlick(&f0, n_dot_v)) * (Vec3::one() - bsdf::schlick(&f0, n_dot_l));
+        let diffuse = through * base_color.scale((1.0 - self.metalness()) * n_dot_l / PI);
+
+        specular + diffuse
+    }
+
+    fn has_lobe(&self, lobe: Lobe) -> bool {
+        match lobe {
+            Lobe::Diffuse => !self.mirror() || self.metalness() < 1.0,
+            Lobe::Reflection => self.mirror(),
+            Lobe::Transmission => false
+        }
+    }
+
+    fn sample_lobe(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, u: f64, v: f64,
+                   sampler: &mut Sampler) -> Option<BsdfSample> {
+        match lobe {
+            Lobe::Diffuse if self.mirror() => bsdf::sample_diffuse(n, wo, sampler, |wi| self.eval(n, wo, wi, u, v)),
+            Lobe::Diffuse => self.sample_layers(n, wo, u, v, sampler),
+            Lobe::Reflection if self.mirror() => {
+                let f0 = self.f0(&self.base_color(u, v));
+                bsdf::sample_reflection(n, wo, 0.0, |h| self.fresnel(&f0, wo.dot(h).abs()), sampler)
+            },
+            _ => None
+        }
+    }
+
+    fn lobe_pdf(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        if lobe != Lobe::Diffuse {
+            return 0.0;
+        }
+        if self.mirror() {
+            return bsdf::cosine_pdf(n, wo, wi);
+        }
+
+        let p = self.specular_probability();
+        p * bsdf::reflection_pdf(n, wo, wi, self.alpha()) + (1.0 - p) * bsdf::cosine_pdf(n, wo, wi)
+    }
+
+    fn transmission(&self, _u: f64, _v: f64) -> Vec3 {
+        Vec3::zero()
+    }
+
+    fn emission(&self, _u: f64, _v: f64) -> Vec3 {
+        self.emission
+    }
+}
+
+impl Default for GgxMaterial {
+    fn default() -> GgxMaterial {
+        GgxMaterial {
+            base_color: Vec3 { x: 0.5, y: 0.5, z: 0.5 },
+            metalness: 0.0,
+            roughness: 0.5,
+            ior: 1.5,
+            conductor: None,
+            emission: Vec3::zero(),
+            base_color_texture: None
+        }
+    }
+}
+
+#[test]
+fn it_conserves_energy() {
+    use raytracer::sampler::SamplerKind;
+
+    // A white surface under uniform light reflects at most all of it. Smooth
+    // surfaces keep most; very rough metals lose a lot to masking.
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let mut sampler = SamplerKind::Stratified.build(32, 0);
+    for &metalness in [0.0, 1.0].iter() {
+        for &roughness in [0.0, 0.1, 0.5, 1.0].iter() {
+            let material = GgxMaterial { base_color: Vec3::one(), metalness: metalness, roughness: roughness,
+                                         ..Default::default() };
+            for &cos_theta in [1.0, 0.5, 0.1f64].iter() {
+                let wo = Vec3 { x: (1.0 - cos_theta * cos_theta).sqrt(), y: 0.0, z: cos_theta };
+                let mut albedo = 0.0;
+                for index in 0..1024 {
+                    sampler.start_pixel_sample(0, 0, index);
+                    if let Some(sample) = material.sample(&n, &wo, 0.0, 0.0, &mut *sampler) {
+                        albedo += sample.weight().x / 1024.0;
+                    }
+                }
+
+                assert!(albedo < 1.02, "albedo {} at metalness {}, roughness {}, cos {}",
+                        albedo, metalness, roughness, cos_theta);
+                if cos_theta > 0.4 && roughness <= 0.5 {
+                    assert!(albedo > 0.75, "albedo {} at metalness {}, roughness {}, cos {}",
+                            albedo, metalness, roughness, cos_theta);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn it_reflects_like_a_mirror_when_smooth() {
+    use raytracer::sampler::SamplerKind;
+
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let wo = Vec3 { x: 0.6, y: 0.0, z: 0.8 };
+    let gold = Vec3 { x: 1.0, y: 0.8, z: 0.3 };
+    let metal = GgxMaterial { base_color: gold, metalness: 1.0, roughness: 0.0, ..Default::default() };
+    assert!(metal.has_lobe(Lobe::Reflection) && !metal.has_lobe(Lobe::Diffuse));
+
+    let mut sampler = SamplerKind::Stratified.build(1, 0);
+    sampler.start_pixel_sample(0, 0, 0);
+    let sample = metal.sample(&n, &wo, 0.0, 0.0, &mut *sampler).unwrap();
+    assert!(sample.delta);
+    assert!((sample.wi - Vec3 { x: -0.6, y: 0.0, z: 0.8 }).len() < 1e-9);
+    assert!((sample.weight() - bsdf::schlick(&gold, 0.8)).len() < 1e-9);
+    assert_eq!(metal.eval(&n, &wo, &sample.wi, 0.0, 0.0), Vec3::zero());
+}
@@ -1,70 +1,111 @@
-use material::{Material, Texture};
+use std::f64::consts::PI;
+use std::sync::Arc;
+use material::{Conductor, Material, Texture};
+use material::bsdf::{self, BsdfSample, Lobe};
 use raytracer::compositor::ColorRGBA;
+use raytracer::sampler::Sampler;
 use vec3::Vec3;
 
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct PhongMaterial {
-    pub k_a: f64,           // Ambient coefficient
-    pub k_d: f64,           // Diffuse coefficient
-    pub k_s: f64,           // Local specular coefficient
-    pub k_sg: f64,          // Global specular coefficient (mirror reflection)
-    pub k_tg: f64,          // Global transmissive coefficient (refraction)
-    pub ambient: Vec3,      // Ambient color
-    pub diffuse: Vec3,      // Diffuse color
-    pub transmission: Vec3, // Transmissive color
-    pub specular: Vec3,     // Specular color
-    pub shininess: f64,     // Size of Phong specular highlight
-    pub glossiness: f64,    // How glossy reflections are. 0 for non-glossy surfaces.
-    pub ior: f64,           // Index of refraction
-    pub diffuse_texture: Option<Box<Texture+Send+Sync>>
+    pub k_a: f64,                     // Ambient coefficient
+    pub k_d: f64,                     // Diffuse coefficient
+    pub k_s: f64,                     // Local specular coefficient
+    pub k_sg: f64,                    // Global specular coefficient (mirror reflection)
+    pub k_tg: f64,                    // Global transmissive coefficient (refraction)
+    pub ambient: Vec3,                // Ambient color
+    pub diffuse: Vec3,                // Diffuse color
+    pub transmission: Vec3,           // Transmissive color
+    pub specular: Vec3,               // Specular color
+    pub shininess: f64,               // Size of Phong specular highlight
+    pub glossiness: f64,              // GGX roughness of reflections. 0 for mirrors.
+    pub ior: f64,                     // Index of refraction
+    pub conductor: Option<Conductor>, // Metal to reflect like instead of a dielectric of `ior`
+    pub emission: Vec3,               // Radiance emitted from the surface
+    pub diffuse_texture: Option<Arc<Texture+Send+Sync>>
 }
 
-impl Material for PhongMaterial {
-    fn sample(&self, n: Vec3, i: Vec3, l: Vec3, u: f64, v: f64) -> Vec3 {
-        let h = (l + i).unit();
+impl PhongMaterial {
+    /// Fraction of light reflected towards `wo` off a surface with normal `n`
+    fn reflectance(&self, wo: &Vec3, n: &Vec3) -> Vec3 {
+        match self.conductor {
+            Some(ref metal) => metal.reflectance(wo.dot(n)),
+            None => Vec3::one().scale(bsdf::fresnel(self.ior, wo, n))
+        }
+    }
+
+    /// The diffuse lobe: diffuse and local specular shading
+    fn shading(&self, n: &Vec3, wo: &Vec3, wi: &Vec3, u: f64, v: f64) -> Vec3 {
+        let n = bsdf::facing(n, wo);
+        if n.dot(wi) <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let h = (*wi + *wo).unit();
 
         // Blinn-Phong approximation
-        let ambient  = self.ambient.scale(self.k_a);
-        let diffuse  = self.diffuse.scale(self.k_d).scale(n.dot(&l)) * match self.diffuse_texture {
+        let diffuse  = self.diffuse.scale(self.k_d).scale(n.dot(wi)) * match self.diffuse_texture {
             Some(ref x) => x.color(u, v),
             None => ColorRGBA::white()
         }.to_vec3();
-        let specular = self.specular.scale(self.k_s).scale(n.dot(&h).powf(self.shininess));
+        let specular = self.specular.scale(self.k_s).scale(n.dot(&h).max(0.0).powf(self.shininess));
 
-        ambient + diffuse + specular
+        (diffuse + specular).scale(1.0 / PI)
     }
 
-    fn is_reflective(&self) -> bool {
-        self.k_sg > 0.0
+    /// Glossy reflection, mirrors being sampled instead
+    fn glossy(&self, n: &Vec3, wo: &Vec3, wi: &Vec3) -> Vec3 {
+        if self.k_sg <= 0.0 {
+            return Vec3::zero();
+        }
+
+        bsdf::eval_reflection(n, wo, wi, self.glossiness, |h| self.reflectance(wo, h).scale(self.k_sg))
     }
+}
 
-    fn is_refractive(&self) -> bool {
-        self.k_tg > 0.0
+impl Material for PhongMaterial {
+    fn eval(&self, n: &Vec3, wo: &Vec3, wi: &Vec3, u: f64, v: f64) -> Vec3 {
+        self.shading(n, wo, wi, u, v) + self.glossy(n, wo, wi)
     }
 
-    fn global_specular(&self, color: &Vec3) -> Vec3 {
-        color.scale(self.k_sg)
+    fn has_lobe(&self, lobe: Lobe) -> bool {
+        match lobe {
+            Lobe::Diffuse => true,
+            Lobe::Reflection => self.k_sg > 0.0,
+            Lobe::Transmission => self.k_tg > 0.0
+        }
     }
 
-    fn global_transmissive(&self, color: &Vec3) -> Vec3 {
-        color.scale(self.k_tg)
+    fn sample_lobe(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, u: f64, v: f64,
+                   sampler: &mut Sampler) -> Option<BsdfSample> {
+        match lobe {
+            Lobe::Diffuse => bsdf::sample_diffuse(n, wo, sampler, |wi| self.shading(n, wo, wi, u, v)),
+            Lobe::Reflection => {
+                bsdf::sample_reflection(n, wo, self.glossiness, |h| self.reflectance(wo, h).scale(self.k_sg), sampler)
+            },
+            Lobe::Transmission => Some(bsdf::sample_transmission(n, wo, self.ior, Vec3::one().scale(self.k_tg)))
+        }
     }
 
-    fn transmission(&self) -> Vec3 {
-        self.transmission
+    fn lobe_pdf(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        match lobe {
+            Lobe::Diffuse => bsdf::cosine_pdf(n, wo, wi),
+            Lobe::Reflection => bsdf::reflection_pdf(n, wo, wi, self.glossiness),
+            Lobe::Transmission => 0.0
+        }
     }
 
-    fn ior(&self) -> f64 {
-        self.ior
+    fn transmission(&self, _u: f64, _v: f64) -> Vec3 {
+        self.transmission
     }
 
-    fn is_glossy(&self) -> bool {
-        self.glossiness > ::std::f64::EPSILON
+    fn ambient(&self, _u: f64, _v: f64) -> Vec3 {
+        self.ambient.scale(self.k_a)
     }
 
-    fn glossiness(&self) -> f64 {
-        self.glossiness
+    fn emission(&self, _u: f64, _v: f64) -> Vec3 {
+        self.emission
     }
 }
 
@@ -79,11 +120,39 @@ impl Default for PhongMaterial {
             shininess: 10.0,
             glossiness: 0.0,
             ior: 1.0,
+            conductor: None,
             ambient: Vec3::one(),
             diffuse: Vec3 { x: 0.5, y: 0.5, z: 0.5 },
             specular: Vec3::one(),
             transmission: Vec3::zero(),
+            emission: Vec3::zero(),
             diffuse_texture: None
         }
     }
 }
+
+#[test]
+fn it_samples_in_proportion_to_eval() {
+    use raytracer::sampler::SamplerKind;
+
+    // Light reflected off a glossy surface under uniform light, estimated by
+    // sampling the BSDF and by cosine-weighted directions, agrees
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let wo = Vec3 { x: 0.6, y: 0.0, z: 0.8 };
+    let material = PhongMaterial { k_s: 0.0, k_sg: 0.8, glossiness: 0.3, ior: 1.5, ..Default::default() };
+    let mut sampler = SamplerKind::Stratified.build(64, 0);
+    let (mut sampled, mut cosine) = (0.0, 0.0);
+    for index in 0..4096 {
+        sampler.start_pixel_sample(0, 0, index);
+        if let Some(sample) = material.sample(&n, &wo, 0.0, 0.0, &mut *sampler) {
+            assert!((sample.pdf - material.pdf(&n, &wo, &sample.wi)).abs() < 1e-9);
+            sampled += sample.weight().x / 4096.0;
+        }
+
+        let (u1, u2) = sampler.next_2d();
+        let wi = Vec3::cosine_hemisphere(&n, u1, u2);
+        cosine += material.eval(&n, &wo, &wi, 0.0, 0.0).x * PI / wi.z / 4096.0;
+    }
+
+    assert!((sampled - cosine).abs() < 0.02, "sampled {}, cosine {}", sampled, cosine);
+}
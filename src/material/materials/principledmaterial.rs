@@ -0,0 +1,281 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use material::{Material, Texture};
+use material::bsdf::{self, BsdfSample, Lobe};
+use raytracer::compositor::accumulator::luminance;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Principled (Disney-style) uber material with the parameters of common
+/// authoring tools. Layers, from the bottom: a diffuse base with Burley's
+/// retro-reflection and a sheen at grazing angles, smooth transmission, a GGX
+/// specular layer and a colorless GGX clearcoat. Parameters with a texture are
+/// multiplied by it, colors by its color and scalars by its red channel.
+///
+/// As with `GgxMaterial` the specular layers are part of the diffuse lobe so
+/// that lights are sampled for them; transmission is a lobe of its own.
+#[derive(Clone)]
+pub struct PrincipledMaterial {
+    pub base_color: Vec3,           // Diffuse albedo of dielectrics, reflectance of metals
+    pub metalness: f64,             // 0 for dielectrics, 1 for metals
+    pub roughness: f64,             // Perceptual roughness, squared into the GGX alpha
+    pub specular_level: f64,        // Dielectric reflectance at normal incidence, 0.5 is 4%
+    pub clearcoat: f64,             // Strength of the clearcoat
+    pub clearcoat_roughness: f64,   // Perceptual roughness of the clearcoat
+    pub sheen: f64,                 // Strength of the grazing sheen, for cloth
+    pub sheen_tint: f64,            // 0 for a white sheen, 1 for a sheen in the base hue
+    pub specular_transmission: f64, // Fraction of the dielectric base that is clear like glass
+    pub ior: f64,                   // Index of refraction of transmitted light
+    pub emission: Vec3,             // Radiance emitted from the surface
+    pub base_color_texture: Option<Arc<Texture+Send+Sync>>,
+    pub metalness_texture: Option<Arc<Texture+Send+Sync>>,
+    pub roughness_texture: Option<Arc<Texture+Send+Sync>>,
+    pub specular_level_texture: Option<Arc<Texture+Send+Sync>>,
+    pub clearcoat_texture: Option<Arc<Texture+Send+Sync>>,
+    pub sheen_texture: Option<Arc<Texture+Send+Sync>>,
+    pub specular_transmission_texture: Option<Arc<Texture+Send+Sync>>,
+    pub clearcoat_roughness_texture: Option<Arc<Texture+Send+Sync>>,
+    pub sheen_tint_texture: Option<Arc<Texture+Send+Sync>>,
+    pub ior_texture: Option<Arc<Texture+Send+Sync>>,
+    pub emission_texture: Option<Arc<Texture+Send+Sync>>
+}
+
+/// `value` times the texture's red channel at (u, v)
+fn textured(value: f64, texture: &Option<Arc<Texture+Send+Sync>>, u: f64, v: f64) -> f64 {
+    match *texture {
+        Some(ref x) => value * x.color(u, v).r,
+        None => value
+    }
+}
+
+/// `color` times the texture's color at (u, v)
+fn textured_color(color: Vec3, texture: &Option<Arc<Texture+Send+Sync>>, u: f64, v: f64) -> Vec3 {
+    match *texture {
+        Some(ref x) => color * x.color(u, v).to_vec3(),
+        None => color
+    }
+}
+
+fn alpha(roughness: f64) -> f64 {
+    (roughness * roughness).max(1e-3)
+}
+
+/// GGX microfacet reflection, D G / (4 n_dot_v n_dot_l) without the Fresnel term
+fn microfacet(n_dot_h: f64, n_dot_v: f64, n_dot_l: f64, alpha: f64) -> f64 {
+    bsdf::ggx(n_dot_h, alpha) * bsdf::smith_g1(n_dot_v, alpha) * bsdf::smith_g1(n_dot_l, alpha) /
+        (4.0 * n_dot_v * n_dot_l)
+}
+
+impl PrincipledMaterial {
+    fn base_color(&self, u: f64, v: f64) -> Vec3 {
+        textured_color(self.base_color, &self.base_color_texture, u, v)
+    }
+
+    fn metalness(&self, u: f64, v: f64) -> f64 {
+        textured(self.metalness, &self.metalness_texture, u, v)
+    }
+
+    fn specular_transmission(&self, u: f64, v: f64) -> f64 {
+        textured(self.specular_transmission, &self.specular_transmission_texture, u, v)
+    }
+
+    /// Color of the light let through the clear parts
+    fn transmittance(&self, u: f64, v: f64) -> Vec3 {
+        self.base_color(u, v).scale(self.specular_transmission(u, v) * (1.0 - self.metalness(u, v)))
+    }
+
+    /// Chances of sampling the diffuse base, the specular layer and the
+    /// clearcoat. Textures are left out so `lobe_pdf` can tell them too.
+    fn layer_probabilities(&self) -> (f64, f64, f64) {
+        let diffuse = (1.0 - self.metalness) * (1.0 - self.specular_transmission);
+        let clearcoat = 0.25 * self.clearcoat;
+        let total = diffuse + 1.0 + clearcoat;
+
+        (diffuse / total, 1.0 / total, clearcoat / total)
+    }
+}
+
+impl Material for PrincipledMaterial {
+    fn eval(&self, n: &Vec3, wo: &Vec3, wi: &Vec3, u: f64, v: f64) -> Vec3 {
+        let n = bsdf::facing(n, wo);
+        let n_dot_v = n.dot(wo);
+        let n_dot_l = n.dot(wi);
+        if n_dot_v <= 0.0 || n_dot_l <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let h = (*wo + *wi).unit();
+        let n_dot_h = n.dot(&h);
+        let cos_d = wi.dot(&h);
+
+        let base_color = self.base_color(u, v);
+        let metalness = self.metalness(u, v);
+        let roughness = textured(self.roughness, &self.roughness_texture, u, v);
+        let specular_level = textured(self.specular_level, &self.specular_level_texture, u, v);
+        let clearcoat = textured(self.clearcoat, &self.clearcoat_texture, u, v);
+        let clearcoat_roughness = textured(self.clearcoat_roughness, &self.clearcoat_roughness_texture, u, v);
+        let sheen = textured(self.sheen, &self.sheen_texture, u, v);
+        let sheen_tint = textured(self.sheen_tint, &self.sheen_tint_texture, u, v);
+        let specular_transmission = self.specular_transmission(u, v);
+
+        // Burley's diffuse, which brightens rough surfaces at grazing angles
+        let fd90 = 0.5 + 2.0 * roughness * cos_d * cos_d;
+        let retro = (1.0 + (fd90 - 1.0) * (1.0 - n_dot_l).powi(5)) * (1.0 + (fd90 - 1.0) * (1.0 - n_dot_v).powi(5));
+        let diffuse = base_color.scale(retro * (1.0 - specular_transmission) / PI);
+
+        let base_luminance = luminance(base_color.x, base_color.y, base_color.z);
+        let tint = if base_luminance > 0.0 { base_color.scale(1.0 / base_luminance) } else { Vec3::one() };
+        let sheen_color = Vec3::lerp(&Vec3::one(), &tint, sheen_tint).scale(sheen * (1.0 - cos_d).powi(5));
+
+        let f0 = Vec3::lerp(&Vec3::one().scale(0.08 * specular_level), &base_color, metalness);
+        let specular = bsdf::schlick(&f0, cos_d).scale(microfacet(n_dot_h, n_dot_v, n_dot_l, alpha(roughness)));
+
+        // The clearcoat is a thin layer of IOR 1.5
+        let coat_fresnel = bsdf::schlick(&Vec3::one().scale(0.04), cos_d).x;
+        let coat = clearcoat * coat_fresnel *
+            microfacet(n_dot_h, n_dot_v, n_dot_l, alpha(clearcoat_roughness));
+
+        ((diffuse + sheen_color).scale(1.0 - metalness) + specular + Vec3::one().scale(coat)).scale(n_dot_l)
+    }
+
+    fn has_lobe(&self, lobe: Lobe) -> bool {
+        match lobe {
+            Lobe::Diffuse => true,
+            Lobe::Reflection => false,
+            Lobe::Transmission => self.specular_transmission > 0.0 && self.metalness < 1.0
+        }
+    }
+
+    fn sample_lobe(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, u: f64, v: f64,
+                   sampler: &mut Sampler) -> Option<BsdfSample> {
+        match lobe {
+            Lobe::Diffuse => {
+                let facing_n = bsdf::facing(n, wo);
+                let (p_diffuse, p_specular, _) = self.layer_probabilities();
+                let layer = sampler.next_1d();
+                let (u1, u2) = sampler.next_2d();
+                let wi = if layer < p_diffuse {
+                    Vec3::cosine_hemisphere(&facing_n, u1, u2)
+                } else {
+                    let roughness = if layer < p_diffuse + p_specular { self.roughness } else { self.clearcoat_roughness };
+                    Vec3::reflect(wo, &bsdf::sample_ggx(&facing_n, alpha(roughness), u1, u2))
+                };
+
+                let pdf = self.lobe_pdf(lobe, n, wo, &wi);
+                if pdf <= 0.0 || facing_n.dot(&wi) <= 0.0 {
+                    return None;
+                }
+
+                Some(BsdfSample { wi: wi, value: self.eval(n, wo, &wi, u, v), pdf: pdf, lobe: lobe, delta: false })
+            },
+            Lobe::Reflection => None,
+            Lobe::Transmission => {
+                let ior = textured(self.ior, &self.ior_texture, u, v);
+                Some(bsdf::sample_transmission(n, wo, ior, self.transmittance(u, v)))
+            }
+        }
+    }
+
+    fn lobe_pdf(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, wi: &Vec3) -> f64 {
+        if lobe != Lobe::Diffuse {
+            return 0.0;
+        }
+
+        let (p_diffuse, p_specular, p_clearcoat) = self.layer_probabilities();
+        p_diffuse * bsdf::cosine_pdf(n, wo, wi) +
+            p_specular * bsdf::reflection_pdf(n, wo, wi, alpha(self.roughness)) +
+            p_clearcoat * bsdf::reflection_pdf(n, wo, wi, alpha(self.clearcoat_roughness))
+    }
+
+    /// Clear parts let light through to shadow rays
+    fn transmission(&self, u: f64, v: f64) -> Vec3 {
+        self.transmittance(u, v)
+    }
+
+    fn emission(&self, u: f64, v: f64) -> Vec3 {
+        textured_color(self.emission, &self.emission_texture, u, v)
+    }
+}
+
+impl Default for PrincipledMaterial {
+    fn default() -> PrincipledMaterial {
+        PrincipledMaterial {
+            base_color: Vec3 { x: 0.8, y: 0.8, z: 0.8 },
+            metalness: 0.0,
+            roughness: 0.5,
+            specular_level: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            specular_transmission: 0.0,
+            ior: 1.5,
+            emission: Vec3::zero(),
+            base_color_texture: None,
+            metalness_texture: None,
+            roughness_texture: None,
+            specular_level_texture: None,
+            clearcoat_texture: None,
+            sheen_texture: None,
+            specular_transmission_texture: None,
+            clearcoat_roughness_texture: None,
+            sheen_tint_texture: None,
+            ior_texture: None,
+            emission_texture: None
+        }
+    }
+}
+
+#[test]
+fn it_reads_parameters_from_textures() {
+    use material::textures::CheckerTexture;
+    use raytracer::compositor::ColorRGBA;
+
+    // Black at (0.25, 0.25), white at (0.75, 0.25)
+    let checker = CheckerTexture { color1: ColorRGBA::white(), color2: ColorRGBA::black(), scale: 1.0 };
+    let metal = PrincipledMaterial { base_color: Vec3 { x: 1.0, y: 0.5, z: 0.0 }, metalness: 1.0,
+                                     metalness_texture: Some(Arc::new(checker)), ..Default::default() };
+    let plastic = PrincipledMaterial { base_color: metal.base_color, ..Default::default() };
+
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let wo = Vec3 { x: 0.6, y: 0.0, z: 0.8 };
+    let wi = Vec3 { x: -0.6, y: 0.0, z: 0.8 };
+    assert_eq!(plastic.eval(&n, &wo, &wi, 0.0, 0.0), metal.eval(&n, &wo, &wi, 0.25, 0.25));
+    let gold = metal.eval(&n, &wo, &wi, 0.75, 0.25);
+    assert!(gold.x > 0.0 && gold.z < 0.01 * gold.x);
+}
+
+#[test]
+fn it_transmits_through_clear_parts() {
+    use raytracer::sampler::SamplerKind;
+
+    let glass = PrincipledMaterial { base_color: Vec3::one(), specular_transmission: 1.0, roughness: 0.0,
+                                     ..Default::default() };
+    assert!(glass.has_lobe(Lobe::Transmission));
+    assert!(!PrincipledMaterial { ..Default::default() }.has_lobe(Lobe::Transmission));
+
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+    let sample = glass.sample_lobe(Lobe::Transmission, &n, &n, 0.0, 0.0, &mut *sampler).unwrap();
+    assert!(sample.delta);
+    assert!((sample.wi.z + 1.0).abs() < 1e-12);
+    assert!((sample.value.x - 0.96).abs() < 1e-12);
+}
+
+#[test]
+fn it_lets_light_through_where_the_texture_is_clear() {
+    use material::textures::CheckerTexture;
+    use raytracer::compositor::ColorRGBA;
+
+    // Black at (0.25, 0.25), white at (0.75, 0.25)
+    let checker = || Some(Arc::new(CheckerTexture { color1: ColorRGBA::white(), color2: ColorRGBA::black(),
+                                                    scale: 1.0 }) as Arc<Texture+Send+Sync>);
+    let glass = PrincipledMaterial { base_color: Vec3 { x: 1.0, y: 0.5, z: 0.0 }, specular_transmission: 1.0,
+                                     base_color_texture: checker(), emission: Vec3::one(),
+                                     emission_texture: checker(), ..Default::default() };
+
+    assert_eq!(glass.transmission(0.25, 0.25), Vec3::zero());
+    assert_eq!(glass.transmission(0.75, 0.25), glass.base_color);
+    assert_eq!(glass.emission(0.25, 0.25), Vec3::zero());
+    assert_eq!(glass.emission(0.75, 0.25), Vec3::one());
+}
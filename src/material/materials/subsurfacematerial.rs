@@ -0,0 +1,150 @@
+use material::Material;
+use material::bsdf::{self, BsdfSample, Lobe};
+use medium::{HenyeyGreenstein, Medium};
+use medium::media::HomogeneousMedium;
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Marble, wax, skin or milk: a smooth surface refracting light into a closed
+/// mesh, inside which it is scattered about before it finds its way out.
+///
+/// Every collision inside scatters or absorbs light in proportion to the
+/// `scattering` and `absorption` colours, which should add up to at most 1;
+/// when they add up to 1 light travels `mean_free_path` between collisions on
+/// average. The path tracer follows the whole random walk, Whitted only light
+/// scattered once.
+#[derive(Clone)]
+pub struct SubsurfaceMaterial {
+    pub ior: f64,            // Index of refraction of the surface
+    pub scattering: Vec3,    // Fraction of collisions that scatter light
+    pub absorption: Vec3,    // Fraction of collisions that absorb light
+    pub mean_free_path: f64, // Average distance between collisions, in scene units
+    pub g: f64               // Henyey-Greenstein asymmetry inside, 0 scatters evenly in all directions
+}
+
+impl SubsurfaceMaterial {
+    fn interior(&self) -> HomogeneousMedium {
+        HomogeneousMedium {
+            absorption: self.absorption.scale(1.0 / self.mean_free_path),
+            scattering: self.scattering.scale(1.0 / self.mean_free_path),
+            g: self.g,
+            bounds: None
+        }
+    }
+}
+
+impl Material for SubsurfaceMaterial {
+    fn eval(&self, _n: &Vec3, _wo: &Vec3, _wi: &Vec3, _u: f64, _v: f64) -> Vec3 {
+        Vec3::zero()
+    }
+
+    fn has_lobe(&self, lobe: Lobe) -> bool {
+        lobe != Lobe::Diffuse
+    }
+
+    fn sample_lobe(&self, lobe: Lobe, n: &Vec3, wo: &Vec3, _u: f64, _v: f64,
+                   _sampler: &mut Sampler) -> Option<BsdfSample> {
+        let fresnel = bsdf::fresnel(self.ior, wo, n);
+        let (wi, value) = match lobe {
+            Lobe::Reflection => (Vec3::reflect(wo, n), Vec3::one().scale(fresnel)),
+            Lobe::Transmission => match Vec3::refract(wo, n, self.ior, n.dot(wo) < 0.0) {
+                Some(t) => (t, Vec3::one().scale(1.0 - fresnel)),
+                None => return None
+            },
+            Lobe::Diffuse => return None
+        };
+
+        Some(BsdfSample { wi: wi, value: value, pdf: 1.0, lobe: lobe, delta: true })
+    }
+
+    /// Reflects or refracts in proportion to the Fresnel reflectance
+    fn sample(&self, n: &Vec3, wo: &Vec3, u: f64, v: f64, sampler: &mut Sampler) -> Option<BsdfSample> {
+        let fresnel = bsdf::fresnel(self.ior, wo, n);
+        let (lobe, probability) = if sampler.next_1d() < fresnel {
+            (Lobe::Reflection, fresnel)
+        } else {
+            (Lobe::Transmission, 1.0 - fresnel)
+        };
+
+        self.sample_lobe(lobe, n, wo, u, v, sampler).map(|mut sample| {
+            sample.pdf = probability;
+            sample
+        })
+    }
+
+    /// Shadow rays go through the surface; the interior dims them
+    fn transmission(&self, _u: f64, _v: f64) -> Vec3 {
+        Vec3::one()
+    }
+
+    fn medium(&self) -> Option<&Medium> {
+        Some(self)
+    }
+}
+
+impl Medium for SubsurfaceMaterial {
+    fn coefficients(&self, position: &Vec3) -> (Vec3, Vec3) {
+        self.interior().coefficients(position)
+    }
+
+    fn majorant(&self) -> f64 {
+        self.interior().majorant()
+    }
+
+    fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        self.interior().clip(ray, t_min, t_max)
+    }
+
+    fn phase(&self) -> HenyeyGreenstein {
+        HenyeyGreenstein { g: self.g }
+    }
+
+    fn transmittance(&self, ray: &Ray, t_max: f64, sampler: &mut Sampler) -> Vec3 {
+        self.interior().transmittance(ray, t_max, sampler)
+    }
+
+    fn sample_scattering(&self, ray: &Ray, t_max: f64, sampler: &mut Sampler) -> Option<(f64, Vec3)> {
+        self.interior().sample_scattering(ray, t_max, sampler)
+    }
+}
+
+impl Default for SubsurfaceMaterial {
+    /// White marble
+    fn default() -> SubsurfaceMaterial {
+        SubsurfaceMaterial {
+            ior: 1.5,
+            scattering: Vec3 { x: 0.998, y: 0.997, z: 0.995 },
+            absorption: Vec3 { x: 0.002, y: 0.003, z: 0.005 },
+            mean_free_path: 0.1,
+            g: 0.0
+        }
+    }
+}
+
+#[test]
+fn it_collides_a_mean_free_path_apart() {
+    use raytracer::sampler::SamplerKind;
+
+    // Wax that only scatters, so every collision scatters
+    let wax = SubsurfaceMaterial { scattering: Vec3::one(), absorption: Vec3::zero(), mean_free_path: 0.5,
+                                   ..Default::default() };
+    let ray = Ray::new(Vec3::zero(), Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+    let mut sampler = SamplerKind::Independent.build(1, 5);
+
+    let runs = 20000;
+    let mean = (0..runs).fold(0.0, |total, _| {
+        let collision = Medium::sample(&wax, &ray, 1000.0, &mut *sampler);
+        assert_eq!(collision.weight, Vec3::one());
+        total + collision.scattered.unwrap() / runs as f64
+    });
+    assert!((mean - 0.5).abs() < 0.01, "mean free path {}", mean);
+
+    // What the surface does not reflect goes in
+    let n = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+    let wo = Vec3 { x: 0.6, y: 0.0, z: 0.8 };
+    let reflected = wax.sample_lobe(Lobe::Reflection, &n, &wo, 0.0, 0.0, &mut *sampler).unwrap();
+    let refracted = wax.sample_lobe(Lobe::Transmission, &n, &wo, 0.0, 0.0, &mut *sampler).unwrap();
+    assert!((reflected.value.x + refracted.value.x - 1.0).abs() < 1e-12);
+    assert!(refracted.wi.z < 0.0);
+}
@@ -1,16 +1,29 @@
+pub use self::conductor::Conductor;
+pub use self::environment::Environment;
 pub use self::material::Material;
 pub use self::texture::Texture;
+pub mod bsdf;
+pub mod conductor;
+pub mod environment;
 pub mod material;
 pub mod texture;
 
 pub mod materials {
     pub use self::cooktorrancematerial::CookTorranceMaterial;
+    pub use self::dielectricmaterial::DielectricMaterial;
     pub use self::flatmaterial::FlatMaterial;
+    pub use self::ggxmaterial::GgxMaterial;
     pub use self::phongmaterial::PhongMaterial;
+    pub use self::principledmaterial::PrincipledMaterial;
+    pub use self::subsurfacematerial::SubsurfaceMaterial;
 
     mod cooktorrancematerial;
+    mod dielectricmaterial;
     mod flatmaterial;
+    mod ggxmaterial;
     mod phongmaterial;
+    mod principledmaterial;
+    mod subsurfacematerial;
 }
 
 pub mod textures {
@@ -18,9 +31,11 @@ pub mod textures {
     pub use self::uvtexture::UVTexture;
     pub use self::imagetexture::ImageTexture;
     pub use self::cubemap::CubeMap;
+    pub use self::equirectmap::EquirectMap;
 
     mod checkertexture;
     mod uvtexture;
     mod imagetexture;
     mod cubemap;
+    mod equirectmap;
 }
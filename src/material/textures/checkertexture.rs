@@ -21,14 +21,6 @@ impl Texture for CheckerTexture {
             self.color2
         }
     }
-
-    fn clone_self(&self) -> Box<Texture+Send+Sync> {
-        Box::new(CheckerTexture {
-            color1: self.color1,
-            color2: self.color2,
-            scale: self.scale
-        }) as Box<Texture+Send+Sync>
-    }
 }
 
 impl CheckerTexture {
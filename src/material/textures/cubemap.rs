@@ -1,3 +1,4 @@
+use material::Environment;
 use material::textures::ImageTexture;
 use std::sync::mpsc::channel;
 use std::thread;
@@ -11,15 +12,14 @@ pub struct CubeMap {
 impl CubeMap {
     /// For y-axis as up, load: left, right, down, up, front, back
     #[allow(dead_code)]
-    pub fn load(x: &str, x_neg: &str, y: &str, y_neg: &str, z: &str, z_neg: &str) -> CubeMap {
+    pub fn load(x: &str, x_neg: &str, y: &str, y_neg: &str, z: &str, z_neg: &str) -> Result<CubeMap, String> {
         let filenames = vec![
             x.clone(), x_neg.clone(),
             y.clone(), y_neg.clone(),
             z.clone(), z_neg.clone()
         ];
 
-        let mut faces: Vec<ImageTexture> = Vec::with_capacity(6);
-        unsafe { faces.set_len(6); }
+        let mut faces: Vec<Option<ImageTexture>> = (0..6).map(|_| None).collect();
 
         let (tx, rx) = channel();
 
@@ -34,15 +34,15 @@ impl CubeMap {
         drop(tx);
 
         for (i, tex) in rx {
-            let p = faces.as_mut_ptr();
-            unsafe { ::std::ptr::write::<ImageTexture>(p.offset(i as isize), tex); }
+            faces[i] = Some(try!(tex));
         }
 
-        CubeMap { faces: faces }
+        Ok(CubeMap { faces: faces.into_iter().map(|face| face.unwrap()).collect() })
     }
+}
 
-    #[allow(dead_code)]
-    pub fn color(&self, dir: Vec3) -> Vec3 {
+impl Environment for CubeMap {
+    fn color(&self, dir: Vec3) -> Vec3 {
         let x_mag = dir.x.abs();
         let y_mag = dir.y.abs();
         let z_mag = dir.z.abs();
@@ -0,0 +1,45 @@
+use std::f64::consts::PI;
+use material::Environment;
+use material::textures::ImageTexture;
+use vec3::Vec3;
+
+/// Environment in a single latitude-longitude image, y-axis up. The top row
+/// is straight up, the middle of the image looks along -z.
+#[derive(Clone)]
+pub struct EquirectMap {
+    pub image: ImageTexture
+}
+
+impl EquirectMap {
+    /// Loads any image ImageTexture can, .hdr files keep their full range
+    pub fn load(filename: &str) -> Result<EquirectMap, String> {
+        Ok(EquirectMap { image: try!(ImageTexture::load(filename)) })
+    }
+
+    /// Image (u, v) for a direction
+    pub fn uv(dir: &Vec3) -> (f64, f64) {
+        let dir = dir.unit();
+        let phi = dir.x.atan2(-dir.z);
+        let theta = dir.y.max(-1.0).min(1.0).acos();
+        (0.5 + phi / (2.0 * PI), theta / PI)
+    }
+}
+
+impl Environment for EquirectMap {
+    fn color(&self, dir: Vec3) -> Vec3 {
+        // ImageTexture wraps coordinates around, v of 1 would be the top row
+        let (u, v) = EquirectMap::uv(&dir);
+        self.image.sample(u, v.min(1.0 - 1e-9))
+    }
+}
+
+#[test]
+fn it_maps_directions_to_latitude_and_longitude() {
+    let (u, v) = EquirectMap::uv(&Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+    assert_eq!(0.0, v);
+    assert!(u >= 0.0 && u <= 1.0);
+
+    assert_eq!((0.5, 0.5), EquirectMap::uv(&Vec3 { x: 0.0, y: 0.0, z: -1.0 }));
+    assert_eq!((0.75, 0.5), EquirectMap::uv(&Vec3 { x: 2.0, y: 0.0, z: 0.0 }));
+    assert_eq!(1.0, EquirectMap::uv(&Vec3 { x: 0.0, y: -1.0, z: 0.0 }).1);
+}
@@ -1,17 +1,39 @@
 use vec3::Vec3;
 use material::Texture;
 use raytracer::compositor::{Surface, ColorRGBA};
+use raytracer::compositor::tonemap::srgb_to_linear;
 
 /// Maps the supplied (u, v) coordinate to the image (s, t).
+/// The image is kept as linear values so filtering and shading happen in linear space.
 #[derive(Clone)]
 pub struct ImageTexture {
-    pub image: Surface
+    pub image: Surface<f64>
 }
 
 impl ImageTexture {
-    #[allow(dead_code)]
-    pub fn load(filename: &str) -> ImageTexture {
-        ImageTexture { image: ::util::import::from_image(filename).unwrap() }
+    /// Radiance .hdr files are linear already, everything else is sRGB
+    pub fn load(filename: &str) -> Result<ImageTexture, String> {
+        let texture = if filename.to_lowercase().ends_with(".hdr") {
+            ::util::import::from_hdr(filename).map(|image| ImageTexture { image: image })
+        } else {
+            ::util::import::from_image(filename).map(|image| ImageTexture::from_srgb(&image))
+        };
+        texture.map_err(|err| format!("{}: {}", filename, err))
+    }
+
+    /// Decodes an 8-bit sRGB image (PNGs, JPEGs...) into linear values
+    pub fn from_srgb(image: &Surface) -> ImageTexture {
+        let mut linear = Surface::new(image.width, image.height, ColorRGBA::transparent());
+
+        for (dst, src) in linear.iter_pixels_mut().zip(image.buffer.iter()) {
+            let color = src.channel_f64();
+            *dst = ColorRGBA::new_rgba(srgb_to_linear(color.r),
+                                       srgb_to_linear(color.g),
+                                       srgb_to_linear(color.b),
+                                       color.a);
+        }
+
+        ImageTexture { image: linear }
     }
 
     // Alias, used by skybox sampling. This is needed because we aren't storing the skybox
@@ -45,13 +67,6 @@ impl Texture for ImageTexture {
             ) * v_ratio
         )
     }
-
-    fn clone_self(&self) -> Box<Texture+Send+Sync> {
-        let tex: Box<Texture+Send+Sync> = Box::new(ImageTexture {
-            image: self.image.clone()
-        });
-        tex
-    }
 }
 
 #[test]
@@ -64,7 +79,7 @@ fn it_bilinearly_filters() {
     surface[(1, 0)] = ColorRGBA::new_rgb(0, 0, 255);
     surface[(1, 1)] = ColorRGBA::new_rgb(0, 0, 0);
 
-    let texture = ImageTexture { image: surface };
+    let texture = ImageTexture::from_srgb(&surface);
 
     let left = texture.color(0.0, 0.5);
     assert_eq!(left.r, 0.5);
@@ -76,3 +91,15 @@ fn it_bilinearly_filters() {
     assert_eq!(center.g, 0.25);
     assert_eq!(center.b, 0.25);
 }
+
+#[test]
+fn it_linearises_srgb_images() {
+    let mut surface = Surface::new(1, 1, ColorRGBA::new_rgb(0, 0, 0));
+    surface[(0, 0)] = ColorRGBA::new_rgb(128, 255, 0);
+
+    let texture = ImageTexture::from_srgb(&surface);
+    let color = texture.image[(0, 0)];
+    assert!((color.r - 0.2158605).abs() < 1e-6);
+    assert_eq!(color.g, 1.0);
+    assert_eq!(color.b, 0.0);
+}
@@ -12,8 +12,4 @@ impl Texture for UVTexture {
     	let range = <f64 as Channel>::max_value() - min_value;
     	ColorRGBA::new_rgb(u % range + min_value, v % range + min_value, min_value)
     }
-
-    fn clone_self(&self) -> Box<Texture+Send+Sync> {
-        Box::new(UVTexture) as Box<Texture+Send+Sync>
-    }
 }
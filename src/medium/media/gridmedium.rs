@@ -0,0 +1,117 @@
+use geometry::BBox;
+use medium::{HenyeyGreenstein, Medium};
+use raytracer::Ray;
+use vec3::Vec3;
+
+/// Smoke or clouds of varying density: a grid of `size` voxels filling
+/// `bounds`, interpolated trilinearly. The coefficients are those at a
+/// density of 1. Load densities with `util::import::from_voxels`.
+#[derive(Clone)]
+pub struct GridMedium {
+    pub absorption: Vec3,   // Absorption coefficient per unit distance at density 1
+    pub scattering: Vec3,   // Scattering coefficient per unit distance at density 1
+    pub g: f64,             // Henyey-Greenstein asymmetry, 0 scatters evenly in all directions
+    pub bounds: BBox,
+    pub size: [usize; 3],   // Voxels along x, y and z
+    pub density: Vec<f64>,  // x varies fastest, then y, then z
+    max_density: f64
+}
+
+impl GridMedium {
+    pub fn new(absorption: Vec3, scattering: Vec3, g: f64, bounds: BBox, size: [usize; 3], density: Vec<f64>)
+               -> GridMedium {
+        assert_eq!(size[0] * size[1] * size[2], density.len());
+        let max_density = density.iter().fold(0.0, |max: f64, &d| max.max(d));
+
+        GridMedium {
+            absorption: absorption,
+            scattering: scattering,
+            g: g,
+            bounds: bounds,
+            size: size,
+            density: density,
+            max_density: max_density
+        }
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.density[(z * self.size[1] + y) * self.size[0] + x]
+    }
+
+    /// Density at `position`, with voxel values at voxel centers
+    pub fn density_at(&self, position: &Vec3) -> f64 {
+        if !self.bounds.inside(position) {
+            return 0.0;
+        }
+
+        let offset = self.bounds.offset(position);
+        let mut cell = [0; 3];
+        let mut fraction = [0.0; 3];
+        for (axis, &o) in [offset.x, offset.y, offset.z].iter().enumerate() {
+            let p = (o * self.size[axis] as f64 - 0.5).max(0.0).min(self.size[axis] as f64 - 1.0);
+            cell[axis] = (p as usize).min(self.size[axis].saturating_sub(2));
+            fraction[axis] = p - cell[axis] as f64;
+        }
+
+        let next = |axis: usize| if self.size[axis] > 1 { cell[axis] + 1 } else { cell[axis] };
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let (x0, y0, z0) = (cell[0], cell[1], cell[2]);
+        let (x1, y1, z1) = (next(0), next(1), next(2));
+
+        let d00 = lerp(self.voxel(x0, y0, z0), self.voxel(x1, y0, z0), fraction[0]);
+        let d10 = lerp(self.voxel(x0, y1, z0), self.voxel(x1, y1, z0), fraction[0]);
+        let d01 = lerp(self.voxel(x0, y0, z1), self.voxel(x1, y0, z1), fraction[0]);
+        let d11 = lerp(self.voxel(x0, y1, z1), self.voxel(x1, y1, z1), fraction[0]);
+
+        lerp(lerp(d00, d10, fraction[1]), lerp(d01, d11, fraction[1]), fraction[2])
+    }
+}
+
+impl Medium for GridMedium {
+    fn coefficients(&self, position: &Vec3) -> (Vec3, Vec3) {
+        let density = self.density_at(position);
+        (self.absorption.scale(density), self.scattering.scale(density))
+    }
+
+    fn majorant(&self) -> f64 {
+        let extinction = self.absorption + self.scattering;
+        self.max_density * extinction.x.max(extinction.y).max(extinction.z)
+    }
+
+    fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        self.bounds.intersection_range(ray).and_then(|(t_start, t_end)| {
+            let (t_start, t_end) = (t_start.max(t_min), t_end.min(t_max));
+            if t_start < t_end { Some((t_start, t_end)) } else { None }
+        })
+    }
+
+    fn phase(&self) -> HenyeyGreenstein {
+        HenyeyGreenstein { g: self.g }
+    }
+}
+
+#[test]
+fn it_interpolates_between_voxels() {
+    use raytracer::sampler::SamplerKind;
+
+    // Two voxels along x, thin on the left and thick on the right
+    let bounds = BBox { min: Vec3::zero(), max: Vec3 { x: 2.0, y: 1.0, z: 1.0 } };
+    let smoke = GridMedium::new(Vec3::one(), Vec3::zero(), 0.0, bounds, [2, 1, 1], vec![0.5, 1.5]);
+
+    let at = |x: f64| smoke.density_at(&Vec3 { x: x, y: 0.5, z: 0.5 });
+    assert_eq!(at(0.2), 0.5);
+    assert_eq!(at(1.0), 1.0);
+    assert_eq!(at(1.8), 1.5);
+    assert_eq!(at(2.5), 0.0);
+    assert_eq!(smoke.majorant(), 1.5);
+
+    // Across the whole grid the optical depth is the integral of the density,
+    // 0.25 + 1.0 + 0.75 between the edges and voxel centers
+    let ray = Ray::new(Vec3 { x: -1.0, y: 0.5, z: 0.5 }, Vec3 { x: 1.0, y: 0.0, z: 0.0 });
+    let mut sampler = SamplerKind::Independent.build(1, 3);
+    let runs = 20000;
+    let transmittance = (0..runs).fold(0.0, |total, _| {
+        total + smoke.transmittance(&ray, 10.0, &mut *sampler).x / runs as f64
+    });
+    assert!((transmittance - (-2.0f64).exp()).abs() < 0.01, "transmittance {}", transmittance);
+}
@@ -0,0 +1,143 @@
+use geometry::BBox;
+use medium::{HenyeyGreenstein, Medium};
+use medium::medium::mean;
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Fog of the same density everywhere, or everywhere within `bounds`
+#[derive(Clone)]
+pub struct HomogeneousMedium {
+    pub absorption: Vec3, // Absorption coefficient per unit distance
+    pub scattering: Vec3, // Scattering coefficient per unit distance
+    pub g: f64,           // Henyey-Greenstein asymmetry, 0 scatters evenly in all directions
+    pub bounds: Option<BBox>
+}
+
+impl HomogeneousMedium {
+    fn extinction(&self) -> Vec3 {
+        self.absorption + self.scattering
+    }
+}
+
+impl Medium for HomogeneousMedium {
+    fn coefficients(&self, _position: &Vec3) -> (Vec3, Vec3) {
+        (self.absorption, self.scattering)
+    }
+
+    fn majorant(&self) -> f64 {
+        let extinction = self.extinction();
+        extinction.x.max(extinction.y).max(extinction.z)
+    }
+
+    fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let (t_start, t_end) = match self.bounds {
+            Some(ref bounds) => match bounds.intersection_range(ray) {
+                Some((t_start, t_end)) => (t_start.max(t_min), t_end.min(t_max)),
+                None => return None
+            },
+            None => (t_min, t_max)
+        };
+
+        if t_start < t_end { Some((t_start, t_end)) } else { None }
+    }
+
+    fn phase(&self) -> HenyeyGreenstein {
+        HenyeyGreenstein { g: self.g }
+    }
+
+    /// Beer-Lambert's law
+    fn transmittance(&self, ray: &Ray, t_max: f64, _sampler: &mut Sampler) -> Vec3 {
+        let distance = match self.clip(ray, 0.0, t_max) {
+            Some((t_start, t_end)) => (t_end - t_start) * ray.direction.len(),
+            None => return Vec3::one()
+        };
+
+        let extinction = self.extinction();
+        Vec3 {
+            x: (-extinction.x * distance).exp(),
+            y: (-extinction.y * distance).exp(),
+            z: (-extinction.z * distance).exp()
+        }
+    }
+
+    /// Picks distances in proportion to the mean transmittance, so thick fog
+    /// is sampled near the ray's origin where most of the light comes from
+    fn sample_scattering(&self, ray: &Ray, t_max: f64, sampler: &mut Sampler) -> Option<(f64, Vec3)> {
+        let (t_start, t_end) = match self.clip(ray, 0.0, t_max) {
+            Some(range) => range,
+            None => return None
+        };
+
+        let sigma = mean(&self.extinction());
+        if sigma <= 0.0 || self.scattering == Vec3::zero() {
+            return None;
+        }
+
+        // Exponential distances, cut off at the end of the range
+        let speed = ray.direction.len();
+        let cutoff = 1.0 - (-sigma * (t_end - t_start) * speed).exp();
+        let distance = -(1.0 - sampler.next_1d() * cutoff).ln() / sigma;
+        let pdf = sigma * (-sigma * distance).exp() / cutoff;
+
+        let t = t_start + distance / speed;
+        Some((t, self.transmittance(ray, t, sampler) * self.scattering.scale(1.0 / pdf)))
+    }
+}
+
+#[test]
+fn it_tracks_the_closed_forms() {
+    use medium::medium::MediumSample;
+    use raytracer::sampler::SamplerKind;
+
+    // Bluish fog that absorbs red, the estimators of the trait should agree
+    // with the closed forms on average
+    struct Tracked(HomogeneousMedium);
+    impl Medium for Tracked {
+        fn coefficients(&self, position: &Vec3) -> (Vec3, Vec3) { self.0.coefficients(position) }
+        fn majorant(&self) -> f64 { self.0.majorant() }
+        fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> { self.0.clip(ray, t_min, t_max) }
+        fn phase(&self) -> HenyeyGreenstein { self.0.phase() }
+    }
+
+    let fog = HomogeneousMedium {
+        absorption: Vec3 { x: 0.3, y: 0.0, z: 0.0 },
+        scattering: Vec3 { x: 0.1, y: 0.2, z: 0.3 },
+        g: 0.0,
+        bounds: None
+    };
+    let tracked = Tracked(fog.clone());
+    let ray = Ray::new(Vec3::zero(), Vec3 { x: 0.0, y: 0.0, z: 2.0 });
+    let mut sampler = SamplerKind::Independent.build(1, 7);
+
+    let exact = fog.transmittance(&ray, 1.5, &mut *sampler);
+    assert!((exact.x - (-1.2f64).exp()).abs() < 1e-12);
+
+    let runs = 20000;
+    let (mut ratio, mut passed, mut scattered, mut gathered) = (Vec3::zero(), Vec3::zero(), Vec3::zero(), Vec3::zero());
+    for _ in 0..runs {
+        ratio = ratio + tracked.transmittance(&ray, 1.5, &mut *sampler).scale(1.0 / runs as f64);
+        match tracked.sample(&ray, 1.5, &mut *sampler) {
+            MediumSample { scattered: Some(_), weight } => scattered = scattered + weight.scale(1.0 / runs as f64),
+            MediumSample { scattered: None, weight } => passed = passed + weight.scale(1.0 / runs as f64)
+        }
+        if let Some((_, weight)) = fog.sample_scattering(&ray, 1.5, &mut *sampler) {
+            gathered = gathered + weight.scale(1.0 / runs as f64);
+        }
+    }
+
+    // Light scattered once somewhere along the ray: the integral of the
+    // transmittance times the scattering coefficient
+    let extinction = fog.extinction();
+    let once = Vec3 {
+        x: fog.scattering.x / extinction.x * (1.0 - exact.x),
+        y: fog.scattering.y / extinction.y * (1.0 - exact.y),
+        z: fog.scattering.z / extinction.z * (1.0 - exact.z)
+    };
+
+    for &(estimate, expected) in [(ratio, exact), (passed, exact), (scattered, once), (gathered, once)].iter() {
+        for &(a, b) in [(estimate.x, expected.x), (estimate.y, expected.y), (estimate.z, expected.z)].iter() {
+            assert!((a - b).abs() < 0.02, "{:?} should be {:?}", estimate, expected);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use medium::HenyeyGreenstein;
+use raytracer::Ray;
+use raytracer::sampler::Sampler;
+use vec3::Vec3;
+
+/// Where a ray travelling through a medium was scattered, if anywhere
+pub struct MediumSample {
+    /// Distance along the ray to the scattering point, None if the ray got
+    /// through to its end
+    pub scattered: Option<f64>,
+    /// What light carried by the ray is multiplied with, zero if it was absorbed
+    pub weight: Vec3
+}
+
+/// Fog, smoke or anything else light is absorbed and scattered in on its way
+/// between surfaces. Coefficients are per unit distance; `t`s are along rays
+/// whose direction need not be a unit vector.
+///
+/// Only the coefficients, a majorant and the bounds are needed: transmittance
+/// and scattering are estimated by tracking null collisions against the
+/// majorant, which media with closed forms can override.
+pub trait Medium {
+    /// Absorption and scattering coefficients at `position`
+    fn coefficients(&self, position: &Vec3) -> (Vec3, Vec3);
+
+    /// Upper bound of every channel of the extinction (absorption plus
+    /// scattering) anywhere in the medium
+    fn majorant(&self) -> f64;
+
+    /// Part of [t_min, t_max] along `ray` that lies within the medium
+    fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)>;
+
+    fn phase(&self) -> HenyeyGreenstein;
+
+    /// Fraction of light that gets through along `ray` from its origin to
+    /// `t_max`, by ratio tracking
+    fn transmittance(&self, ray: &Ray, t_max: f64, sampler: &mut Sampler) -> Vec3 {
+        let (t_start, t_end) = match self.clip(ray, 0.0, t_max) {
+            Some(range) => range,
+            None => return Vec3::one()
+        };
+
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return Vec3::one();
+        }
+        let mut transmittance = Vec3::one();
+        let mut t = t_start;
+        loop {
+            t = t + free_path(majorant, ray, sampler);
+            if t >= t_end {
+                return transmittance;
+            }
+
+            let (absorption, scattering) = self.coefficients(&(ray.origin + ray.direction.scale(t)));
+            transmittance = transmittance * (Vec3::one() - (absorption + scattering).scale(1.0 / majorant));
+
+            // Dim paths are continued at random, unbiased but cheaper than tracking them to the end
+            let brightest = transmittance.x.max(transmittance.y).max(transmittance.z);
+            if brightest < 0.1 {
+                if sampler.next_1d() > brightest {
+                    return Vec3::zero();
+                }
+                transmittance = transmittance.scale(1.0 / brightest);
+            }
+        }
+    }
+
+    /// Follows `ray` until it is scattered or absorbed, or reaches `t_max`,
+    /// by weighted delta tracking
+    fn sample(&self, ray: &Ray, t_max: f64, sampler: &mut Sampler) -> MediumSample {
+        let (t_start, t_end) = match self.clip(ray, 0.0, t_max) {
+            Some(range) => range,
+            None => return MediumSample { scattered: None, weight: Vec3::one() }
+        };
+
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return MediumSample { scattered: None, weight: Vec3::one() };
+        }
+        let mut weight = Vec3::one();
+        let mut t = t_start;
+        loop {
+            t = t + free_path(majorant, ray, sampler);
+            if t >= t_end {
+                return MediumSample { scattered: None, weight: weight };
+            }
+
+            // A tentative collision scatters, is absorbed or does nothing in
+            // proportion to the mean of each coefficient
+            let (absorption, scattering) = self.coefficients(&(ray.origin + ray.direction.scale(t)));
+            let null = Vec3::one().scale(majorant) - absorption - scattering;
+            let p_scatter = mean(&scattering) / majorant;
+            let p_null = mean(&null) / majorant;
+
+            let u = sampler.next_1d();
+            if u < p_scatter {
+                return MediumSample { scattered: Some(t), weight: weight * scattering.scale(1.0 / mean(&scattering)) };
+            } else if u < p_scatter + p_null {
+                weight = weight * null.scale(1.0 / mean(&null));
+            } else {
+                return MediumSample { scattered: None, weight: Vec3::zero() };
+            }
+        }
+    }
+
+    /// Picks a point along `ray` before `t_max` to gather light scattered
+    /// towards the ray's origin at, uniformly within the medium. The weight
+    /// is the transmittance to the point times the scattering coefficient,
+    /// over the pdf of the distance.
+    fn sample_scattering(&self, ray: &Ray, t_max: f64, sampler: &mut Sampler) -> Option<(f64, Vec3)> {
+        let (t_start, t_end) = match self.clip(ray, 0.0, t_max) {
+            Some(range) => range,
+            None => return None
+        };
+        if !t_end.is_finite() {
+            return None;
+        }
+
+        let t = t_start + (t_end - t_start) * sampler.next_1d();
+        let (_, scattering) = self.coefficients(&(ray.origin + ray.direction.scale(t)));
+        let length = (t_end - t_start) * ray.direction.len();
+
+        Some((t, self.transmittance(ray, t, sampler) * scattering.scale(length)))
+    }
+}
+
+/// Distance along `ray`, in t, to the next collision in a medium of
+/// extinction `majorant`
+pub fn free_path(majorant: f64, ray: &Ray, sampler: &mut Sampler) -> f64 {
+    -(1.0 - sampler.next_1d()).ln() / (majorant * ray.direction.len())
+}
+
+pub fn mean(v: &Vec3) -> f64 {
+    (v.x + v.y + v.z) / 3.0
+}
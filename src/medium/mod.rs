@@ -0,0 +1,13 @@
+pub use self::medium::Medium;
+pub use self::phase::HenyeyGreenstein;
+
+pub mod medium;
+pub mod phase;
+
+pub mod media {
+    pub use self::gridmedium::GridMedium;
+    pub use self::homogeneousmedium::HomogeneousMedium;
+
+    mod gridmedium;
+    mod homogeneousmedium;
+}
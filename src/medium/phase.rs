@@ -0,0 +1,66 @@
+use std::f64::consts::PI;
+use vec3::Vec3;
+
+/// Henyey-Greenstein phase function: how light scattered in a medium is spread
+/// around its direction of travel. `g` is the mean cosine of the deflection,
+/// positive for forward scattering (fog, smoke), 0 for isotropic scattering.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HenyeyGreenstein {
+    pub g: f64
+}
+
+impl HenyeyGreenstein {
+    /// Density of light travelling back along `wo` being scattered from `wi`,
+    /// both pointing away from the scattering point
+    pub fn eval(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        let cos_theta = -wo.dot(wi);
+        let denominator = 1.0 + self.g * self.g - 2.0 * self.g * cos_theta;
+
+        (1.0 - self.g * self.g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+
+    /// Picks `wi` in proportion to `eval`, which is also its pdf
+    pub fn sample(&self, wo: &Vec3, u1: f64, u2: f64) -> Vec3 {
+        let g = self.g;
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u1
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u1);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        }.max(-1.0).min(1.0);
+
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * u2;
+        let forward = -*wo;
+        let (t, b) = forward.orthonormal_basis();
+
+        t.scale(sin_theta * phi.cos()) + b.scale(sin_theta * phi.sin()) + forward.scale(cos_theta)
+    }
+}
+
+#[test]
+fn it_scatters_with_a_mean_cosine_of_g() {
+    use raytracer::sampler::SamplerKind;
+
+    let wo = Vec3 { x: 0.0, y: 0.0, z: -1.0 };
+    let mut sampler = SamplerKind::Stratified.build(32, 0);
+    for &g in [-0.5, 0.0, 0.3, 0.9].iter() {
+        let phase = HenyeyGreenstein { g: g };
+
+        // Integrates to 1 over the sphere, in which the cosine of uniform directions is uniform
+        let total = (0..4096).fold(0.0, |total, index| {
+            let cos_theta = (index as f64 + 0.5) / 2048.0 - 1.0;
+            let wi = Vec3 { x: (1.0 - cos_theta * cos_theta).sqrt(), y: 0.0, z: cos_theta };
+            total + phase.eval(&wo, &wi) * 4.0 * PI / 4096.0
+        });
+        assert!((total - 1.0).abs() < 1e-3, "total {} for g {}", total, g);
+
+        let mut mean_cos = 0.0;
+        for index in 0..1024 {
+            sampler.start_pixel_sample(0, 0, index);
+            let (u1, u2) = sampler.next_2d();
+            mean_cos += phase.sample(&wo, u1, u2).z / 1024.0;
+        }
+        assert!((mean_cos - g).abs() < 0.01, "mean cosine {} for g {}", mean_cos, g);
+    }
+}
@@ -4,12 +4,15 @@ use geometry::prim::{Prim};
 use geometry::prims::{Plane, Sphere, Triangle, TriangleOptions};
 use light::light::{Light};
 use light::lights::{PointLight, SphereLight};
+use material::conductor::{SILVER};
 use material::materials::{CookTorranceMaterial, FlatMaterial, PhongMaterial};
 use material::Texture;
 use material::textures::{CheckerTexture, CubeMap, UVTexture, ImageTexture};
+use raytracer::{Accelerator, AcceleratorKind};
 use raytracer::animator::CameraKeyframe;
 use raytracer::compositor::ColorRGBA;
 use scene::{Camera, Scene};
+use std::sync::Arc;
 use vec3::Vec3;
 
 // 10 primitives, octree is super inefficient for this scene
@@ -24,24 +27,24 @@ pub fn get_camera(image_width: u32, image_height: u32, fov: f64) -> Camera {
     )
 }
 
-pub fn get_scene() -> Scene {
+pub fn get_scene(accelerator_kind: AcceleratorKind) -> Scene {
     let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
-    lights.push(Box::new(SphereLight {position: Vec3 { x: 50.0, y: 80.0, z: 50.0 }, color: Vec3::one(), radius: 10.0 }));
+    lights.push(Box::new(SphereLight { position: Vec3 { x: 50.0, y: 80.0, z: 50.0 }, color: Vec3 { x: 25.0, y: 25.0, z: 25.0 }, radius: 10.0 }));
 
     // Example of a textured material
-    let checker: Box<Texture+Send+Sync> = Box::new(CheckerTexture { color1: ColorRGBA::white(), color2: ColorRGBA::new_rgb(0.8, 0.1, 0.1), scale: 16.0 });
-    let checker_grey = CookTorranceMaterial { k_a: 0.0, k_d: 1.0, k_s: 0.0, k_sg: 0.0, k_tg: 0.0, gauss_constant: 1.0,  roughness: 0.15, glossiness: 0.0, ior: 0.7,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.6, y: 0.6, z: 0.6 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: Some(checker.clone()) };
+    let checker: Arc<Texture+Send+Sync> = Arc::new(CheckerTexture { color1: ColorRGBA::white(), color2: ColorRGBA::new_rgb(0.8, 0.1, 0.1), scale: 16.0 });
+    let checker_grey = CookTorranceMaterial { k_a: 0.0, k_d: 1.0, k_s: 0.0, k_sg: 0.0, k_tg: 0.0, gauss_constant: 1.0,  roughness: 0.15, glossiness: 0.0, ior: 1.43, conductor: None, ambient: Vec3::one(), diffuse: Vec3 { x: 0.6, y: 0.6, z: 0.6 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: Some(checker.clone()) };
 
     // Example of a short-form material definition using defaults
-    // let grey      = CookTorranceMaterial { k_a: 0.0, k_d: 1.0, k_s: 1.0, k_sg: 0.0, k_tg: 0.0, gauss_constant: 1.0,  roughness: 0.15, glossiness: 0.0, ior: 1.5,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.6, y: 0.6, z: 0.6 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: None };
+    // let grey      = CookTorranceMaterial { k_a: 0.0, k_d: 1.0, k_s: 1.0, k_sg: 0.0, k_tg: 0.0, gauss_constant: 1.0,  roughness: 0.15, glossiness: 0.0, ior: 1.5, conductor: None,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.6, y: 0.6, z: 0.6 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
     let grey         = CookTorranceMaterial { diffuse: Vec3 { x: 0.6, y: 0.6, z: 0.6 }, ..Default::default() };
 
-    let blue         = CookTorranceMaterial { k_a: 0.0, k_d: 0.3, k_s: 0.7, k_sg: 0.0, k_tg: 0.0, gauss_constant: 50.0, roughness: 0.1,  glossiness: 0.0, ior: 1.3,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.1, y: 0.1, z: 1.0 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: None };
-    let red          = PhongMaterial        { k_a: 0.0, k_d: 0.6, k_s: 0.4, k_sg: 0.8, k_tg: 0.0, shininess: 10.0,                       glossiness: 0.0, ior: 0.5,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 0.0, z: 0.0 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: None };
-    let green        = PhongMaterial        { k_a: 0.0, k_d: 0.9, k_s: 0.1, k_sg: 0.5, k_tg: 0.0, shininess: 10.0,                       glossiness: 0.0, ior: 0.7,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.0, y: 1.0, z: 0.0 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: None };
-    let shiny        = CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 0.8, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01,  glossiness: 0.0, ior: 0.25, ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), diffuse_texture: None };
-    let shiny_glossy = CookTorranceMaterial { k_a: 0.0, k_d: 0.7, k_s: 1.0, k_sg: 0.4, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01,  glossiness: 0.2, ior: 0.25, ambient: Vec3::one(), diffuse: Vec3 { x: 0.3, y: 0.3, z: 1.0 }, specular: Vec3 { x: 0.3, y: 0.3, z: 1.0 }, transmission: Vec3::zero(), diffuse_texture: None };
-    let refract      = CookTorranceMaterial { k_a: 0.0, k_d: 0.0, k_s: 1.0, k_sg: 1.0, k_tg: 1.0, gauss_constant: 5.0, roughness: 0.01,  glossiness: 0.0, ior: 3.0,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3 { x: 0.8, y: 0.8, z: 0.8 }, diffuse_texture: None };
+    let blue         = CookTorranceMaterial { k_a: 0.0, k_d: 0.3, k_s: 0.7, k_sg: 0.0, k_tg: 0.0, gauss_constant: 50.0, roughness: 0.1,  glossiness: 0.0, ior: 1.3, conductor: None,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.1, y: 0.1, z: 1.0 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
+    let red          = PhongMaterial        { k_a: 0.0, k_d: 0.6, k_s: 0.4, k_sg: 0.8, k_tg: 0.0, shininess: 10.0,                       glossiness: 0.0, ior: 2.0, conductor: None,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 0.0, z: 0.0 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
+    let green        = PhongMaterial        { k_a: 0.0, k_d: 0.9, k_s: 0.1, k_sg: 0.5, k_tg: 0.0, shininess: 10.0,                       glossiness: 0.0, ior: 1.43, conductor: None, ambient: Vec3::one(), diffuse: Vec3 { x: 0.0, y: 1.0, z: 0.0 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
+    let shiny        = CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 0.8, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01,  glossiness: 0.0, ior: 1.5, conductor: Some(SILVER), ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
+    let shiny_glossy = CookTorranceMaterial { k_a: 0.0, k_d: 0.7, k_s: 1.0, k_sg: 0.4, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01,  glossiness: 0.2, ior: 4.0, conductor: None, ambient: Vec3::one(), diffuse: Vec3 { x: 0.3, y: 0.3, z: 1.0 }, specular: Vec3 { x: 0.3, y: 0.3, z: 1.0 }, transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
+    let refract      = CookTorranceMaterial { k_a: 0.0, k_d: 0.0, k_s: 1.0, k_sg: 1.0, k_tg: 1.0, gauss_constant: 5.0, roughness: 0.01,  glossiness: 0.0, ior: 3.0, conductor: None,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3 { x: 0.8, y: 0.8, z: 0.8 }, emission: Vec3::zero(), diffuse_texture: None };
 
     let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
     prims.push(Box::new(Plane { a:  0.0, b:  0.0, c: 1.0, d: 0.0,   material: Box::new(grey.clone()) }));         // Ahead
@@ -62,14 +65,15 @@ pub fn get_scene() -> Scene {
     triopts.material(Box::new(blue));
     prims.push(Box::new(triopts.build()));
 
-    println!("Generating octree...");
-    let octree = prims.into_iter().collect();
-    println!("Octree generated...");
+    println!("Generating accelerator...");
+    let accelerator = Accelerator::new(accelerator_kind, prims);
+    println!("Accelerator generated...");
 
     Scene {
         lights: lights,
-        octree: octree,
+        accelerator: accelerator,
         background: Vec3::one(),
-        skybox: None
+        skybox: None,
+        medium: None
     }
 }
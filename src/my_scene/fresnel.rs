@@ -4,12 +4,15 @@ use geometry::prim::{Prim};
 use geometry::prims::{Plane, Sphere, Triangle};
 use light::light::{Light};
 use light::lights::{PointLight, SphereLight};
+use material::conductor::{SILVER};
 use material::materials::{CookTorranceMaterial, FlatMaterial, PhongMaterial};
 use material::Texture;
 use material::textures::{CheckerTexture, CubeMap, UVTexture, ImageTexture};
+use raytracer::{Accelerator, AcceleratorKind};
 use raytracer::animator::CameraKeyframe;
 use raytracer::compositor::ColorRGBA;
 use scene::{Camera, Scene};
+use std::sync::Arc;
 use vec3::Vec3;
 
 // Fresnel test scene
@@ -41,25 +44,33 @@ pub fn get_animation_camera(image_width: u32, image_height: u32, fov: f64) -> Ca
                 time: 2.5,
                 position: Vec3 { x: 50.0, y: 100.0, z: 250.0 },
                 look_at: Vec3 { x: 0.0, y: 1.0, z: 50.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
             CameraKeyframe {
                 time: 5.0,
                 position: Vec3 { x: 0.0, y: 200.0, z: 250.0 },
                 look_at: Vec3 { x: 0.0, y: 1.0, z: 50.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
             CameraKeyframe {
                 time: 7.5,
                 position: Vec3 { x: -50.0, y: 100.0, z: 250.0 },
                 look_at: Vec3 { x: 0.0, y: 1.0, z: 50.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
             CameraKeyframe {
                 time: 10.0,
                 position: Vec3 { x: 0.0, y: 1.0, z: 250.0 },
                 look_at: Vec3 { x: 0.0, y: 1.0, z: 50.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
         ]
     );
@@ -67,16 +78,16 @@ pub fn get_animation_camera(image_width: u32, image_height: u32, fov: f64) -> Ca
     camera
 }
 
-pub fn get_scene() -> Scene {
+pub fn get_scene(accelerator_kind: AcceleratorKind) -> Scene {
     let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
-    lights.push(Box::new(SphereLight { position: Vec3 { x: 50.0, y: 80.0, z: 50.0 }, color: Vec3::one(), radius: 10.0 }));
+    lights.push(Box::new(SphereLight { position: Vec3 { x: 50.0, y: 80.0, z: 50.0 }, color: Vec3 { x: 40.0, y: 40.0, z: 40.0 }, radius: 10.0 }));
 
 
-    let checker: Box<Texture+Send+Sync> = Box::new(CheckerTexture { color1: ColorRGBA::white(), color2: ColorRGBA::new_rgb(0.1, 0.1, 0.1), scale: 32.0 });
-    let checker_red          = CookTorranceMaterial { k_a: 0.0, k_d: 1.0, k_s: 0.0, k_sg: 0.0, k_tg: 0.0, gauss_constant: 1.0, roughness: 0.15, glossiness: 0.0, ior: 1.5,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.6, y: 0.6, z: 0.6 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: Some(checker.clone()) };
-    let shiny                = CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 0.15, ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), diffuse_texture: None };
-    let global_specular_only = CookTorranceMaterial { k_a: 0.0, k_d: 0.0, k_s: 0.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 1.5,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), diffuse_texture: None };
-    let refract              = CookTorranceMaterial { k_a: 0.0, k_d: 0.0, k_s: 1.0, k_sg: 1.0, k_tg: 1.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 3.0,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), diffuse_texture: None };
+    let checker: Arc<Texture+Send+Sync> = Arc::new(CheckerTexture { color1: ColorRGBA::white(), color2: ColorRGBA::new_rgb(0.1, 0.1, 0.1), scale: 32.0 });
+    let checker_red          = CookTorranceMaterial { k_a: 0.0, k_d: 1.0, k_s: 0.0, k_sg: 0.0, k_tg: 0.0, gauss_constant: 1.0, roughness: 0.15, glossiness: 0.0, ior: 1.5, conductor: None,  ambient: Vec3::one(), diffuse: Vec3 { x: 0.6, y: 0.6, z: 0.6 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: Some(checker.clone()) };
+    let shiny                = CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 1.5, conductor: Some(SILVER), ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
+    let global_specular_only = CookTorranceMaterial { k_a: 0.0, k_d: 0.0, k_s: 0.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 1.5, conductor: None,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
+    let refract              = CookTorranceMaterial { k_a: 0.0, k_d: 0.0, k_s: 1.0, k_sg: 1.0, k_tg: 1.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 3.0, conductor: None,  ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
 
     let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
     prims.push(Box::new(Plane { a: 0.0,  b:  0.0, c: 1.0, d: 0.0, material: Box::new(checker_red.clone()) })); // Ahead
@@ -84,14 +95,15 @@ pub fn get_scene() -> Scene {
     prims.push(Box::new(Sphere { center: Vec3 {x: 30.0, y: 15.0, z: 20.0 }, radius: 15.0, material: Box::new(shiny.clone()) }));
     prims.push(Box::new(Sphere { center: Vec3 {x: 70.0, y: 17.0, z: 60.0 }, radius: 17.0, material: Box::new(refract.clone()) }));
 
-    println!("Generating octree...");
-    let octree = prims.into_iter().collect();
-    println!("Octree generated...");
+    println!("Generating accelerator...");
+    let accelerator = Accelerator::new(accelerator_kind, prims);
+    println!("Accelerator generated...");
 
     Scene {
         lights: lights,
-        octree: octree,
+        accelerator: accelerator,
         background: Vec3 { x: 1.0, y: 1.0, z: 1.0 },
-        skybox: None
+        skybox: None,
+        medium: None
     }
 }
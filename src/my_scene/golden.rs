@@ -0,0 +1,146 @@
+//! Golden-image regression tests: small renders of the built-in scenes are
+//! compared against reference images in `test/res/golden`. Set UPDATE_GOLDEN
+//! to write new references instead, after checking the change is intended.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use raytracer::{AcceleratorKind, Renderer, RenderOptions};
+use raytracer::compositor::{ColorRGBA, Surface};
+use raytracer::compositor::tonemap::ToneMapping;
+use scene::{Camera, Scene};
+use util::export::to_file;
+use util::import::from_image;
+
+static SIZE: u32 = 64;
+static FOV: f64 = 30.0;
+
+// Root mean square difference of the 8-bit channels allowed before a render
+// counts as changed. Seeded renders are identical on one machine, this only
+// absorbs floating point differences between platforms.
+static MAX_RMSE: f64 = 2.0;
+
+static REFERENCE_DIR: &'static str = "test/res/golden";
+static FAILURE_DIR: &'static str = "target/golden";
+
+fn render(camera: Camera, scene: Scene) -> Surface {
+    let renderer = Renderer {
+        options: RenderOptions {
+            reflect_depth: 3,
+            refract_depth: 6,
+            shadow_samples: 8,
+            gloss_samples: 4,
+            pixel_samples: 2,
+            max_pixel_samples: 4,
+            ..Default::default()
+        },
+        tasks: ::num_cpus::get(),
+    };
+
+    let tone_mapping: ToneMapping = Default::default();
+    tone_mapping.apply(&renderer.render(camera, Arc::new(scene)))
+}
+
+fn rmse(a: &Surface, b: &Surface) -> f64 {
+    let sum = a.buffer.iter().zip(b.buffer.iter()).fold(0.0, |sum, (a, b)| {
+        let (dr, dg, db) = (a.r as f64 - b.r as f64, a.g as f64 - b.g as f64, a.b as f64 - b.b as f64);
+        sum + dr * dr + dg * dg + db * db
+    });
+
+    (sum / (a.pixel_count() * 3) as f64).sqrt()
+}
+
+/// Absolute difference, brightened so small changes show up
+fn diff_image(a: &Surface, b: &Surface) -> Surface {
+    let mut diff = Surface::new(a.width, a.height, ColorRGBA::new_rgb(0, 0, 0));
+    for ((dst, a), b) in diff.iter_pixels_mut().zip(a.buffer.iter()).zip(b.buffer.iter()) {
+        let channel = |a: u8, b: u8| ((a as i32 - b as i32).abs() * 8).min(255) as u8;
+        *dst = ColorRGBA::new_rgb(channel(a.r, b.r), channel(a.g, b.g), channel(a.b, b.b));
+    }
+    diff
+}
+
+/// Renders the scene, unless one of `assets` or its reference is missing, and
+/// compares it with the reference. On a mismatch the render and a difference
+/// image are written to target/golden.
+fn check_scene<F>(name: &str, assets: &[&str], get_camera_and_scene: F)
+        where F: Fn(u32, u32, f64) -> (Camera, Scene) {
+    if let Some(missing) = assets.iter().find(|asset| !Path::new(asset).exists()) {
+        println!("skipping {}, ``{}'' is missing", name, missing);
+        return;
+    }
+
+    let reference_file = format!("{}/{}.png", REFERENCE_DIR, name);
+    let updating = env::var("UPDATE_GOLDEN").is_ok();
+
+    // References are only committed for scenes whose assets are in this
+    // repository; the others can be created locally
+    if !updating && !Path::new(&reference_file).exists() {
+        println!("skipping {}, no reference ``{}''. Run with UPDATE_GOLDEN=1 to create it.",
+                 name, reference_file);
+        return;
+    }
+
+    let (camera, scene) = get_camera_and_scene(SIZE, SIZE, FOV);
+    let image = render(camera, scene);
+
+    if updating {
+        fs::create_dir_all(REFERENCE_DIR).unwrap();
+        to_file(&image, &reference_file).unwrap();
+        return;
+    }
+
+    let reference = match from_image(&reference_file) {
+        Ok(reference) => reference,
+        Err(err) => panic!("failed to load ``{}'': {}", reference_file, err)
+    };
+    assert_eq!((reference.width, reference.height), (image.width, image.height));
+
+    let error = rmse(&image, &reference);
+    if error > MAX_RMSE {
+        fs::create_dir_all(FAILURE_DIR).unwrap();
+        let actual_file = format!("{}/{}.png", FAILURE_DIR, name);
+        let diff_file = format!("{}/{}-diff.png", FAILURE_DIR, name);
+        to_file(&image, &actual_file).unwrap();
+        to_file(&diff_image(&image, &reference), &diff_file).unwrap();
+
+        panic!("{} differs from ``{}'' (RMSE {:.2}, at most {} allowed). Wrote ``{}'' and ``{}''.",
+               name, reference_file, error, MAX_RMSE, actual_file, diff_file);
+    }
+}
+
+#[test]
+fn it_renders_the_box_scene() {
+    check_scene("box", &[], |width, height, fov| {
+        (::my_scene::cornell::get_camera(width, height, fov), ::my_scene::cornell::get_scene(AcceleratorKind::Octree))
+    });
+}
+
+#[test]
+fn it_renders_the_fresnel_scene() {
+    check_scene("fresnel", &[], |width, height, fov| {
+        (::my_scene::fresnel::get_camera(width, height, fov), ::my_scene::fresnel::get_scene(AcceleratorKind::Octree))
+    });
+}
+
+#[test]
+fn it_renders_the_sphere_scene() {
+    let skybox = "./docs/assets/textures/skyboxes/storm_y_up";
+    let faces = ["left", "right", "down", "up", "front", "back"].iter()
+        .map(|face| format!("{}/{}.png", skybox, face))
+        .collect::<Vec<_>>();
+    let assets = faces.iter().map(|face| face.as_ref()).collect::<Vec<&str>>();
+
+    check_scene("sphere", &assets, |width, height, fov| {
+        (::my_scene::sphere::get_camera(width, height, fov), ::my_scene::sphere::get_scene(AcceleratorKind::Octree))
+    });
+}
+
+#[test]
+fn it_renders_the_cow_scene() {
+    check_scene("cow", &["./docs/assets/models/cow.obj"], |width, height, fov| {
+        (::my_scene::cow::get_camera(width, height, fov), ::my_scene::cow::get_scene(AcceleratorKind::Octree))
+    });
+}
@@ -4,11 +4,14 @@ use geometry::prim::{Prim};
 use geometry::prims::{Plane, Sphere, Triangle};
 use light::light::{Light};
 use light::lights::{PointLight, SphereLight};
-use material::materials::{CookTorranceMaterial, FlatMaterial, PhongMaterial};
+use material::conductor::{ALUMINIUM};
+use material::materials::{CookTorranceMaterial, DielectricMaterial, FlatMaterial, PhongMaterial};
 use material::Texture;
 use material::textures::{CheckerTexture, CubeMap, UVTexture, ImageTexture};
+use raytracer::{Accelerator, AcceleratorKind};
 use raytracer::animator::CameraKeyframe;
 use scene::{Camera, Scene};
+use std::sync::Arc;
 use vec3::Vec3;
 
 // 114688 tris, 57302 verts
@@ -23,36 +26,37 @@ pub fn get_camera(image_width: u32, image_height: u32, fov: f64) -> Camera {
     )
 }
 
-pub fn get_scene(material_option: &str) -> Scene {
+pub fn get_scene(material_option: &str, accelerator_kind: AcceleratorKind) -> Scene {
     let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
-    lights.push(Box::new(SphereLight { position: Vec3 { x: 2.0, y: 3.0, z: -2.0 }, color: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, radius: 1.0 }));
+    lights.push(Box::new(SphereLight { position: Vec3 { x: 2.0, y: 3.0, z: -2.0 }, color: Vec3 { x: 17.0, y: 17.0, z: 17.0 }, radius: 1.0 }));
 
     // Defaults to white
-    let heptoroid_material = match material_option {
-        "shiny" => CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 0.55, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 0.25, ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), diffuse_texture: None },
-        "refractive" => CookTorranceMaterial { k_a: 0.0, k_d: 0.0, k_s: 1.0, k_sg: 1.0, k_tg: 1.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 1.50, ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3 { x: 0.8, y: 0.8, z: 0.8 }, diffuse_texture: None },
-        _ => CookTorranceMaterial { k_a: 0.0, k_d: 0.9, k_s: 1.0, k_sg: 0.15, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.1, ior: 0.5, glossiness: 0.0, ambient: Vec3::one(), diffuse: Vec3 { x: 0.9, y: 0.85, z: 0.7 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: None }
-    };
+    let filename = "./docs/assets/models/heptoroid.obj";
+    let heptoroid = match material_option {
+        "shiny" => ::util::import::from_obj(CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 0.55, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 1.5, conductor: Some(ALUMINIUM), ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None }, false, filename),
+        "refractive" => ::util::import::from_obj(DielectricMaterial { ior: 1.5, cauchy_b: 0.0042, color: Vec3::one(), absorption: Vec3 { x: 0.3, y: 0.05, z: 0.2 }, medium: None }, false, filename),
+        _ => ::util::import::from_obj(CookTorranceMaterial { k_a: 0.0, k_d: 0.9, k_s: 1.0, k_sg: 0.15, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.1, ior: 2.0, conductor: None, glossiness: 0.0, ambient: Vec3::one(), diffuse: Vec3 { x: 0.9, y: 0.85, z: 0.7 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None }, false, filename)
+    }.ok().expect("failed to load obj model");
 
     let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
-    let heptoroid = ::util::import::from_obj(heptoroid_material, false, "./docs/assets/models/heptoroid.obj").ok().expect("failed to load obj model");;
     for triangle in heptoroid.triangles.into_iter() { prims.push(triangle); }
 
-    println!("Generating octree...");
-    let octree = prims.into_iter().collect();
-    println!("Octree generated...");
+    println!("Generating accelerator...");
+    let accelerator = Accelerator::new(accelerator_kind, prims);
+    println!("Accelerator generated...");
 
     Scene {
         lights: lights,
-        octree: octree,
+        accelerator: accelerator,
         background: Vec3 { x: 0.84, y: 0.34, z: 0.0 },
-        skybox: Some(CubeMap::load(
+        skybox: Some(Arc::new(CubeMap::load(
             "./docs/assets/textures/skyboxes/miramar_y_up/left.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/right.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/down.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/up.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/front.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/back.png"
-        ))
+        ).ok().expect("failed to load skybox"))),
+        medium: None
     }
 }
@@ -4,6 +4,8 @@ pub mod bunny;
 pub mod cornell;
 pub mod cow;
 pub mod fresnel;
+#[cfg(test)]
+mod golden;
 pub mod heptoroid;
 pub mod lucy;
 pub mod sibenik;
@@ -11,3 +13,145 @@ pub mod sphere;
 pub mod sponza;
 pub mod tachikoma;
 pub mod teapot;
+
+use raytracer::AcceleratorKind;
+use scene::{Camera, Scene};
+
+/// A built-in scene, rendered by giving its name instead of a scene file
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str, // Shown by list-scenes
+    build: fn(u32, u32, f64, bool, AcceleratorKind) -> (Camera, Scene)
+}
+
+impl Preset {
+    pub fn build(&self, image_width: u32, image_height: u32, fov: f64, animating: bool,
+                 accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+        (self.build)(image_width, image_height, fov, animating, accelerator_kind)
+    }
+}
+
+// Lower the render quality (especially shadow_samples) for complex scenes
+pub static PRESETS: [Preset; 13] = [
+    Preset { name: "box", description: "Cornell box, 9 primitives", build: cornell_box },
+    Preset { name: "bunny", description: "Stanford bunny, around 300 primitives, skybox", build: bunny },
+    Preset { name: "teapot", description: "Utah teapot, around 2500 polygons, skybox", build: teapot },
+    Preset { name: "cow", description: "cow, around 5000 polygons", build: cow },
+    Preset { name: "lucy", description: "Lucy, around 525K primitives, skybox", build: lucy },
+    Preset { name: "sponza", description: "Sponza atrium, around 28K triangles", build: sponza },
+    Preset { name: "sibenik", description: "Sibenik cathedral, around 70K triangles, animated", build: sibenik },
+    Preset { name: "heptoroid-white", description: "heptoroid, 114688 triangles", build: heptoroid_white },
+    Preset { name: "heptoroid-shiny", description: "reflective heptoroid", build: heptoroid_shiny },
+    Preset { name: "heptoroid-refractive", description: "refractive heptoroid, raise refract_depth",
+             build: heptoroid_refractive },
+    Preset { name: "tachikoma", description: "Tachikoma model", build: tachikoma },
+    Preset { name: "sphere", description: "sphere skybox test, animated", build: sphere },
+    Preset { name: "fresnel", description: "Fresnel test scene, animated", build: fresnel },
+];
+
+pub fn find_preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+// Box. Simplest scene with 9 primitives, no octree
+fn cornell_box(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+               accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (cornell::get_camera(image_width, image_height, fov), cornell::get_scene(accelerator_kind))
+}
+
+// Bunny. Around 300 primitives, 2 lights. Uses octree. Has skybox, textures are
+// in another repository.
+fn bunny(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+         accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (bunny::get_camera(image_width, image_height, fov), bunny::get_scene(accelerator_kind))
+}
+
+// Teapot. Around 2500 polygons. Octree helps a bit. Has skybox.
+fn teapot(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+          accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (teapot::get_teapot_camera(image_width, image_height, fov), teapot::get_teapot_scene(accelerator_kind))
+}
+
+// Cow. Around 5000 polygons. Octree helps considerably.
+fn cow(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+       accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (cow::get_camera(image_width, image_height, fov), cow::get_scene(accelerator_kind))
+}
+
+// Lucy. Around 525814+1 primitives. Octree pretty much required. The model is included
+// separately, in another repository. Has skybox.
+fn lucy(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+        accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (lucy::get_camera(image_width, image_height, fov), lucy::get_scene(accelerator_kind))
+}
+
+// Sponza. Around 28K triangles, but more complex than Lucy. 2 lights.
+fn sponza(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+          accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (sponza::get_camera(image_width, image_height, fov), sponza::get_scene(accelerator_kind))
+}
+
+// Sibenik, around 70K triangles, no texture work, 3 lights.
+fn sibenik(image_width: u32, image_height: u32, fov: f64, animating: bool,
+           accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    let camera = match animating {
+        true => sibenik::get_animation_camera(image_width, image_height, fov),
+        false => sibenik::get_camera(image_width, image_height, fov)
+    };
+    (camera, sibenik::get_scene(accelerator_kind))
+}
+
+// Heptoroid, 114688 tris, 57302 verts
+fn heptoroid_white(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+                   accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (heptoroid::get_camera(image_width, image_height, fov), heptoroid::get_scene("white", accelerator_kind))
+}
+
+// Shiny heptoroid, 114688 tris, 57302 verts
+fn heptoroid_shiny(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+                   accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (heptoroid::get_camera(image_width, image_height, fov), heptoroid::get_scene("shiny", accelerator_kind))
+}
+
+// Refractive heptoroid, you want to limit your reflect levels (2/3?)
+// and up your refract levels (10/16?) for this
+fn heptoroid_refractive(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+                        accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (heptoroid::get_camera(image_width, image_height, fov), heptoroid::get_scene("refractive", accelerator_kind))
+}
+
+// Shiny heptoroid, 114688 tris, 57302 verts
+// You can forget about refractions, it's too complex a scene
+fn tachikoma(image_width: u32, image_height: u32, fov: f64, _animating: bool,
+             accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    (tachikoma::get_camera(image_width, image_height, fov), tachikoma::get_scene(accelerator_kind))
+}
+
+// Sphere skybox test scene
+fn sphere(image_width: u32, image_height: u32, fov: f64, animating: bool,
+          accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    let camera = match animating {
+        true => sphere::get_animation_camera(image_width, image_height, fov),
+        false => sphere::get_camera(image_width, image_height, fov)
+    };
+    (camera, sphere::get_scene(accelerator_kind))
+}
+
+// Fresnel test scene
+fn fresnel(image_width: u32, image_height: u32, fov: f64, animating: bool,
+           accelerator_kind: AcceleratorKind) -> (Camera, Scene) {
+    let camera = match animating {
+        true => fresnel::get_animation_camera(image_width, image_height, fov),
+        false => fresnel::get_camera(image_width, image_height, fov)
+    };
+    (camera, fresnel::get_scene(accelerator_kind))
+}
+
+#[test]
+fn it_names_every_preset_once() {
+    for (i, preset) in PRESETS.iter().enumerate() {
+        assert!(find_preset(preset.name).is_some());
+        assert!(PRESETS[i + 1..].iter().all(|other| other.name != preset.name), "{} twice", preset.name);
+    }
+    assert!(find_preset("nonexistent").is_none());
+}
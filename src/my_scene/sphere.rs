@@ -4,11 +4,14 @@ use geometry::prim::{Prim};
 use geometry::prims::{Plane, Sphere, Triangle};
 use light::light::{Light};
 use light::lights::{PointLight, SphereLight};
+use material::conductor::{SILVER};
 use material::materials::{CookTorranceMaterial, FlatMaterial, PhongMaterial};
 use material::Texture;
 use material::textures::{CheckerTexture, CubeMap, UVTexture, ImageTexture};
+use raytracer::{Accelerator, AcceleratorKind};
 use raytracer::animator::CameraKeyframe;
 use scene::{Camera, Scene};
+use std::sync::Arc;
 use vec3::Vec3;
 
 // Skybox test scene
@@ -49,25 +52,33 @@ pub fn get_animation_camera(image_width: u32, image_height: u32, fov: f64) -> Ca
                 time: 2.5,
                 position: Vec3 { x: 10.0, y: 0.0, z: 0.0 },
                 look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
             CameraKeyframe {
                 time: 5.0,
                 position: Vec3 { x: 0.0, y: 0.0, z: -10.0 },
                 look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
             CameraKeyframe {
                 time: 7.5,
                 position: Vec3 { x: -10.0, y: 0.0, z: 0.0 },
                 look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
             CameraKeyframe {
                 time: 10.0,
                 position: Vec3 { x: 0.0, y: 0.0, z: 10.0 },
                 look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
         ]
     );
@@ -75,30 +86,31 @@ pub fn get_animation_camera(image_width: u32, image_height: u32, fov: f64) -> Ca
     camera
 }
 
-pub fn get_scene() -> Scene {
+pub fn get_scene(accelerator_kind: AcceleratorKind) -> Scene {
     let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
-    lights.push(Box::new(SphereLight { position: Vec3 { x: 3.0, y: 10.0, z: 6.0 }, color: Vec3::one(), radius: 5.0 }));
+    lights.push(Box::new(SphereLight { position: Vec3 { x: 3.0, y: 10.0, z: 6.0 }, color: Vec3 { x: 6.0, y: 6.0, z: 6.0 }, radius: 5.0 }));
 
     let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
-    let shiny = CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 0.05, ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), diffuse_texture: None };
+    let shiny = CookTorranceMaterial { k_a: 0.0, k_d: 0.2, k_s: 1.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.01, glossiness: 0.0, ior: 1.5, conductor: Some(SILVER), ambient: Vec3::one(), diffuse: Vec3 { x: 1.0, y: 1.0, z: 1.0 }, specular: Vec3 { x: 0.9, y: 0.9, z: 0.9 }, transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
     prims.push(Box::new(Sphere { center: Vec3::zero(), radius: 2.0, material: Box::new(shiny) }));
 
-    println!("Generating octree...");
-    let octree = prims.into_iter().collect();
-    println!("Octree generated...");
+    println!("Generating accelerator...");
+    let accelerator = Accelerator::new(accelerator_kind, prims);
+    println!("Accelerator generated...");
 
     // For y as up
     Scene {
         lights: lights,
         background: Vec3 { x: 0.3, y: 0.5, z: 0.8 },
-        octree: octree,
-        skybox: Some(CubeMap::load(
+        accelerator: accelerator,
+        skybox: Some(Arc::new(CubeMap::load(
             "./docs/assets/textures/skyboxes/storm_y_up/left.png",
             "./docs/assets/textures/skyboxes/storm_y_up/right.png",
             "./docs/assets/textures/skyboxes/storm_y_up/down.png",
             "./docs/assets/textures/skyboxes/storm_y_up/up.png",
             "./docs/assets/textures/skyboxes/storm_y_up/front.png",
             "./docs/assets/textures/skyboxes/storm_y_up/back.png"
-        ))
+        ).ok().expect("failed to load skybox"))),
+        medium: None
     }
 }
@@ -8,8 +8,10 @@ use material::materials::{CookTorranceMaterial, FlatMaterial, PhongMaterial};
 use material::Texture;
 use material::textures::{CheckerTexture, CubeMap, UVTexture, ImageTexture};
 use mat4::{Mat4, Transform};
+use raytracer::{Accelerator, AcceleratorKind};
 use raytracer::animator::CameraKeyframe;
 use scene::{Camera, Scene};
+use std::sync::Arc;
 use vec3::Vec3;
 
 // When using Fresnel, set k_sg and k_tg (if applicable) to 1.0 for easier material definition.
@@ -27,11 +29,11 @@ pub fn get_teapot_camera(image_width: u32, image_height: u32, fov: f64) -> Camer
     )
 }
 
-pub fn get_teapot_scene() -> Scene {
+pub fn get_teapot_scene(accelerator_kind: AcceleratorKind) -> Scene {
     let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
-    lights.push(Box::new(SphereLight { position: Vec3 { x: 0.6, y: 2.0, z: 1.2 }, color: Vec3::one(), radius: 1.0 }));
+    lights.push(Box::new(SphereLight { position: Vec3 { x: 0.6, y: 2.0, z: 1.2 }, color: Vec3 { x: 4.0, y: 4.0, z: 4.0 }, radius: 1.0 }));
 
-    let porcelain = CookTorranceMaterial { k_a: 0.0, k_d: 0.9, k_s: 1.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.1, glossiness: 0.0, ior: 1.1, ambient: Vec3::one(), diffuse: Vec3 { x: 0.9, y: 0.85, z: 0.7 }, specular: Vec3::one(), transmission: Vec3::zero(), diffuse_texture: None };
+    let porcelain = CookTorranceMaterial { k_a: 0.0, k_d: 0.9, k_s: 1.0, k_sg: 1.0, k_tg: 0.0, gauss_constant: 5.0, roughness: 0.1, glossiness: 0.0, ior: 1.1, conductor: None, ambient: Vec3::one(), diffuse: Vec3 { x: 0.9, y: 0.85, z: 0.7 }, specular: Vec3::one(), transmission: Vec3::zero(), emission: Vec3::zero(), diffuse_texture: None };
 
     let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
     // prims.push(Box::new(Plane { a: 0.0, b: 1.0, c: 0.0, d: 0.0, material: Box::new(green) }));
@@ -40,21 +42,22 @@ pub fn get_teapot_scene() -> Scene {
     teapot.mut_transform(&rotate);
     for triangle in teapot.triangles.into_iter() { prims.push(triangle); }
 
-    println!("Generating octree...");
-    let octree = prims.into_iter().collect();
-    println!("Octree generated...");
+    println!("Generating accelerator...");
+    let accelerator = Accelerator::new(accelerator_kind, prims);
+    println!("Accelerator generated...");
 
     Scene {
         lights: lights,
-        octree: octree,
+        accelerator: accelerator,
         background: Vec3 { x: 0.3, y: 0.5, z: 0.8 },
-        skybox: Some(CubeMap::load(
+        skybox: Some(Arc::new(CubeMap::load(
             "./docs/assets/textures/skyboxes/miramar_y_up/left.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/right.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/down.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/up.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/front.png",
             "./docs/assets/textures/skyboxes/miramar_y_up/back.png"
-        ))
+        ).ok().expect("failed to load skybox"))),
+        medium: None
     }
 }
@@ -0,0 +1,66 @@
+use std::iter::FromIterator;
+use geometry::PartialBoundingBox;
+use raytracer::{Bvh, Octree, Ray};
+use raytracer::bvh::BvhIterator;
+use raytracer::octree::OctreeIterator;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum AcceleratorKind {
+    Octree,
+    Bvh
+}
+
+/// Spatial index over the scene's prims. Collecting into an Accelerator builds
+/// an octree; `new` builds the structure asked for.
+pub enum Accelerator<T> where T: PartialBoundingBox {
+    Octree(Octree<T>),
+    Bvh(Bvh<T>)
+}
+
+impl<T> FromIterator<T> for Accelerator<T> where T: PartialBoundingBox {
+    fn from_iter<I>(iterator: I) -> Self where I: IntoIterator<Item=T> {
+        Accelerator::Octree(iterator.into_iter().collect())
+    }
+}
+
+impl<T> Accelerator<T> where T: PartialBoundingBox {
+    pub fn new(kind: AcceleratorKind, prims: Vec<T>) -> Accelerator<T> {
+        match kind {
+            AcceleratorKind::Octree => Accelerator::Octree(prims.into_iter().collect()),
+            AcceleratorKind::Bvh => Accelerator::Bvh(prims.into_iter().collect())
+        }
+    }
+
+    /// All prims whose bounds the ray passes through, in no particular order
+    pub fn intersect_iter<'a>(&'a self, ray: &'a Ray) -> AcceleratorIterator<'a, T> {
+        match *self {
+            Accelerator::Octree(ref octree) => AcceleratorIterator::Octree(octree.intersect_iter(ray)),
+            Accelerator::Bvh(ref bvh) => AcceleratorIterator::Bvh(bvh.intersect_iter(ray))
+        }
+    }
+
+    /// Returns the hit with the smallest t found by `intersect`, see `Bvh::nearest_hit`
+    pub fn nearest_hit<'a, H, F>(&'a self, ray: &'a Ray, t_max: f64, intersect: F) -> Option<H>
+            where F: FnMut(&'a T, f64) -> Option<(f64, H)> {
+        match *self {
+            Accelerator::Octree(ref octree) => octree.nearest_hit(ray, t_max, intersect),
+            Accelerator::Bvh(ref bvh) => bvh.nearest_hit(ray, t_max, intersect)
+        }
+    }
+}
+
+pub enum AcceleratorIterator<'a, T: 'a> where T: PartialBoundingBox {
+    Octree(OctreeIterator<'a, T>),
+    Bvh(BvhIterator<'a, T>)
+}
+
+impl<'a, T> Iterator for AcceleratorIterator<'a, T> where T: PartialBoundingBox {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        match *self {
+            AcceleratorIterator::Octree(ref mut iter) => iter.next(),
+            AcceleratorIterator::Bvh(ref mut iter) => iter.next()
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use raytracer::animator::CameraKeyframe;
+use raytracer::compositor::tonemap::ToneMapping;
 use raytracer::Renderer;
 use scene::{Camera, Scene};
 use std::sync::mpsc::sync_channel;
@@ -11,14 +12,18 @@ pub struct Animator {
     pub animate_from: f64, // Number of frames is rounded down to nearest frame
     pub animate_to: f64,
     pub starting_frame_number: u32, // For filename
-    pub renderer: Renderer
+    pub renderer: Renderer,
+    pub tone_mapping: ToneMapping
 }
 
 // TODO: Non-linear interpolation
 impl Animator {
     // TODO: make this a Surface iterator so both single frame and animation
     // process flows are similar
-    pub fn animate(&self, camera: Camera, shared_scene: Arc<Scene>, filename: &str) {
+    /// Frames are written as `filename` followed by the frame number, e.g.
+    /// `frames/out.png` gives `frames/out000000.png`. Stops at the first frame
+    /// that fails to be written.
+    pub fn animate(&self, camera: Camera, shared_scene: Arc<Scene>, filename: &str) -> Result<(), String> {
         let animate_start = ::time::get_time();
         let length = self.animate_to - self.animate_from;
         let total_frames = (self.fps * length).floor() as u32;
@@ -29,30 +34,38 @@ impl Animator {
 
         let starting_frame_number = self.starting_frame_number;
 
-        let filename = filename.to_string();
+        let (stem, extension) = ::util::export::split_extension(filename);
+        let tone_mapping = self.tone_mapping;
         thread::spawn(move || {
             for (frame_num, frame_data) in frame_rx.iter().enumerate() {
                 let file_frame_number = starting_frame_number as usize + frame_num;
 
-                let shared_name = format!("{}{:06}.ppm", filename, file_frame_number);
-                ::util::export::to_ppm(frame_data, &shared_name);
+                let shared_name = format!("{}{:06}.{}", stem, file_frame_number, extension);
+                if let Err(error) = ::util::export::save(&frame_data, &tone_mapping, &shared_name) {
+                    exit_tx.send(Err(error)).unwrap();
+                    return;
+                }
             }
 
-            exit_tx.send(()).unwrap();
+            exit_tx.send(Ok(())).unwrap();
         });
 
         for frame_number in 0..total_frames {
             let time = self.animate_from + frame_number as f64 / self.fps;
             let lerped_camera = Animator::lerp_camera(&camera, time);
             let frame_data = self.renderer.render(lerped_camera, shared_scene.clone());
-            frame_tx.send(frame_data).unwrap();
+
+            // The writer hung up because of an error, which recv() below picks up
+            if frame_tx.send(frame_data).is_err() {
+                break;
+            }
 
             ::util::print_progress("*** Frame", animate_start.clone(), frame_number as usize + 1usize, total_frames as usize);
             println!("");
         }
         drop(frame_tx);
 
-        let () = exit_rx.recv().unwrap();
+        exit_rx.recv().unwrap()
     }
 
     fn get_neighbour_keyframes(keyframes: Vec<CameraKeyframe>, time: f64)
@@ -100,6 +113,11 @@ impl Animator {
         let lerped_look_at  = Vec3::lerp(&first.look_at, &second.look_at, alpha);
         let lerped_up       = Vec3::lerp(&first.up, &second.up, alpha);
 
+        let lerp = |a: f64, b: f64| a + (b - a) * alpha;
+        let focus_distance = |keyframe: &CameraKeyframe| {
+            keyframe.focus_distance.unwrap_or((keyframe.look_at - keyframe.position).len())
+        };
+
         let mut lerped_camera = Camera::new(
             lerped_position,
             lerped_look_at,
@@ -109,6 +127,10 @@ impl Animator {
             camera.image_height,
         );
 
+        lerped_camera.aperture_radius = lerp(first.aperture_radius.unwrap_or(camera.aperture_radius),
+                                             second.aperture_radius.unwrap_or(camera.aperture_radius));
+        lerped_camera.focus_distance = lerp(focus_distance(&first), focus_distance(&second));
+        lerped_camera.aperture = camera.aperture.clone();
         lerped_camera.keyframes = camera.keyframes.clone();
         lerped_camera
     }
@@ -129,13 +151,17 @@ fn test_lerp_camera_position() {
                 time: 5.0,
                 position: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
                 look_at: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
             CameraKeyframe {
                 time: 10.0,
                 position: Vec3 { x: 10.0, y: 0.0, z: 0.0 },
                 look_at: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
-                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
             },
         ]
     );
@@ -152,3 +178,79 @@ fn test_lerp_camera_position() {
     let expected_position_10 = Vec3 { x: 10.0, y: 0.0, z: 0.0 };
     assert_eq!(Animator::lerp_camera(&camera, 10.0).position, expected_position_10);
 }
+
+#[test]
+fn test_lerp_camera_focus_pull() {
+    // Focus pulls from 10 to 2 while the aperture opens up, and back
+    let mut camera = Camera::new(
+        Vec3 { x: 0.0, y: 0.0, z: 10.0 },
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        45.0,
+        10,
+        10
+    );
+    camera.aperture_radius = 0.1;
+    camera.insert_keyframes(vec![
+        CameraKeyframe {
+            time: 2.0,
+            position: Vec3 { x: 0.0, y: 0.0, z: 10.0 },
+            look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+            aperture_radius: Some(0.5),
+            focus_distance: Some(2.0)
+        },
+        CameraKeyframe {
+            time: 4.0,
+            position: Vec3 { x: 0.0, y: 0.0, z: 10.0 },
+            look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+            aperture_radius: None,
+            focus_distance: None
+        }
+    ]);
+
+    let halfway = Animator::lerp_camera(&camera, 1.0);
+    assert_eq!(halfway.focus_distance, 6.0);
+    assert!((halfway.aperture_radius - 0.3).abs() < 1e-12);
+
+    // Keyframes leaving the lens out fall back to the camera's aperture and
+    // focus on what they look at
+    let after = Animator::lerp_camera(&camera, 3.0);
+    assert_eq!(after.focus_distance, 6.0);
+    assert!((after.aperture_radius - 0.3).abs() < 1e-12);
+}
+
+#[test]
+fn test_lerp_camera_focus_follows_look_at() {
+    // Dollies in from 10 to 4 away, focused on what it looks at throughout
+    let camera = Camera::new_with_keyframes(
+        Vec3 { x: 0.0, y: 0.0, z: 10.0 },
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        45.0,
+        10,
+        10,
+        vec![
+            CameraKeyframe {
+                time: 0.0,
+                position: Vec3 { x: 0.0, y: 0.0, z: 10.0 },
+                look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
+            },
+            CameraKeyframe {
+                time: 2.0,
+                position: Vec3 { x: 0.0, y: 0.0, z: 4.0 },
+                look_at: Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+                up: Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                aperture_radius: None,
+                focus_distance: None
+            }
+        ]
+    );
+
+    assert_eq!(Animator::lerp_camera(&camera, 1.0).focus_distance, 7.0);
+    assert_eq!(Animator::lerp_camera(&camera, 2.0).focus_distance, 4.0);
+}
@@ -1,9 +1,13 @@
 use vec3::Vec3;
 
+/// Where the camera is at `time`. An aperture left out is carried over from
+/// the camera, a focus distance left out is the distance to `look_at`.
 #[derive(Clone)]
 pub struct CameraKeyframe {
     pub time: f64,
     pub position: Vec3,
     pub look_at: Vec3,
-    pub up: Vec3
+    pub up: Vec3,
+    pub aperture_radius: Option<f64>,
+    pub focus_distance: Option<f64>
 }
@@ -0,0 +1,350 @@
+use std::iter::FromIterator;
+use std::slice::Iter;
+use geometry::{BBox, PartialBoundingBox};
+use geometry::bbox::{union_bbox, union_point};
+use raytracer::Ray;
+use vec3::Vec3;
+
+#[cfg(test)]
+use geometry::prim::Prim;
+#[cfg(test)]
+use geometry::prims::Sphere;
+#[cfg(test)]
+use material::materials::FlatMaterial;
+
+// Number of buckets candidate splits are binned into when evaluating the SAH
+static SAH_BUCKETS: usize = 12;
+// Relative cost of visiting a node compared to intersecting a primitive
+static SAH_TRAVERSAL_COST: f64 = 0.125;
+// Nodes with more primitives than this are always split if possible
+static MAX_LEAF_PRIMS: usize = 8;
+
+/// Bounding volume hierarchy built with the surface area heuristic (pbrt, 4.4).
+/// Nodes are stored flattened in depth-first order: the first child of an
+/// interior node directly follows it and the second is found through `offset`.
+pub struct Bvh<T> where T: PartialBoundingBox {
+    prims: Vec<T>,
+    infinites: Vec<T>, // for infinite prims (planes)
+    nodes: Vec<BvhNode>,
+}
+
+struct BvhNode {
+    bbox: BBox,
+    offset: usize, // Leaf: first index into prims. Interior: index of the second child.
+    count: usize,  // Number of prims in a leaf, 0 for interior nodes
+    axis: usize,   // Split axis of interior nodes
+}
+
+struct BuildPrim {
+    index: usize,
+    bbox: BBox,
+    centroid: Vec3,
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    count: usize,
+    bbox: Option<BBox>,
+}
+
+fn axis_value(v: &Vec3, axis: usize) -> f64 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z
+    }
+}
+
+fn union_option(a: Option<BBox>, b: &BBox) -> Option<BBox> {
+    Some(a.map(|a| union_bbox(&a, b)).unwrap_or(*b))
+}
+
+impl<T> FromIterator<T> for Bvh<T> where T: PartialBoundingBox {
+    fn from_iter<I>(iterator: I) -> Self where I: IntoIterator<Item=T> {
+        let iterator = iterator.into_iter();
+
+        let (finites, infinites): (Vec<T>, Vec<T>) =
+            iterator.partition(|item| item.partial_bounding_box().is_some());
+
+        let mut build_prims: Vec<BuildPrim> = finites.iter().enumerate().map(|(i, prim)| {
+            let bbox = prim.partial_bounding_box().unwrap();
+            BuildPrim { index: i, bbox: bbox, centroid: bbox.centroid() }
+        }).collect();
+
+        let mut nodes = Vec::new();
+        let mut order = Vec::with_capacity(finites.len());
+        if build_prims.len() > 0 {
+            Bvh::<T>::build(&mut build_prims[..], &mut nodes, &mut order);
+        }
+
+        // Reorder the prims so every leaf refers to a contiguous range
+        let mut slots: Vec<Option<T>> = finites.into_iter().map(Some).collect();
+        let prims = order.iter().map(|&i| slots[i].take().unwrap()).collect();
+
+        println!("BVH nodes {}", nodes.len());
+
+        Bvh {
+            prims: prims,
+            infinites: infinites,
+            nodes: nodes,
+        }
+    }
+}
+
+impl<T> Bvh<T> where T: PartialBoundingBox {
+    /// Recursively builds the subtree for `build_prims` and returns its node index
+    fn build(build_prims: &mut [BuildPrim], nodes: &mut Vec<BvhNode>, order: &mut Vec<usize>) -> usize {
+        let node_index = nodes.len();
+        let count = build_prims.len();
+
+        let bbox = build_prims.iter().fold(build_prims[0].bbox, |acc, p| union_bbox(&acc, &p.bbox));
+        let first_centroid = build_prims[0].centroid;
+        let centroid_bbox = build_prims.iter().fold(BBox { min: first_centroid, max: first_centroid },
+                                                    |acc, p| union_point(&acc, &p.centroid));
+        let axis = centroid_bbox.max_extent() as usize;
+        let axis_min = axis_value(&centroid_bbox.min, axis);
+        let axis_len = axis_value(&centroid_bbox.max, axis) - axis_min;
+
+        nodes.push(BvhNode { bbox: bbox, offset: order.len(), count: count, axis: axis });
+
+        // All centroids coincide, there is no way to separate them
+        if count == 1 || axis_len <= 0.0 {
+            order.extend(build_prims.iter().map(|p| p.index));
+            return node_index;
+        }
+
+        let bucket_of = |p: &BuildPrim| {
+            let b = ((axis_value(&p.centroid, axis) - axis_min) / axis_len * SAH_BUCKETS as f64) as usize;
+            if b >= SAH_BUCKETS { SAH_BUCKETS - 1 } else { b }
+        };
+
+        let mut buckets = vec![Bucket { count: 0, bbox: None }; SAH_BUCKETS];
+        for p in build_prims.iter() {
+            let bucket = &mut buckets[bucket_of(p)];
+            bucket.count += 1;
+            bucket.bbox = union_option(bucket.bbox, &p.bbox);
+        }
+
+        // Cost of splitting after each bucket, relative to intersecting one prim
+        let parent_area = bbox.surface_area();
+        let mut best_cost = ::std::f64::INFINITY;
+        let mut best_split = 0;
+        for split in 0..(SAH_BUCKETS - 1) {
+            let (left, right) = buckets.split_at(split + 1);
+            let side_cost = |side: &[Bucket]| {
+                let (count, bbox) = side.iter().fold((0, None), |(count, bbox), b| {
+                    match b.bbox {
+                        Some(ref b_bbox) => (count + b.count, union_option(bbox, b_bbox)),
+                        None => (count, bbox)
+                    }
+                });
+                count as f64 * bbox.map(|b| b.surface_area()).unwrap_or(0.0)
+            };
+
+            let cost = SAH_TRAVERSAL_COST + (side_cost(left) + side_cost(right)) / parent_area;
+            if cost < best_cost {
+                best_cost = cost;
+                best_split = split;
+            }
+        }
+
+        if count <= MAX_LEAF_PRIMS && best_cost >= count as f64 {
+            order.extend(build_prims.iter().map(|p| p.index));
+            return node_index;
+        }
+
+        let mut mid = 0;
+        for i in 0..count {
+            if bucket_of(&build_prims[i]) <= best_split {
+                build_prims.swap(i, mid);
+                mid += 1;
+            }
+        }
+
+        // Degenerate split (e.g. all prims in one bucket): fall back to halving
+        if mid == 0 || mid == count {
+            build_prims.sort_by(|a, b| {
+                axis_value(&a.centroid, axis).partial_cmp(&axis_value(&b.centroid, axis)).unwrap()
+            });
+            mid = count / 2;
+        }
+
+        let (left, right) = build_prims.split_at_mut(mid);
+        Bvh::<T>::build(left, nodes, order);
+        let second_child = Bvh::<T>::build(right, nodes, order);
+
+        let node = &mut nodes[node_index];
+        node.offset = second_child;
+        node.count = 0;
+
+        node_index
+    }
+
+    pub fn intersect_iter<'a>(&'a self, ray: &'a Ray) -> BvhIterator<'a, T> {
+        BvhIterator::new(self, ray)
+    }
+
+    /// Visits nodes front-to-back along the ray and skips every node further
+    /// away than the nearest hit found so far. `intersect` is called with a prim
+    /// and the current t_max and returns the distance to the hit along with it.
+    pub fn nearest_hit<'a, H, F>(&'a self, ray: &Ray, t_max: f64, mut intersect: F) -> Option<H>
+            where F: FnMut(&'a T, f64) -> Option<(f64, H)> {
+
+        let mut nearest_t = t_max;
+        let mut nearest_hit = None;
+
+        for prim in self.infinites.iter() {
+            if let Some((t, hit)) = intersect(prim, nearest_t) {
+                if t < nearest_t {
+                    nearest_t = t;
+                    nearest_hit = Some(hit);
+                }
+            }
+        }
+
+        if self.nodes.len() == 0 {
+            return nearest_hit;
+        }
+
+        let mut stack = vec![0usize];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+
+            match node.bbox.intersection_range(ray) {
+                Some((t_enter, _)) if t_enter <= nearest_t => {},
+                _ => continue
+            }
+
+            if node.count > 0 {
+                for prim in self.prims[node.offset..node.offset + node.count].iter() {
+                    if let Some((t, hit)) = intersect(prim, nearest_t) {
+                        if t < nearest_t {
+                            nearest_t = t;
+                            nearest_hit = Some(hit);
+                        }
+                    }
+                }
+            } else if ray.signs[node.axis] {
+                // Travelling along the axis: the first (lower) child is nearer
+                stack.push(node.offset);
+                stack.push(node_index + 1);
+            } else {
+                stack.push(node_index + 1);
+                stack.push(node.offset);
+            }
+        }
+
+        nearest_hit
+    }
+}
+
+/// Iterates over the prims of every leaf the ray passes through, in no
+/// particular order, followed by the infinite prims.
+pub struct BvhIterator<'a, T: 'a> where T: PartialBoundingBox {
+    bvh: &'a Bvh<T>,
+    stack: Vec<usize>,
+    leaf_iter: Option<Iter<'a, T>>,
+    ray: &'a Ray,
+    infinites: Iter<'a, T>,
+}
+
+impl<'a, T> BvhIterator<'a, T> where T: PartialBoundingBox {
+    fn new<'b>(bvh: &'b Bvh<T>, ray: &'b Ray) -> BvhIterator<'b, T> {
+        BvhIterator {
+            bvh: bvh,
+            stack: if bvh.nodes.len() > 0 { vec![0] } else { vec![] },
+            leaf_iter: None,
+            ray: ray,
+            infinites: bvh.infinites.iter(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for BvhIterator<'a, T> where T: PartialBoundingBox {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        loop {
+            if let Some(prim) = self.leaf_iter.as_mut().and_then(|leaf_iter| leaf_iter.next()) {
+                return Some(prim);
+            }
+
+            let bvh = self.bvh;
+            let node = match self.stack.pop() {
+                Some(node_index) => (node_index, &bvh.nodes[node_index]),
+                None => return self.infinites.next()
+            };
+
+            if !node.1.bbox.intersects(self.ray) {
+                continue;
+            }
+
+            if node.1.count > 0 {
+                self.leaf_iter = Some(bvh.prims[node.1.offset..node.1.offset + node.1.count].iter());
+            } else {
+                self.stack.push(node.1.offset);
+                self.stack.push(node.0 + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn sphere_grid() -> Vec<Box<Prim+Send+Sync>> {
+    let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
+    for x in 0..6 {
+        for y in 0..6 {
+            for z in 0..6 {
+                prims.push(Box::new(Sphere {
+                    center: Vec3 { x: x as f64 * 3.0, y: y as f64 * 2.5, z: z as f64 * 2.0 },
+                    radius: 0.5 + (x + y + z) as f64 * 0.05,
+                    material: Box::new(FlatMaterial { color: Vec3::one() })
+                }));
+            }
+        }
+    }
+    prims
+}
+
+#[test]
+fn it_finds_the_same_nearest_hit_as_brute_force() {
+    let bvh: Bvh<Box<Prim+Send+Sync>> = sphere_grid().into_iter().collect();
+    let prims = sphere_grid();
+    let origin = Vec3 { x: -5.0, y: -4.0, z: -3.0 };
+
+    for i in 0..200 {
+        let target = Vec3 {
+            x: (i % 7) as f64 * 2.5,
+            y: (i % 11) as f64 * 1.3,
+            z: (i % 5) as f64 * 2.2
+        };
+        let ray = Ray::new(origin, (target - origin).unit());
+
+        let expected = prims.iter().filter_map(|prim| prim.intersects(&ray, 0.000001, ::std::f64::INFINITY))
+            .map(|hit| hit.t)
+            .fold(::std::f64::INFINITY, f64::min);
+        let nearest = bvh.nearest_hit(&ray, ::std::f64::INFINITY, |prim, t_max| {
+            prim.intersects(&ray, 0.000001, t_max).map(|hit| (hit.t, hit.t))
+        });
+
+        match nearest {
+            Some(t) => assert_eq!(expected, t),
+            None => assert_eq!(::std::f64::INFINITY, expected)
+        }
+    }
+}
+
+#[test]
+fn it_iterates_over_every_prim_hit_by_a_ray() {
+    let bvh: Bvh<Box<Prim+Send+Sync>> = sphere_grid().into_iter().collect();
+
+    // Straight down the z axis through the first column of spheres
+    let ray = Ray::new(Vec3 { x: 0.0, y: 0.0, z: -10.0 }, Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+    let hits = bvh.intersect_iter(&ray)
+        .filter(|prim| prim.intersects(&ray, 0.000001, ::std::f64::INFINITY).is_some())
+        .count();
+    assert_eq!(6, hits);
+
+    let miss = Ray::new(Vec3 { x: 0.0, y: 0.0, z: -10.0 }, Vec3 { x: 0.0, y: 0.0, z: -1.0 });
+    assert_eq!(0, bvh.intersect_iter(&miss).count());
+}
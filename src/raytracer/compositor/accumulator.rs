@@ -0,0 +1,102 @@
+use raytracer::compositor::{ColorRGBA, Surface};
+
+/// Running per-pixel mean of rendered passes, with enough statistics to
+/// estimate how noisy the mean still is.
+pub struct Accumulator {
+    pub width: usize,
+    pub height: usize,
+    pub passes: u32,
+    sum: Vec<ColorRGBA<f64>>,
+    luminance_sum: Vec<f64>,
+    luminance_sq_sum: Vec<f64>,
+}
+
+// Keeps near-black pixels from needing an absurd number of passes
+static MIN_LUMINANCE: f64 = 0.01;
+
+/// Rec. 709 relative luminance of linear RGB
+pub fn luminance(r: f64, g: f64, b: f64) -> f64 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// Standard error of a mean luminance relative to that luminance, from the
+/// number of samples, their sum and their sum of squares. Infinite until there
+/// are at least two samples to compare.
+pub fn relative_error(samples: u32, sum: f64, sq_sum: f64) -> f64 {
+    if samples < 2 {
+        return ::std::f64::INFINITY;
+    }
+
+    let n = samples as f64;
+    let mean = sum / n;
+    let variance = ((sq_sum - mean * mean * n) / (n - 1.0)).max(0.0);
+
+    (variance / n).sqrt() / mean.max(MIN_LUMINANCE)
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Accumulator {
+        Accumulator {
+            width: width,
+            height: height,
+            passes: 0,
+            sum: vec![ColorRGBA::new_rgb(0.0, 0.0, 0.0); width * height],
+            luminance_sum: vec![0.0; width * height],
+            luminance_sq_sum: vec![0.0; width * height],
+        }
+    }
+
+    pub fn add_pass(&mut self, pass: &Surface<f64>) {
+        for (i, color) in pass.buffer.iter().enumerate() {
+            let l = luminance(color.r, color.g, color.b);
+            self.sum[i] = self.sum[i] + *color;
+            self.luminance_sum[i] += l;
+            self.luminance_sq_sum[i] += l * l;
+        }
+        self.passes += 1;
+    }
+
+    pub fn mean(&self) -> Surface<f64> {
+        let mut surface = Surface::new(self.width, self.height, ColorRGBA::new_rgb(0.0, 0.0, 0.0));
+        let inv_passes = 1.0 / self.passes.max(1) as f64;
+
+        for (dst, sum) in surface.iter_pixels_mut().zip(self.sum.iter()) {
+            *dst = ColorRGBA::new_rgb(sum.r * inv_passes, sum.g * inv_passes, sum.b * inv_passes);
+        }
+
+        surface
+    }
+
+    /// See `relative_error`, with every pass counting as one sample
+    pub fn relative_error(&self, index: usize) -> f64 {
+        relative_error(self.passes, self.luminance_sum[index], self.luminance_sq_sum[index])
+    }
+
+    /// Fraction of pixels whose relative error is at most `threshold`
+    pub fn converged_fraction(&self, threshold: f64) -> f64 {
+        let pixels = self.width * self.height;
+        let converged = (0..pixels).filter(|&i| self.relative_error(i) <= threshold).count();
+        converged as f64 / pixels as f64
+    }
+}
+
+#[test]
+fn it_averages_passes_and_estimates_noise() {
+    let mut accumulator = Accumulator::new(2, 1);
+    for &value in [1.0, 3.0, 1.0, 3.0].iter() {
+        let mut pass = Surface::new(2, 1, ColorRGBA::new_rgb(0.5, 0.5, 0.5));
+        pass[(1, 0)] = ColorRGBA::new_rgb(value, value, value);
+        accumulator.add_pass(&pass);
+    }
+
+    let mean = accumulator.mean();
+    assert_eq!(0.5, mean[(0, 0)].r);
+    assert_eq!(2.0, mean[(1, 0)].g);
+
+    // Constant pixel has no noise, the other has a sample variance of 4/3
+    assert!(accumulator.relative_error(0) < 1e-9);
+    let expected = (4.0 / 3.0 / 4.0f64).sqrt() / 2.0;
+    assert!((accumulator.relative_error(1) - expected).abs() < 1e-9);
+    assert_eq!(0.5, accumulator.converged_fraction(0.1));
+    assert_eq!(1.0, accumulator.converged_fraction(0.5));
+}
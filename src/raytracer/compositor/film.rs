@@ -0,0 +1,142 @@
+use raytracer::compositor::{ColorRGBA, Surface};
+use raytracer::filter::Filter;
+use vec3::Vec3;
+
+/// Filter-weighted sums of samples for a tile, plus a border wide enough for
+/// the filter to reach into the neighbouring tiles' pixels.
+pub struct FilmTile {
+    x_off: isize,
+    y_off: isize,
+    width: usize,
+    height: usize,
+    sum: Vec<Vec3>,
+    weight: Vec<f64>,
+}
+
+impl FilmTile {
+    /// Covers the pixels in x_off..x_off + width, y_off..y_off + height (surface
+    /// coordinates, y down) plus the border needed for `filter_radius`
+    pub fn new(x_off: usize, y_off: usize, width: usize, height: usize, filter_radius: f64) -> FilmTile {
+        let border = (filter_radius + 0.5).ceil() as usize;
+        let (width, height) = (width + 2 * border, height + 2 * border);
+
+        FilmTile {
+            x_off: x_off as isize - border as isize,
+            y_off: y_off as isize - border as isize,
+            width: width,
+            height: height,
+            sum: vec![Vec3::zero(); width * height],
+            weight: vec![0.0; width * height],
+        }
+    }
+
+    /// Adds a sample at (x, y) in continuous surface coordinates, where pixel
+    /// (i, j) spans [i, i + 1) x [j, j + 1), to every pixel the filter reaches
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Vec3, filter: &Filter) {
+        let radius = filter.radius();
+        let x_min = (x - 0.5 - radius).ceil() as isize;
+        let x_max = (x - 0.5 + radius).floor() as isize;
+        let y_min = (y - 0.5 - radius).ceil() as isize;
+        let y_max = (y - 0.5 + radius).floor() as isize;
+
+        for py in y_min..(y_max + 1) {
+            let ty = py - self.y_off;
+            if ty < 0 || ty >= self.height as isize { continue; }
+
+            for px in x_min..(x_max + 1) {
+                let tx = px - self.x_off;
+                if tx < 0 || tx >= self.width as isize { continue; }
+
+                let weight = filter.evaluate(px as f64 + 0.5 - x, py as f64 + 0.5 - y);
+                if weight != 0.0 {
+                    let idx = ty as usize * self.width + tx as usize;
+                    self.sum[idx] = self.sum[idx] + color.scale(weight);
+                    self.weight[idx] += weight;
+                }
+            }
+        }
+    }
+}
+
+/// Whole-image counterpart of `FilmTile`, which tiles are added into
+pub struct Film {
+    width: usize,
+    height: usize,
+    sum: Vec<Vec3>,
+    weight: Vec<f64>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Film {
+        Film {
+            width: width,
+            height: height,
+            sum: vec![Vec3::zero(); width * height],
+            weight: vec![0.0; width * height],
+        }
+    }
+
+    /// Adds the tile's sums, including its border, dropping anything outside the image
+    pub fn merge(&mut self, tile: &FilmTile) {
+        for ty in 0..tile.height {
+            let y = tile.y_off + ty as isize;
+            if y < 0 || y >= self.height as isize { continue; }
+
+            for tx in 0..tile.width {
+                let x = tile.x_off + tx as isize;
+                if x < 0 || x >= self.width as isize { continue; }
+
+                let src = ty * tile.width + tx;
+                let dst = y as usize * self.width + x as usize;
+                self.sum[dst] = self.sum[dst] + tile.sum[src];
+                self.weight[dst] += tile.weight[src];
+            }
+        }
+    }
+
+    pub fn to_surface(&self) -> Surface<f64> {
+        let mut surface = Surface::new(self.width, self.height, ColorRGBA::new_rgb(0.0, 0.0, 0.0));
+
+        for (i, pixel) in surface.iter_pixels_mut().enumerate() {
+            if self.weight[i] != 0.0 {
+                let color = self.sum[i].scale(1.0 / self.weight[i]);
+                *pixel = ColorRGBA::new_rgb(color.x, color.y, color.z);
+            }
+        }
+
+        surface
+    }
+}
+
+#[test]
+fn it_splats_samples_across_tiles() {
+    use raytracer::filter::FilterKind;
+
+    let tent = FilterKind::Tent.build(1.0);
+    let mut film = Film::new(4, 1);
+
+    // Two tiles of two pixels; the sample on the left tile's edge reaches into the right tile
+    let mut left = FilmTile::new(0, 0, 2, 1, 1.0);
+    let mut right = FilmTile::new(2, 0, 2, 1, 1.0);
+    left.add_sample(1.9, 0.5, Vec3::one(), &*tent);
+    right.add_sample(3.5, 0.5, Vec3::zero(), &*tent);
+    film.merge(&left);
+    film.merge(&right);
+
+    let surface = film.to_surface();
+    assert_eq!(0.0, surface[(0, 0)].r);
+    assert_eq!(1.0, surface[(1, 0)].r);
+    // Weight 0.4 from the left sample and none from the one at 3.5, a whole pixel away
+    assert_eq!(1.0, surface[(2, 0)].r);
+    assert_eq!(0.0, surface[(3, 0)].r);
+
+    // A box of radius 0.5 keeps samples in their own pixel
+    let box_filter = FilterKind::Box.build(0.5);
+    let mut tile = FilmTile::new(0, 0, 2, 1, 0.5);
+    tile.add_sample(0.3, 0.5, Vec3::one(), &*box_filter);
+    tile.add_sample(0.7, 0.5, Vec3::zero(), &*box_filter);
+    let mut film = Film::new(2, 1);
+    film.merge(&tile);
+    assert_eq!(0.5, film.to_surface()[(0, 0)].r);
+    assert_eq!(0.0, film.to_surface()[(1, 0)].r);
+}
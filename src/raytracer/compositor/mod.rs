@@ -1,9 +1,14 @@
+pub use self::accumulator::Accumulator;
 pub use self::colorrgba::{Channel, ColorRGBA};
+pub use self::film::{Film, FilmTile};
 pub use self::surface::Surface;
 pub use self::surfacefactory::SurfaceFactory;
 pub use self::surfaceiterator::SurfaceIterator;
 
+pub mod accumulator;
 pub mod colorrgba;
+pub mod film;
 pub mod surface;
 pub mod surfacefactory;
 pub mod surfaceiterator;
+pub mod tonemap;
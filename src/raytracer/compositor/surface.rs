@@ -2,7 +2,7 @@ use std::cmp::min;
 use std::iter::repeat;
 use std::ops::{Index, IndexMut};
 
-use raytracer::compositor::{ColorRGBA, SurfaceFactory};
+use raytracer::compositor::{Channel, ColorRGBA, SurfaceFactory};
 
 pub struct IterPixelMut<'a, T: 'a>(::std::slice::IterMut<'a, ColorRGBA<T>>);
 
@@ -14,20 +14,22 @@ impl<'a, T> Iterator for IterPixelMut<'a, T> where T: 'a {
     }
 }
 
+/// A grid of pixels. Images and textures use 8-bit channels, the renderer
+/// accumulates linear radiance into a `Surface<f64>`.
 #[derive(Clone)]
-pub struct Surface {
+pub struct Surface<T = u8> {
     pub width: usize,
     pub height: usize,
     pub x_off: usize,
     pub y_off: usize,
-    pub background: ColorRGBA<u8>,
-    pub buffer: Vec<ColorRGBA<u8>>,
+    pub background: ColorRGBA<T>,
+    pub buffer: Vec<ColorRGBA<T>>,
 }
 
 
 #[allow(dead_code)]
-impl Surface {
-    pub fn new(width: usize, height: usize, background: ColorRGBA<u8>) -> Surface {
+impl<T: Channel + Copy> Surface<T> {
+    pub fn new(width: usize, height: usize, background: ColorRGBA<T>) -> Surface<T> {
         Surface {
             width: width,
             height: height,
@@ -39,7 +41,7 @@ impl Surface {
     }
 
     pub fn with_offset(width: usize, height: usize, x_off: usize, y_off: usize,
-                       background: ColorRGBA<u8>) -> Surface {
+                       background: ColorRGBA<T>) -> Surface<T> {
         Surface {
             width: width,
             height: height,
@@ -50,7 +52,7 @@ impl Surface {
         }
     }
 
-    pub fn divide(&self, tile_width: usize, tile_height: usize) -> SubsurfaceIterator {
+    pub fn divide(&self, tile_width: usize, tile_height: usize) -> SubsurfaceIterator<T> {
         SubsurfaceIterator {
             parent_width: self.width,
             parent_height: self.height,
@@ -80,7 +82,7 @@ impl Surface {
         (width, height)
     }
 
-    pub fn merge(&mut self, tile: &Surface) {
+    pub fn merge(&mut self, tile: &Surface<T>) {
         let x_len: usize = min(tile.width, self.width - tile.x_off);
         let y_len: usize = min(tile.height, self.height - tile.y_off);
 
@@ -109,41 +111,41 @@ impl Surface {
         self.width * y + x
     }
 
-    pub fn iter_pixels_mut<'a>(&'a mut self) -> IterPixelMut<'a, u8> {
+    pub fn iter_pixels_mut<'a>(&'a mut self) -> IterPixelMut<'a, T> {
         IterPixelMut(self.buffer.iter_mut())
     }
 }
 
-impl Index<(usize, usize)> for Surface {
-    type Output = ColorRGBA<u8>;
+impl<T: Channel + Copy> Index<(usize, usize)> for Surface<T> {
+    type Output = ColorRGBA<T>;
 
-    fn index<'a>(&'a self, index: (usize, usize)) -> &'a ColorRGBA<u8> {
+    fn index<'a>(&'a self, index: (usize, usize)) -> &'a ColorRGBA<T> {
         let (x, y) = index;
         let idx = self.get_idx(x, y);
         &self.buffer[idx]
     }
 }
 
-impl IndexMut<(usize, usize)> for Surface {
-    fn index_mut<'a>(&'a mut self, index: (usize, usize)) -> &'a mut ColorRGBA<u8> {
+impl<T: Channel + Copy> IndexMut<(usize, usize)> for Surface<T> {
+    fn index_mut<'a>(&'a mut self, index: (usize, usize)) -> &'a mut ColorRGBA<T> {
         let (x, y) = index;
         let idx = self.get_idx(x, y);
         &mut self.buffer[idx]
     }
 }
 
-struct SubsurfaceIterator {
+struct SubsurfaceIterator<T> {
     x_delta: usize,
     x_off: usize,
     y_delta: usize,
     y_off: usize,
     parent_width: usize,
     parent_height: usize,
-    background: ColorRGBA<u8>,
+    background: ColorRGBA<T>,
 }
 
 
-impl SubsurfaceIterator {
+impl<T: Channel + Copy> SubsurfaceIterator<T> {
     fn incr_tile(&mut self) {
         if self.x_off + self.x_delta < self.parent_width {
             self.x_off += self.x_delta;
@@ -153,7 +155,7 @@ impl SubsurfaceIterator {
         }
     }
 
-    fn current_tile(&self) -> Option<SurfaceFactory> {
+    fn current_tile(&self) -> Option<SurfaceFactory<T>> {
         if self.x_off < self.parent_width && self.y_off < self.parent_height {
             Some(SurfaceFactory::new(
                 self.x_delta,
@@ -168,10 +170,10 @@ impl SubsurfaceIterator {
     }
 }
 
-impl Iterator for SubsurfaceIterator {
-    type Item = SurfaceFactory;
+impl<T: Channel + Copy> Iterator for SubsurfaceIterator<T> {
+    type Item = SurfaceFactory<T>;
 
-    fn next(&mut self) -> Option<SurfaceFactory> {
+    fn next(&mut self) -> Option<SurfaceFactory<T>> {
         let tile = self.current_tile();
         self.incr_tile();
         tile
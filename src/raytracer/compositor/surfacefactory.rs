@@ -1,18 +1,18 @@
-use raytracer::compositor::{ColorRGBA, Surface};
+use raytracer::compositor::{Channel, ColorRGBA, Surface};
 
 
-pub struct SurfaceFactory {
+pub struct SurfaceFactory<T = u8> {
     pub width: usize,
     pub height: usize,
     pub x_off: usize,
     pub y_off: usize,
-    pub background: ColorRGBA<u8>
+    pub background: ColorRGBA<T>
 }
 
 
-impl SurfaceFactory {
+impl<T: Channel + Copy> SurfaceFactory<T> {
     pub fn new(width: usize, height: usize, x_off: usize, y_off: usize,
-               background: ColorRGBA<u8>) -> SurfaceFactory {
+               background: ColorRGBA<T>) -> SurfaceFactory<T> {
         SurfaceFactory {
             width: width,
             height: height,
@@ -23,7 +23,7 @@ impl SurfaceFactory {
     }
 
     #[allow(dead_code)]
-    pub fn create(&self) -> Surface {
+    pub fn create(&self) -> Surface<T> {
         Surface::with_offset(self.width, self.height, self.x_off, self.y_off, self.background)
     }
 }
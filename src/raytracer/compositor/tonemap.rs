@@ -0,0 +1,119 @@
+use raytracer::compositor::{ColorRGBA, Surface};
+
+/// Curve used to compress linear radiance into [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum ToneMapOperator {
+    /// Values above 1.0 are clipped.
+    Clamp,
+    /// x / (1 + x) per channel, never clips.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve. Adds contrast and desaturates highlights.
+    Aces,
+}
+
+/// Post-process turning the renderer's linear HDR surface into an 8-bit image.
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapping {
+    pub exposure: f64, // In stops, each one doubles the brightness
+    pub operator: ToneMapOperator,
+    pub srgb: bool,    // Encode with the sRGB transfer curve. Otherwise output stays linear.
+}
+
+impl Default for ToneMapping {
+    fn default() -> ToneMapping {
+        ToneMapping {
+            exposure: 0.0,
+            operator: ToneMapOperator::Clamp,
+            srgb: true
+        }
+    }
+}
+
+impl ToneMapping {
+    pub fn apply(&self, hdr: &Surface<f64>) -> Surface<u8> {
+        let mut ldr = Surface::with_offset(hdr.width, hdr.height, hdr.x_off, hdr.y_off,
+                                           ColorRGBA::new_rgb(0, 0, 0));
+
+        for (dst, src) in ldr.iter_pixels_mut().zip(hdr.buffer.iter()) {
+            *dst = ColorRGBA::new_rgb_clamped(self.map_channel(src.r),
+                                              self.map_channel(src.g),
+                                              self.map_channel(src.b));
+        }
+
+        ldr
+    }
+
+    fn map_channel(&self, value: f64) -> f64 {
+        let exposed = value.max(0.0) * 2.0f64.powf(self.exposure);
+
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => exposed,
+            ToneMapOperator::Reinhard => exposed / (1.0 + exposed),
+            ToneMapOperator::Aces => {
+                let x = exposed;
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            }
+        }.min(1.0);
+
+        if self.srgb { linear_to_srgb(mapped) } else { mapped }
+    }
+}
+
+/// sRGB transfer function (IEC 61966-2-1), [0, 1] linear to encoded
+pub fn linear_to_srgb(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse of `linear_to_srgb`, used to linearise texture images
+pub fn srgb_to_linear(encoded: f64) -> f64 {
+    if encoded <= 0.04045 {
+        encoded / 12.92
+    } else {
+        ((encoded + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[test]
+fn it_clamps_out_of_range_values() {
+    let mut hdr: Surface<f64> = Surface::new(2, 1, ColorRGBA::black());
+    hdr[(0, 0)] = ColorRGBA::new_rgb(0.5, 4.0, -1.0);
+    hdr[(1, 0)] = ColorRGBA::new_rgb(1.0, 0.0, 0.2);
+
+    let linear = ToneMapping { exposure: 0.0, operator: ToneMapOperator::Clamp, srgb: false };
+    let ldr = linear.apply(&hdr);
+    assert_eq!((128, 255, 0), (ldr[(0, 0)].r, ldr[(0, 0)].g, ldr[(0, 0)].b));
+    assert_eq!((255, 0, 51), (ldr[(1, 0)].r, ldr[(1, 0)].g, ldr[(1, 0)].b));
+
+    // Two stops down brings 4.0 back into range
+    let exposed = ToneMapping { exposure: -2.0, operator: ToneMapOperator::Clamp, srgb: false };
+    assert_eq!(255, exposed.apply(&hdr)[(0, 0)].g);
+    assert_eq!(32, exposed.apply(&hdr)[(0, 0)].r);
+}
+
+#[test]
+fn it_compresses_highlights() {
+    let reinhard = ToneMapping { exposure: 0.0, operator: ToneMapOperator::Reinhard, srgb: false };
+    assert_eq!(0.5, reinhard.map_channel(1.0));
+    assert!(reinhard.map_channel(1000.0) < 1.0);
+
+    let aces = ToneMapping { exposure: 0.0, operator: ToneMapOperator::Aces, srgb: false };
+    assert_eq!(0.0, aces.map_channel(0.0));
+    assert!(aces.map_channel(0.18) < aces.map_channel(0.5));
+    assert_eq!(1.0, aces.map_channel(1000.0));
+}
+
+#[test]
+fn it_round_trips_srgb() {
+    assert_eq!(0.0, linear_to_srgb(0.0));
+    assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-9);
+    assert!((linear_to_srgb(0.214) - 0.5).abs() < 0.001);
+
+    for i in 0..11 {
+        let v = i as f64 / 10.0;
+        assert!((srgb_to_linear(linear_to_srgb(v)) - v).abs() < 1e-9);
+    }
+}
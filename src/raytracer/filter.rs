@@ -0,0 +1,155 @@
+use std::f64::consts::PI;
+
+/// Pixel reconstruction filter (pbrt, 7.8). Weights samples by their offset
+/// from a pixel's center, measured in pixels. Every filter is separable.
+pub trait Filter {
+    /// Samples further than this along either axis get no weight
+    fn radius(&self) -> f64;
+    fn evaluate_1d(&self, x: f64) -> f64;
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let radius = self.radius();
+        if x.abs() > radius || y.abs() > radius {
+            return 0.0;
+        }
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterKind {
+    pub fn default_radius(&self) -> f64 {
+        match *self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 2.0,
+        }
+    }
+
+    pub fn build(&self, radius: f64) -> Box<Filter+Send+Sync> {
+        match *self {
+            FilterKind::Box => Box::new(BoxFilter { radius: radius }),
+            FilterKind::Tent => Box::new(TentFilter { radius: radius }),
+            FilterKind::Gaussian => Box::new(GaussianFilter { radius: radius, alpha: 2.0 }),
+            FilterKind::Mitchell => Box::new(MitchellFilter { radius: radius, b: 1.0 / 3.0, c: 1.0 / 3.0 }),
+            FilterKind::Lanczos => Box::new(LanczosFilter { radius: radius }),
+        }
+    }
+}
+
+/// Equal weight for every sample within the radius. With a radius of 0.5 this
+/// is the plain per-pixel average.
+pub struct BoxFilter {
+    pub radius: f64,
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 { self.radius }
+
+    fn evaluate_1d(&self, _: f64) -> f64 { 1.0 }
+}
+
+/// Linear falloff to zero at the radius
+pub struct TentFilter {
+    pub radius: f64,
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 { self.radius }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        (self.radius - x.abs()).max(0.0)
+    }
+}
+
+/// Gaussian shifted down so it reaches zero at the radius
+pub struct GaussianFilter {
+    pub radius: f64,
+    pub alpha: f64, // Falloff rate, higher is sharper
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 { self.radius }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        ((-self.alpha * x * x).exp() - (-self.alpha * self.radius * self.radius).exp()).max(0.0)
+    }
+}
+
+/// Mitchell-Netravali cubic. B = C = 1/3 is the authors' recommended tradeoff
+/// between blurring and ringing.
+pub struct MitchellFilter {
+    pub radius: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 { self.radius }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        // The cubic is defined over [-2, 2]
+        let x = (2.0 * x / self.radius).abs();
+        let (b, c) = (self.b, self.c);
+
+        if x > 1.0 {
+            ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x +
+             (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+        } else {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x +
+             (6.0 - 2.0 * b)) / 6.0
+        }
+    }
+}
+
+/// Sinc windowed by a wider sinc that reaches zero at the radius
+pub struct LanczosFilter {
+    pub radius: f64,
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 { self.radius }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        sinc(x) * sinc(x / self.radius)
+    }
+}
+
+#[test]
+fn it_weights_samples_by_offset() {
+    for &kind in [FilterKind::Box, FilterKind::Tent, FilterKind::Gaussian,
+                  FilterKind::Mitchell, FilterKind::Lanczos].iter() {
+        let radius = kind.default_radius();
+        let filter = kind.build(radius);
+
+        // Peak at the center, symmetric, nothing beyond the radius
+        assert!(filter.evaluate(0.0, 0.0) > 0.0);
+        assert!(filter.evaluate(0.0, 0.0) >= filter.evaluate(0.25, 0.1));
+        assert_eq!(filter.evaluate(0.3, -0.2), filter.evaluate(-0.3, 0.2));
+        assert_eq!(0.0, filter.evaluate(radius + 0.01, 0.0));
+        assert_eq!(0.0, filter.evaluate(0.0, -radius - 0.01));
+    }
+
+    let mitchell = FilterKind::Mitchell.build(2.0);
+    assert!(mitchell.evaluate_1d(1.5) < 0.0); // Negative lobe
+    assert!(mitchell.evaluate_1d(2.0).abs() < 1e-12);
+    assert!(FilterKind::Lanczos.build(2.0).evaluate_1d(1.0).abs() < 1e-12);
+}
@@ -1,12 +1,18 @@
+pub use self::accelerator::{Accelerator, AcceleratorKind};
 pub use self::animator::{Animator, CameraKeyframe};
+pub use self::bvh::Bvh;
 pub use self::intersection::Intersection;
 pub use self::ray::Ray;
 pub use self::octree::Octree;
-pub use self::renderer::{Renderer, RenderOptions};
+pub use self::renderer::{Integrator, ProgressiveOptions, Renderer, RenderOptions};
 
+pub mod accelerator;
 pub mod animator;
+pub mod bvh;
 pub mod compositor;
+pub mod filter;
 pub mod intersection;
 pub mod octree;
 pub mod ray;
 pub mod renderer;
+pub mod sampler;
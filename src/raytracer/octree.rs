@@ -46,6 +46,24 @@ impl<T> Octree<T> where T: PartialBoundingBox {
     pub fn intersect_iter<'a>(&'a self, ray: &'a Ray) -> OctreeIterator<'a, T> {
         OctreeIterator::new(self, ray)
     }
+
+    /// Candidates arrive unordered so every one of them has to be tested
+    pub fn nearest_hit<'a, H, F>(&'a self, ray: &'a Ray, t_max: f64, mut intersect: F) -> Option<H>
+            where F: FnMut(&'a T, f64) -> Option<(f64, H)> {
+        let mut nearest_t = t_max;
+        let mut nearest_hit = None;
+
+        for prim in self.intersect_iter(ray) {
+            if let Some((t, hit)) = intersect(prim, nearest_t) {
+                if t < nearest_t {
+                    nearest_t = t;
+                    nearest_hit = Some(hit);
+                }
+            }
+        }
+
+        nearest_hit
+    }
 }
 
 pub struct OctreeNode {
@@ -132,7 +150,7 @@ impl OctreeNode {
     }
 }
 
-struct OctreeIterator<'a, T:'a> {
+pub struct OctreeIterator<'a, T:'a> {
     prims: &'a [T],
     stack: Vec<&'a OctreeNode>,
     leaf_iter: Option<Iter<'a, OctreeData>>,
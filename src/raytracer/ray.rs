@@ -43,41 +43,16 @@ impl Ray {
 
     pub fn get_nearest_hit<'a>(&'a self, scene: &'a Scene) -> Option<Intersection<'a>> {
         let t_min = 0.000001;
-        let mut nearest_hit = None;
-        let mut nearest_t = INFINITY;
 
-        for prim in scene.octree.intersect_iter(self) {
-            let intersection = prim.intersects(self, t_min, nearest_t);
-
-            nearest_hit = match intersection {
-                Some(intersection) => {
-                    if intersection.t > t_min && intersection.t < nearest_t {
-                        nearest_t = intersection.t;
-                        Some(intersection)
-                    } else {
-                        nearest_hit
-                    }
-                },
-                None => nearest_hit
-            };
-        }
-
-        nearest_hit
-    }
-
-    pub fn perturb(&self, magnitude: f64) -> Ray {
-        let rand_vec = Vec3::random() * magnitude;
-
-        // Force random vectors to be in same direction as original vector
-        let corrected_rand_vec = if rand_vec.dot(&self.direction) < 0.0 {
-            rand_vec * -1.0
-        } else {
-            rand_vec
-        };
-
-        let direction = (corrected_rand_vec + self.direction).unit();
-
-        Ray::new(self.origin, direction)
+        scene.accelerator.nearest_hit(self, INFINITY, |prim, t_max| {
+            prim.intersects(self, t_min, t_max).and_then(|intersection| {
+                if intersection.t > t_min && intersection.t < t_max {
+                    Some((intersection.t, intersection))
+                } else {
+                    None
+                }
+            })
+        })
     }
 }
 
@@ -106,15 +81,16 @@ fn it_gets_the_nearest_hit() {
     prims.push(Box::new(sphere_mid));
     prims.push(Box::new(sphere_bot));
 
-    println!("Generating octree...");
-    let octree = prims.into_iter().collect();
-    println!("Octree generated...");
+    println!("Generating accelerator...");
+    let accelerator = prims.into_iter().collect();
+    println!("Accelerator generated...");
 
     let scene = Scene {
         lights: lights,
         background: Vec3::one(),
-        octree: octree,
-        skybox: None
+        accelerator: accelerator,
+        skybox: None,
+        medium: None
     };
 
     let intersecting_ray = Ray::new(
@@ -1,25 +1,90 @@
-use light::Light;
-use raytracer::compositor::{ColorRGBA, Surface, SurfaceFactory};
+use raytracer::compositor::{Accumulator, ColorRGBA, Film, FilmTile, Surface, SurfaceFactory};
+use raytracer::compositor::accumulator::{luminance, relative_error};
 use raytracer::{Intersection, Ray};
+use material::bsdf::Lobe;
+use medium::Medium;
+use raytracer::filter::FilterKind;
+use raytracer::sampler::{Sampler, SamplerKind};
 use scene::{Camera, Scene};
+use std::cmp::Ordering;
+use std::f64::INFINITY;
+use std::io::Write;
 use std::ops::Deref;
 use std::sync::Arc;
 use std::sync::mpsc::channel;
 use vec3::Vec3;
-use rand::{thread_rng, Rng, Isaac64Rng};
 use threadpool::ThreadPool;
 
+#[cfg(test)]
+use light::Light;
+
 pub static EPSILON: f64 = ::std::f64::EPSILON * 10000.0;
 
+/// How radiance along a camera ray is computed.
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum Integrator {
+    /// Recursive ray tracing: direct lighting plus mirror reflection and refraction.
+    Whitted,
+    /// Monte Carlo path tracing with next-event estimation and Russian roulette.
+    /// Adds indirect diffuse lighting in place of ambient (k_a) terms, which it ignores.
+    PathTracer,
+}
+
 #[derive(Clone, Copy)]
 pub struct RenderOptions {
-    pub reflect_depth: u32,  // Maximum reflection recursions.
+    pub reflect_depth: u32,  // Maximum reflection recursions. Maximum path length when path tracing.
     pub refract_depth: u32,  // Maximum refraction recursions. A sphere takes up 2 recursions.
     pub shadow_samples: u32, // Number of samples for soft shadows and area lights.
     pub gloss_samples: u32,  // Number of samples for glossy reflections.
     pub pixel_samples: u32,  // The square of this is the number of samples per pixel.
+    pub adaptive_threshold: Option<f64>, // Keep sampling pixels with a higher relative error than this.
+    pub max_pixel_samples: u32,          // Upper bound on samples per pixel when sampling adaptively.
+    pub filter: FilterKind,  // Reconstruction filter samples are weighted with.
+    pub filter_radius: f64,  // In pixels. A box of radius 0.5 averages the samples in each pixel.
+    pub sampler: SamplerKind,
+    pub seed: u64,           // Renders with the same options and seed come out identical.
+    pub integrator: Integrator,
+}
+
+impl Default for RenderOptions {
+    /// One sample per pixel, light and gloss, traced up to four bounces deep
+    fn default() -> RenderOptions {
+        RenderOptions {
+            reflect_depth: 4,
+            refract_depth: 4,
+            shadow_samples: 1,
+            gloss_samples: 1,
+            pixel_samples: 1,
+            adaptive_threshold: None,
+            max_pixel_samples: 1,
+            filter: FilterKind::Box,
+            filter_radius: 0.5,
+            sampler: SamplerKind::Stratified,
+            seed: 0,
+            integrator: Integrator::Whitted,
+        }
+    }
 }
 
+/// Stopping conditions for `Renderer::render_progressive`. At least one of
+/// them should be set or rendering never finishes.
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressiveOptions {
+    pub max_passes: Option<u32>,
+    pub time_limit: Option<f64>,      // Seconds. Checked after every pass.
+    pub noise_threshold: Option<f64>, // Relative standard error, see `Accumulator::relative_error`
+    pub update_every: u32,            // Passes between intermediate images, 0 for none
+}
+
+// Share of pixels below the noise threshold for the image to count as converged.
+// Leaves room for a few fireflies that would otherwise never settle.
+static CONVERGED_FRACTION: f64 = 0.99;
+
+// Most times a path is scattered inside media. Scattering does not count against
+// `reflect_depth`, so light can random walk through wax and marble; Russian
+// roulette ends long walks, this only stops those that would never end.
+static MAX_SCATTERINGS: u32 = 256;
+
 #[derive(Clone)]
 pub struct Renderer {
     pub tasks: usize, // Minimum number of tasks to spawn.
@@ -27,11 +92,67 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    pub fn render(&self, camera: Camera, shared_scene: Arc<Scene>) -> Surface {
+    /// Renders linear, unclamped radiance. Use `compositor::tonemap` to get
+    /// an 8-bit image out of it.
+    pub fn render(&self, camera: Camera, shared_scene: Arc<Scene>) -> Surface<f64> {
+        self.render_pass(camera, shared_scene, 0, false, true).0
+    }
+
+    /// Like `render`, also returning the number of samples taken for each pixel
+    /// in all channels. Useful to see where adaptive sampling spent its rays.
+    pub fn render_with_sample_map(&self, camera: Camera, shared_scene: Arc<Scene>)
+            -> (Surface<f64>, Surface<f64>) {
+        self.render_pass(camera, shared_scene, 0, false, true)
+    }
+
+    /// Renders passes of pixel_samples² samples per pixel and averages them until
+    /// one of the `options` budgets runs out. `on_update` receives the pass count
+    /// and current average every `options.update_every` passes.
+    pub fn render_progressive<F>(&self, camera: Camera, shared_scene: Arc<Scene>,
+                                 options: ProgressiveOptions, mut on_update: F) -> Surface<f64>
+            where F: FnMut(u32, &Surface<f64>) {
+        let start_time = ::time::precise_time_s();
+        let mut accumulator = Accumulator::new(camera.image_width as usize, camera.image_height as usize);
 
-        let mut surface = Surface::new(camera.image_width as usize,
-                                       camera.image_height as usize,
-                                       ColorRGBA::new_rgb(0, 0, 0));
+        loop {
+            let (pass, _) = self.render_pass(camera.clone(), shared_scene.clone(), accumulator.passes, true, false);
+            accumulator.add_pass(&pass);
+
+            let passes = accumulator.passes;
+            let elapsed = ::time::precise_time_s() - start_time;
+            let converged = options.noise_threshold.map(|threshold| accumulator.converged_fraction(threshold));
+
+            print!("\rPass {} ({:.1}s)", passes, elapsed);
+            if let Some(converged) = converged {
+                print!(", {:.2}% of pixels converged", converged * 100.0);
+            }
+            ::std::io::stdout().flush().ok().expect("failed to flush io");
+
+            let done = options.max_passes.map_or(false, |max_passes| passes >= max_passes)
+                    || options.time_limit.map_or(false, |time_limit| elapsed >= time_limit)
+                    || converged.map_or(false, |converged| converged >= CONVERGED_FRACTION);
+
+            if done {
+                println!("");
+                return accumulator.mean();
+            }
+
+            if options.update_every > 0 && passes % options.update_every == 0 {
+                on_update(passes, &accumulator.mean());
+            }
+        }
+    }
+
+    /// Returns the image and a map of the number of samples taken for each pixel.
+    /// Every pass index gets different samples, as long as they are jittered.
+    fn render_pass(&self, camera: Camera, shared_scene: Arc<Scene>, pass_index: u32, jitter: bool,
+                   show_progress: bool)
+            -> (Surface<f64>, Surface<f64>) {
+
+        let mut film = Film::new(camera.image_width as usize, camera.image_height as usize);
+        let mut sample_map = Surface::new(camera.image_width as usize,
+                                          camera.image_height as usize,
+                                          ColorRGBA::new_rgb(0.0, 0.0, 0.0));
 
         let pool = ThreadPool::new(self.tasks);
 
@@ -39,7 +160,8 @@ impl Renderer {
 
         let mut jobs = 0;
 
-        for subsurface_factory in surface.divide(128, 8) {
+        for subsurface_factory in sample_map.divide(128, 8) {
+            let job = jobs;
             jobs += 1;
 
             let renderer = self.clone();
@@ -48,201 +170,475 @@ impl Renderer {
             let camera_local = camera.clone();
 
             pool.execute(move || {
-                let _ = child_tx.send(renderer.render_tile(camera_local.clone(),
-                    scene_local.deref(), subsurface_factory));
+                let _ = child_tx.send((job, renderer.render_tile(camera_local.clone(),
+                    scene_local.deref(), subsurface_factory, pass_index, jitter)));
             });
         }
         drop(tx);
 
         let start_time = ::time::get_time();
 
-        for (i, subsurface) in rx.iter().enumerate() {
-            surface.merge(&subsurface);
-            ::util::print_progress("Tile", start_time.clone(), (i + 1) as usize, jobs);
+        // Tiles overlap where filters spill over, so they are merged in job order
+        // to add up the overlap the same way whichever tiles finish first
+        let mut finished = (0..jobs).map(|_| None).collect::<Vec<_>>();
+        let mut merged = 0;
+
+        for (i, (job, tile)) in rx.iter().enumerate() {
+            finished[job] = Some(tile);
+            while merged < jobs {
+                match finished[merged].take() {
+                    Some((film_tile, sample_counts)) => {
+                        film.merge(&film_tile);
+                        sample_map.merge(&sample_counts);
+                        merged += 1;
+                    },
+                    None => break
+                }
+            }
+
+            if show_progress {
+                ::util::print_progress("Tile", start_time.clone(), (i + 1) as usize, jobs);
+            }
         }
-        surface
+        (film.to_surface(), sample_map)
     }
 
-    fn render_tile(&self, camera: Camera, scene: &Scene, tile_factory: SurfaceFactory) -> Surface {
-        let mut tile = tile_factory.create();
-        let mut rng: Isaac64Rng = thread_rng().gen();
+    /// Returns the tile's filtered samples, which spill over into the neighbouring
+    /// tiles, and a tile holding the number of samples taken for each pixel.
+    /// Samples are jittered with `jitter` or whenever a pixel takes more than one.
+    fn render_tile(&self, camera: Camera, scene: &Scene, tile_factory: SurfaceFactory<f64>, pass_index: u32,
+                   jitter: bool) -> (FilmTile, Surface<f64>) {
+        let mut sample_counts = tile_factory.create();
+        let filter = self.options.filter.build(self.options.filter_radius);
+        let mut film_tile = FilmTile::new(sample_counts.x_off, sample_counts.y_off,
+                                          sample_counts.width, sample_counts.height,
+                                          self.options.filter_radius);
         let pixel_samples = self.options.pixel_samples;
+        // Samplers derive each pixel's samples from the seed alone, so tiles can render in any order
+        let seed = self.options.seed.wrapping_add((pass_index as u64).wrapping_mul(0x9e3779b97f4a7c15));
+        let mut sampler = self.options.sampler.build(pixel_samples, seed);
+        let jitter = jitter || pixel_samples > 1 || self.options.adaptive_threshold.is_some();
 
-        for rel_y in 0usize..tile.height {
-            let abs_y = camera.image_height as usize - (tile.y_off + rel_y) - 1;
-            for rel_x in 0usize..tile.width {
-                let abs_x = tile.x_off + rel_x;
+        for rel_y in 0usize..sample_counts.height {
+            let y = sample_counts.y_off + rel_y;
+            let abs_y = camera.image_height as usize - y - 1;
+            for rel_x in 0usize..sample_counts.width {
+                let abs_x = sample_counts.x_off + rel_x;
 
-                // Supersampling, jitter algorithm
-                let pixel_width = 1.0 / pixel_samples as f64;
-                let mut color = Vec3::zero();
+                let mut samples = 0;
+                let mut luminance_sum = 0.0;
+                let mut luminance_sq_sum = 0.0;
+
+                // Adaptive sampling takes further batches until the pixel is smooth enough
+                loop {
+                    for _ in 0..pixel_samples * pixel_samples {
+                        sampler.start_pixel_sample(abs_x as u32, abs_y as u32, samples);
 
-                for y_subpixel in 0u32..pixel_samples {
-                    for x_subpixel in 0u32..pixel_samples {
                         // Don't jitter if not antialiasing
-                        let (j_x, j_y) = if pixel_samples > 1 {
-                            (x_subpixel as f64 * pixel_width + rng.gen::<f64>() * pixel_width,
-                             y_subpixel as f64 * pixel_width + rng.gen::<f64>() * pixel_width)
+                        let (j_x, j_y) = if jitter { sampler.next_2d() } else { (0.0, 0.0) };
+
+                        let ray = camera.get_ray(abs_x as f64 + j_x, abs_y as f64 + j_y, &mut *sampler);
+                        let result = match self.options.integrator {
+                            Integrator::Whitted => Renderer::trace(scene, &ray, self.options, true, &mut *sampler),
+                            Integrator::PathTracer => Renderer::trace_path(scene, &ray, self.options, &mut *sampler)
+                        };
+
+                        // Camera y points up, surface y down. Unjittered samples sit at the pixel center.
+                        let (film_x, film_y) = if jitter {
+                            (abs_x as f64 + j_x, y as f64 + 1.0 - j_y)
                         } else {
-                            (0.0, 0.0)
+                            (abs_x as f64 + 0.5, y as f64 + 0.5)
                         };
+                        film_tile.add_sample(film_x, film_y, result, &*filter);
 
-                        let ray = camera.get_ray(abs_x as f64 + j_x, abs_y as f64 + j_y);
-                        let result = Renderer::trace(scene, &ray, self.options, false);
-                        // Clamp subpixels for now to avoid intense aliasing when combined value is clamped later
-                        // Should think of a better way to handle this
-                        color = color + result.clamp(0.0, 1.0).scale(1.0 / (pixel_samples * pixel_samples) as f64);
+                        let l = luminance(result.x, result.y, result.z);
+                        samples += 1;
+                        luminance_sum += l;
+                        luminance_sq_sum += l * l;
+                    }
+
+                    let refine = match self.options.adaptive_threshold {
+                        Some(threshold) => samples < self.options.max_pixel_samples &&
+                            relative_error(samples, luminance_sum, luminance_sq_sum) > threshold,
+                        None => false
+                    };
+
+                    if !refine {
+                        break;
                     }
                 }
-                tile[(rel_x, rel_y)] = ColorRGBA::new_rgb_clamped(color.x, color.y, color.z);
+
+                sample_counts[(rel_x, rel_y)] = ColorRGBA::new_rgb(samples as f64, samples as f64, samples as f64);
             }
         }
 
-        tile
+        (film_tile, sample_counts)
     }
 
-    fn trace(scene: &Scene, ray: &Ray, options: RenderOptions, inside: bool) -> Vec3 {
+    /// Lights were sampled for the direction of `ray` at its origin unless it
+    /// is `specular`, so only then is light it hits straight away added
+    fn trace(scene: &Scene, ray: &Ray, options: RenderOptions, specular: bool, sampler: &mut Sampler) -> Vec3 {
         if options.reflect_depth <= 0 || options.refract_depth <= 0 { return Vec3::zero() }
 
-        match ray.get_nearest_hit(scene) {
-            Some(hit) => {
+        let nearest = ray.get_nearest_hit(scene);
+        let t_hit = nearest.as_ref().map_or(INFINITY, |hit| hit.t);
+        let light = Renderer::emitted(scene, ray, t_hit);
+        let t_end = light.map_or(t_hit, |(t, _)| t);
+        let medium = Renderer::medium(scene, nearest.as_ref(), ray);
+
+        let result = match (light, nearest) {
+            (Some((_, emitted)), _) => if specular { emitted } else { Vec3::zero() },
+            (None, Some(hit)) => {
                 let n = hit.n.unit();
                 let i = (-ray.direction).unit();
+                let absorbed = Renderer::absorbed(&hit, ray);
 
-                // Local lighting computation: surface shading, shadows
-                let mut result = scene.lights.iter().fold(Vec3::zero(), |color_acc, light| {
-                    let shadow = Renderer::shadow_intensity(scene, &hit, light, options.shadow_samples);
-                    let l = (light.center() - hit.position).unit();
-
-                    color_acc + light.color() * hit.material.sample(n, i, l, hit.u, hit.v) * shadow
-                });
+                let emission = if specular { hit.material.emission(hit.u, hit.v) } else { Vec3::zero() };
+                let mut result = emission + Renderer::local_lighting(scene, &hit, &n, &i, options, sampler);
 
                 // Global lighting computation: reflections, refractions
-                if hit.material.is_reflective() || hit.material.is_refractive() {
-                    let reflect_fresnel = Renderer::fresnel_reflect(hit.material.ior(), &i, &n, inside);
-                    let refract_fresnel = 1.0 - reflect_fresnel;
-
-                    if hit.material.is_reflective() {
-                        result = result + Renderer::global_reflection(scene, &hit, options, inside,
-                                                                      &i, &n, reflect_fresnel);
-                    }
+                if hit.material.has_lobe(Lobe::Reflection) {
+                    let next_reflect_options = RenderOptions { reflect_depth: options.reflect_depth - 1, ..options };
+                    result = result + Renderer::trace_lobe(scene, &hit, &n, &i, Lobe::Reflection,
+                                                           next_reflect_options, sampler);
+                }
 
-                    if hit.material.is_refractive() {
-                        result = result + Renderer::global_transmission(scene, &hit, options, inside,
-                                                                        &i, &n, refract_fresnel);
-                    }
+                if hit.material.has_lobe(Lobe::Transmission) {
+                    let next_refract_options = RenderOptions { refract_depth: options.refract_depth - 1, ..options };
+                    result = result + Renderer::trace_lobe(scene, &hit, &n, &i, Lobe::Transmission,
+                                                           next_refract_options, sampler);
                 }
 
-                result
+                result * absorbed
             },
-            None => {
-                match scene.skybox {
-                    Some(ref skybox) => skybox.color(ray.direction),
-                    None => scene.background
-                }
+            (None, None) => Renderer::sky_color(scene, ray)
+        };
+
+        match medium {
+            Some(medium) => result * medium.transmittance(ray, t_end, sampler) +
+                            Renderer::single_scattering(scene, medium, ray, t_end, options, sampler),
+            None => result
+        }
+    }
+
+    /// Averages `gloss_samples` rays sampled from one lobe of the hit's
+    /// material. Mirrors and clear glass only have one direction to follow.
+    fn trace_lobe(scene: &Scene, hit: &Intersection, n: &Vec3, i: &Vec3, lobe: Lobe, options: RenderOptions,
+                  sampler: &mut Sampler) -> Vec3 {
+        let samples = options.gloss_samples.max(1);
+        let mut result = Vec3::zero();
+
+        for _ in 0..samples {
+            let sample = match hit.material.sample_lobe(lobe, n, i, hit.u, hit.v, sampler) {
+                Some(sample) => sample,
+                None => continue
+            };
+
+            // Offset ray origin by EPSILON * direction to avoid hitting self
+            let ray = Ray::new(hit.position + sample.wi.scale(EPSILON), sample.wi);
+            let color = Renderer::trace(scene, &ray, options, !sample.evaluated(), sampler) * sample.weight();
+            if sample.delta {
+                return color;
             }
+            result = result + color;
         }
+
+        result.scale(1.0 / samples as f64)
     }
 
-    fn global_reflection(scene: &Scene, hit: &Intersection, options: RenderOptions, inside: bool,
-                         i: &Vec3, n: &Vec3, reflect_fresnel: f64) -> Vec3 {
+    /// Unbiased Monte Carlo path tracing. Lights are sampled directly at every
+    /// vertex (next-event estimation), each bounce follows a direction sampled
+    /// from the material's BSDF, or the phase function where a medium scatters
+    /// the path, and long paths are terminated by Russian roulette. Media are
+    /// random walked through, their collisions being bounces of their own.
+    fn trace_path(scene: &Scene, camera_ray: &Ray, options: RenderOptions, sampler: &mut Sampler) -> Vec3 {
+        let mut radiance = Vec3::zero();
+        let mut throughput = Vec3::one();
+        let mut ray = Ray::new(camera_ray.origin, camera_ray.direction);
+        let mut specular = true; // Camera rays count as specular
+        let mut bounce = 0;
+        let mut scatterings = 0;
+
+        while bounce < options.reflect_depth {
+            let next_ray = {
+                let nearest = ray.get_nearest_hit(scene);
+                let t_hit = nearest.as_ref().map_or(INFINITY, |hit| hit.t);
+                let light = Renderer::emitted(scene, &ray, t_hit);
+                let t_end = light.map_or(t_hit, |(t, _)| t);
+
+                // The path may be scattered or absorbed before it gets anywhere
+                let scattered = match Renderer::medium(scene, nearest.as_ref(), &ray) {
+                    Some(medium) => {
+                        let event = medium.sample(&ray, t_end, sampler);
+                        throughput = throughput * event.weight;
+                        if throughput == Vec3::zero() {
+                            break;
+                        }
+                        event.scattered.map(|t| (medium, t))
+                    },
+                    None => None
+                };
 
-        let r = Vec3::reflect(&i, &n);
-        let reflect_ray = Ray::new(hit.position, r);
-        let next_reflect_options = RenderOptions { reflect_depth: options.reflect_depth - 1, ..options };
+                if let Some((medium, t)) = scattered {
+                    scatterings += 1;
+                    if scatterings > MAX_SCATTERINGS {
+                        break;
+                    }
+
+                    let position = ray.origin + ray.direction.scale(t);
+                    let wo = (-ray.direction).unit();
+                    let phase = medium.phase();
+
+                    radiance = radiance + throughput * Renderer::direct_lighting(scene, &position, options, sampler,
+                        |l| Vec3::one().scale(phase.eval(&wo, l)));
+
+                    if scatterings >= 3 {
+                        throughput = match Renderer::russian_roulette(throughput, sampler) {
+                            Some(throughput) => throughput,
+                            None => break
+                        };
+                    }
+
+                    // The phase function is sampled exactly, leaving the throughput as it is
+                    specular = false;
+                    let (u1, u2) = sampler.next_2d();
+                    Ray::new(position, phase.sample(&wo, u1, u2))
+                } else {
+                    // Lights were sampled directly at the previous vertex, so hitting one only
+                    // adds light after bounces that could not have sampled it
+                    if let Some((_, emitted)) = light {
+                        if specular {
+                            radiance = radiance + throughput * emitted;
+                        }
+                        break;
+                    }
 
-        let reflection = if hit.material.is_glossy() {
-            // For glossy materials, average multiple perturbed reflection rays
-            // Potential overflow by scaling after everything is done instead of scaling every iteration?
-            (0..options.gloss_samples).fold(Vec3::zero(), |acc, _| {
-                let gloss_reflect_ray = reflect_ray.perturb(hit.material.glossiness());
-                acc + Renderer::trace(scene, &gloss_reflect_ray, next_reflect_options, inside)
-            }).scale(1.0 / options.gloss_samples as f64)
+                    let hit = match nearest {
+                        Some(hit) => hit,
+                        None => {
+                            radiance = radiance + throughput * Renderer::sky_color(scene, &ray);
+                            break;
+                        }
+                    };
+                    throughput = throughput * Renderer::absorbed(&hit, &ray);
+
+                    // Emissive prims are sampled directly like lights too
+                    if specular {
+                        radiance = radiance + throughput * hit.material.emission(hit.u, hit.v);
+                    }
+
+                    let n = hit.n.unit();
+                    let i = (-ray.direction).unit();
+
+                    // Indirect light takes the place of the ambient term
+                    radiance = radiance + throughput * Renderer::direct_lighting(scene, &hit.position, options, sampler,
+                                                                                 |l| hit.material.eval(&n, &i, l, hit.u, hit.v));
+
+                    let sample = match hit.material.sample(&n, &i, hit.u, hit.v, sampler) {
+                        Some(sample) => sample,
+                        None => break
+                    };
+
+                    // Lights are sampled for everything `eval` covers
+                    specular = !sample.evaluated();
+                    throughput = throughput * sample.weight();
+
+                    if bounce >= 3 {
+                        throughput = match Renderer::russian_roulette(throughput, sampler) {
+                            Some(throughput) => throughput,
+                            None => break
+                        };
+                    }
+                    bounce += 1;
+
+                    Ray::new(hit.position + sample.wi.scale(EPSILON), sample.wi)
+                }
+            };
+
+            ray = next_ray;
+        }
+
+        radiance
+    }
+
+    /// Ends a path at random with a chance that grows as its `throughput`
+    /// drops. Paths that go on carry the throughput of those that ended.
+    fn russian_roulette(throughput: Vec3, sampler: &mut Sampler) -> Option<Vec3> {
+        let survive = throughput.x.max(throughput.y).max(throughput.z).min(0.95);
+        if survive <= 0.0 || sampler.next_1d() > survive {
+            None
         } else {
-            // For mirror-like materials just shoot a perfectly reflected ray instead
-            Renderer::trace(scene, &reflect_ray, next_reflect_options, inside)
-        };
+            Some(throughput.scale(1.0 / survive))
+        }
+    }
 
-        hit.material.global_specular(&reflection).scale(reflect_fresnel)
+    /// Local lighting computation: surface shading, shadows and the ambient term
+    fn local_lighting(scene: &Scene, hit: &Intersection, n: &Vec3, i: &Vec3, options: RenderOptions,
+                      sampler: &mut Sampler) -> Vec3 {
+        hit.material.ambient(hit.u, hit.v) +
+        Renderer::direct_lighting(scene, &hit.position, options, sampler, |l| hit.material.eval(n, i, l, hit.u, hit.v))
     }
 
-    fn global_transmission(scene: &Scene, hit: &Intersection, options: RenderOptions, inside: bool,
-                           i: &Vec3, n: &Vec3, refract_fresnel: f64) -> Vec3 {
+    /// Light arriving at `position` straight from the lights, weighted by
+    /// `eval` of the direction it arrives from. Lights with an extent are
+    /// sampled `shadow_samples` times, each sampled point from its own direction.
+    fn direct_lighting<F>(scene: &Scene, position: &Vec3, options: RenderOptions, sampler: &mut Sampler,
+                          eval: F) -> Vec3
+            where F: Fn(&Vec3) -> Vec3 {
+        scene.lights.iter().fold(Vec3::zero(), |color_acc, light| {
+            // Point light speedup (no point in sampling a point light multiple times)
+            let samples = if light.is_point() || options.shadow_samples == 0 { 1 } else { options.shadow_samples };
+            let mut color = Vec3::zero();
+
+            for _ in 0..samples {
+                let sample = light.sample(position, sampler);
+                if sample.color.x <= 0.0 && sample.color.y <= 0.0 && sample.color.z <= 0.0 {
+                    continue;
+                }
 
-        let (t, actual_refract_fresnel) = match Vec3::refract(&i, &n, hit.material.ior(), inside) {
-            Some(ref t) => (*t, refract_fresnel),
-            None => {
-                (Vec3::reflect(&i, &n), 1.0) // Fresnel of 1.0 = total internal reflection (TODO: verify)
+                let l = (sample.position - *position).unit();
+                let shadow = if options.shadow_samples > 0 {
+                    Renderer::transmission(scene, position, &sample.position, sampler)
+                } else {
+                    Vec3::one()
+                };
+
+                color = color + sample.color * eval(&l) * shadow;
             }
-        };
 
-        // Offset ray origin by EPSILON * direction to avoid hitting self when refracting
-        let refract_ray = Ray::new(hit.position + t.scale(EPSILON), t);
-        let next_refract_options = RenderOptions { refract_depth: options.refract_depth - 1, ..options };
-        let refraction = Renderer::trace(scene, &refract_ray, next_refract_options, !inside);
+            color_acc + color.scale(1.0 / samples as f64)
+        })
+    }
+
+    /// Light scattered once towards the origin of `ray` by `medium` before
+    /// `t_max`, gathered from the lights at `shadow_samples` points
+    fn single_scattering(scene: &Scene, medium: &Medium, ray: &Ray, t_max: f64, options: RenderOptions,
+                         sampler: &mut Sampler) -> Vec3 {
+        let samples = options.shadow_samples.max(1);
+        let wo = (-ray.direction).unit();
+        let phase = medium.phase();
 
-        hit.material.global_transmissive(&refraction).scale(actual_refract_fresnel)
+        // Every point gets a single sample of each light
+        let point_options = RenderOptions { shadow_samples: options.shadow_samples.min(1), ..options };
+        let mut result = Vec3::zero();
+        for _ in 0..samples {
+            if let Some((t, weight)) = medium.sample_scattering(ray, t_max, sampler) {
+                let position = ray.origin + ray.direction.scale(t);
+                result = result + weight * Renderer::direct_lighting(scene, &position, point_options, sampler,
+                    |l| Vec3::one().scale(phase.eval(&wo, l)));
+            }
+        }
+
+        result.scale(1.0 / samples as f64)
+    }
+
+    /// Beer-Lambert attenuation of light that travelled along `ray` to `hit`
+    /// inside the object the hit surface encloses
+    fn absorbed(hit: &Intersection, ray: &Ray) -> Vec3 {
+        let absorption = hit.material.absorption();
+        if hit.n.dot(&ray.direction) <= 0.0 || absorption == Vec3::zero() {
+            return Vec3::one();
+        }
+
+        let distance = hit.t * ray.direction.len();
+        Vec3 {
+            x: (-absorption.x * distance).exp(),
+            y: (-absorption.y * distance).exp(),
+            z: (-absorption.z * distance).exp()
+        }
     }
 
-    fn shadow_intensity(scene: &Scene, hit: &Intersection,
-                        light: &Box<Light+Send+Sync>, shadow_samples: u32) -> Vec3 {
-
-        if shadow_samples <= 0 { return Vec3::one() }
-
-        // Point light speedup (no point in sampling a point light multiple times)
-        let shadow_sample_tries = if light.is_point() { 1 } else { shadow_samples };
-        let mut shadow = Vec3::zero();
-
-        // Take average shadow color after jittering/sampling light position
-        for _ in 0..shadow_sample_tries {
-            // L has to be a unit vector for t_max 1:1 correspondence to
-            // distance to light to work. Shadow feelers only search up
-            // until light source.
-            let sampled_light_position = light.position();
-            let shadow_l = (sampled_light_position - hit.position).unit();
-            let shadow_ray = Ray::new(hit.position, shadow_l);
-            let distance_to_light = (sampled_light_position - hit.position).len();
-
-            // Check against candidate primitives in scene for occlusion
-            // and multiply shadow color by occluders' shadow colors
-            let candidate_nodes = scene.octree.intersect_iter(&shadow_ray);
-
-            shadow = shadow + candidate_nodes.fold(Vec3::one(), |shadow_acc, prim| {
-                let occlusion = prim.intersects(&shadow_ray, EPSILON, distance_to_light);
-                match occlusion {
-                    Some(occlusion) => shadow_acc * occlusion.material.transmission(),
-                    None => shadow_acc
+    /// Distance to the nearest light `ray` hits before reaching `t_max`, and
+    /// the radiance it emits
+    fn emitted(scene: &Scene, ray: &Ray, t_max: f64) -> Option<(f64, Vec3)> {
+        let mut nearest = None;
+        let mut nearest_t = t_max;
+
+        for light in scene.lights.iter() {
+            if let Some((t, radiance)) = light.intersect(ray) {
+                if t < nearest_t {
+                    nearest = Some((t, radiance));
+                    nearest_t = t;
                 }
-            });
+            }
         }
 
-        shadow.scale(1.0 / shadow_sample_tries as f64)
+        nearest
     }
 
-    /// Calculates the fresnel (reflectivity) given the index of refraction and the cos_angle
-    /// This uses Schlick's approximation. cos_angle is normal_dot_incoming
-    /// http://graphics.stanford.edu/courses/cs148-10-summer/docs/2006--degreve--reflection_refraction.pdf
-    fn fresnel_reflect(ior: f64, i: &Vec3, n: &Vec3, inside: bool) -> f64 {
-        let (n1, n2) = if inside { (ior, 1.0) } else { (1.0, ior) };
-        let actual_n = if inside { -*n } else { *n };
+    /// Medium `ray` travels through on its way to `hit`: that of the closed
+    /// surface it leaves, which is none for plain glass, otherwise the scene's
+    fn medium<'a>(scene: &'a Scene, hit: Option<&Intersection<'a>>, ray: &Ray) -> Option<&'a Medium> {
+        match hit {
+            Some(hit) if hit.n.dot(&ray.direction) > 0.0 => hit.material.medium(),
+            _ => scene.medium.as_ref().map(|medium| &**medium as &Medium)
+        }
+    }
 
-        let r0_sqrt = (n1 - n2) / (n1 + n2);
-        let r0 = r0_sqrt * r0_sqrt;
+    fn sky_color(scene: &Scene, ray: &Ray) -> Vec3 {
+        match scene.skybox {
+            Some(ref skybox) => skybox.color(ray.direction),
+            None => scene.background
+        }
+    }
 
-        let cos_angle = if n1 <= n2 {
-            i.dot(&actual_n)
-        } else {
-            let t = match Vec3::refract(i, &-actual_n, ior, inside) {
-                Some(x) => x,
-                None => return 1.0 // n1 > n2 && TIR
-            };
+    /// Fraction of the light leaving `to` that reaches `from`: the product of
+    /// the transmission of everything in between, surfaces and media
+    fn transmission(scene: &Scene, from: &Vec3, to: &Vec3, sampler: &mut Sampler) -> Vec3 {
+        // L has to be a unit vector for t_max 1:1 correspondence to
+        // distance to light to work. Shadow feelers only search up
+        // until light source.
+        let to_light = *to - *from;
+        let distance_to_light = to_light.len();
+        let shadow_ray = Ray::new(*from, to_light.scale(1.0 / distance_to_light));
 
-            -actual_n.dot(&t) // n1 > n2 && !TIR
-        };
+        // Stop short of `to` relative to the distance, so an emissive triangle
+        // does not shadow the point sampled on it
+        let t_max = distance_to_light * (1.0 - 1e-9) - EPSILON;
+
+        // Check against candidate primitives in scene for occlusion
+        // and multiply shadow color by occluders' shadow colors
+        let mut shadow = Vec3::one();
+        let mut occluders = Vec::new();
+        for prim in scene.accelerator.intersect_iter(&shadow_ray) {
+            if let Some(occlusion) = prim.intersects(&shadow_ray, EPSILON, t_max) {
+                shadow = shadow * occlusion.material.transmission(occlusion.u, occlusion.v);
+                if shadow == Vec3::zero() {
+                    return shadow;
+                }
+                occluders.push(occlusion);
+            }
+        }
 
-        let cos_term = 1.0 - cos_angle;
+        if scene.medium.is_none() && occluders.iter().all(|occlusion| occlusion.material.medium().is_none()) {
+            return shadow;
+        }
 
-        (r0 + ((1.0 - r0) * cos_term * cos_term * cos_term * cos_term * cos_term)).max(0.0).min(1.0)
+        // Media between the occluders, each segment in the medium it leaves
+        // into the next occluder through, and the last one in whatever the
+        // last occluder enclosed
+        occluders.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Ordering::Equal));
+        let mut t_start = 0.0;
+        for occlusion in occluders.iter() {
+            if let Some(medium) = Renderer::medium(scene, Some(occlusion), &shadow_ray) {
+                let segment = Ray::new(*from + shadow_ray.direction.scale(t_start), shadow_ray.direction);
+                shadow = shadow * medium.transmittance(&segment, occlusion.t - t_start, sampler);
+            }
+            t_start = occlusion.t;
+        }
+
+        let last = match occluders.last() {
+            Some(occlusion) if occlusion.n.dot(&shadow_ray.direction) < 0.0 => occlusion.material.medium(),
+            _ => scene.medium.as_ref().map(|medium| &**medium as &Medium)
+        };
+
+        match last {
+            Some(medium) => {
+                let segment = Ray::new(*from + shadow_ray.direction.scale(t_start), shadow_ray.direction);
+                shadow * medium.transmittance(&segment, t_max - t_start, sampler)
+            },
+            None => shadow
+        }
     }
 }
 
@@ -259,9 +655,10 @@ fn it_renders_the_background_of_an_empty_scene() {
 
     let test_scene = Scene {
         lights: vec!(),
-        octree: vec!().into_iter().collect(),
+        accelerator: vec!().into_iter().collect(),
         background: Vec3 { x: 1.0, y: 0.0, z: 0.0 },
-        skybox: None
+        skybox: None,
+        medium: None
     };
 
     let shared_scene = Arc::new(test_scene);
@@ -269,9 +666,7 @@ fn it_renders_the_background_of_an_empty_scene() {
     let render_options = RenderOptions {
         reflect_depth: 1,
         refract_depth: 1,
-        shadow_samples: 1,
-        gloss_samples: 1,
-        pixel_samples: 1,
+        ..Default::default()
     };
 
 
@@ -283,8 +678,397 @@ fn it_renders_the_background_of_an_empty_scene() {
     let image_data = renderer.render(camera, shared_scene);
 
     for color in image_data.buffer.iter() {
-        assert_eq!(color.r, 255);
-        assert_eq!(color.g, 0);
-        assert_eq!(color.b, 0);
+        assert_eq!(color.r, 1.0);
+        assert_eq!(color.g, 0.0);
+        assert_eq!(color.b, 0.0);
     }
 }
+
+#[test]
+fn it_path_traces_a_lit_plane() {
+    use geometry::prims::Plane;
+    use light::lights::PointLight;
+    use material::materials::FlatMaterial;
+
+    let camera = Camera::new(
+        Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+        45.0,
+        8,
+        8
+    );
+
+    let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
+    // Bright enough that the irradiance over PI is at least 1 across the view
+    let intensity = Vec3::one().scale(32.0 * ::std::f64::consts::PI);
+    lights.push(Box::new(PointLight { position: Vec3 { x: 0.0, y: 5.0, z: 0.0 }, intensity: intensity }));
+
+    let test_scene = Scene {
+        lights: lights,
+        accelerator: vec!(Box::new(Plane {
+            a: 0.0, b: 1.0, c: 0.0, d: 0.0,
+            material: Box::new(FlatMaterial { color: Vec3 { x: 0.0, y: 0.5, z: 0.0 } })
+        }) as Box<::geometry::Prim+Send+Sync>).into_iter().collect(),
+        background: Vec3 { x: 1.0, y: 0.0, z: 0.0 },
+        skybox: None,
+        medium: None
+    };
+
+    let render_options = RenderOptions {
+        pixel_samples: 2,
+        integrator: Integrator::PathTracer,
+        max_pixel_samples: 4,
+        ..Default::default()
+    };
+
+    let renderer = Renderer {
+        options: render_options,
+        tasks: 2,
+    };
+
+    let image_data = renderer.render(camera, Arc::new(test_scene));
+
+    // Every vertex adds the flat green under the light. Paths that escape to the
+    // red background carry no red, as the plane does not reflect any.
+    for color in image_data.buffer.iter() {
+        assert_eq!(color.r, 0.0);
+        assert!(color.g >= 0.499);
+        assert_eq!(color.b, 0.0);
+    }
+}
+
+#[test]
+fn it_lights_a_plane_with_an_emissive_sphere() {
+    use geometry::Prim;
+    use geometry::prims::{Plane, Sphere};
+    use light::lights::PrimLight;
+    use material::materials::PhongMaterial;
+
+    // Seen from under it the sphere covers sin² = 1/9 of the hemisphere's
+    // projected area, so the white plane reflects at most 1 under an emission
+    // of 9, less further out
+    let prims = || -> Vec<Box<Prim+Send+Sync>> {
+        vec![
+            Box::new(Plane {
+                a: 0.0, b: 1.0, c: 0.0, d: 0.0,
+                material: Box::new(PhongMaterial { k_s: 0.0, diffuse: Vec3::one(), ..Default::default() })
+            }),
+            Box::new(Sphere {
+                center: Vec3 { x: 0.0, y: 3.0, z: 0.0 },
+                radius: 1.0,
+                material: Box::new(PhongMaterial { k_d: 0.0, k_s: 0.0, emission: Vec3::one().scale(9.0),
+                                                   ..Default::default() })
+            })
+        ]
+    };
+
+    for &integrator in [Integrator::Whitted, Integrator::PathTracer].iter() {
+        let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
+        for light in PrimLight::from_prims(&prims()).into_iter() {
+            lights.push(Box::new(light));
+        }
+
+        let scene = Scene {
+            lights: lights,
+            accelerator: prims().into_iter().collect(),
+            background: Vec3::zero(),
+            skybox: None,
+            medium: None
+        };
+
+        let camera = Camera::new(
+            Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+            Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+            Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+            45.0,
+            8,
+            8
+        );
+
+        let renderer = Renderer {
+            options: RenderOptions {
+                shadow_samples: 16,
+                pixel_samples: 2,
+                integrator: integrator,
+                max_pixel_samples: 4,
+                ..Default::default()
+            },
+            tasks: 2,
+        };
+
+        let image_data = renderer.render(camera, Arc::new(scene));
+        for color in image_data.buffer.iter() {
+            assert!(color.r > 0.6 && color.r < 1.05, "{:?}: {}", integrator, color.r);
+        }
+    }
+}
+
+#[test]
+fn it_dims_the_background_through_fog() {
+    use geometry::BBox;
+    use medium::media::HomogeneousMedium;
+
+    // Fog that only absorbs, two units deep along the ray
+    let fog = HomogeneousMedium {
+        absorption: Vec3::one().scale(0.5),
+        scattering: Vec3::zero(),
+        g: 0.0,
+        bounds: Some(BBox { min: Vec3 { x: -1.0, y: 1.0, z: -1.0 }, max: Vec3 { x: 1.0, y: 3.0, z: 1.0 } })
+    };
+
+    let test_scene = Scene {
+        lights: vec!(),
+        accelerator: vec!().into_iter().collect(),
+        background: Vec3::one(),
+        skybox: None,
+        medium: Some(Arc::new(fog))
+    };
+
+    let options = RenderOptions {
+        sampler: SamplerKind::Independent,
+        ..Default::default()
+    };
+
+    let ray = Ray::new(Vec3::zero(), Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+    let mut sampler = options.sampler.build(1, 0);
+    let expected = (-1.0f64).exp();
+
+    let traced = Renderer::trace(&test_scene, &ray, options, true, &mut *sampler);
+    assert!((traced.x - expected).abs() < 1e-12);
+
+    // The path tracer gets the same on average, absorbing whole paths
+    let runs = 10000;
+    let path_traced = (0..runs).fold(0.0, |total, _| {
+        total + Renderer::trace_path(&test_scene, &ray, options, &mut *sampler).x / runs as f64
+    });
+    assert!((path_traced - expected).abs() < 0.02, "path traced {}", path_traced);
+}
+
+#[test]
+fn it_keeps_fog_out_of_glass() {
+    use geometry::{BBox, Prim};
+    use geometry::prims::Sphere;
+    use material::materials::DielectricMaterial;
+    use medium::media::HomogeneousMedium;
+
+    // Absorbing fog three units up from the center of an invisible glass
+    // sphere, only the two units outside the sphere should dim the light
+    let fog = HomogeneousMedium {
+        absorption: Vec3::one().scale(0.5),
+        scattering: Vec3::zero(),
+        g: 0.0,
+        bounds: Some(BBox { min: Vec3 { x: -3.0, y: -3.0, z: -3.0 }, max: Vec3 { x: 3.0, y: 3.0, z: 3.0 } })
+    };
+    let glass = DielectricMaterial { ior: 1.0, ..Default::default() };
+
+    let prims: Vec<Box<Prim+Send+Sync>> = vec![Box::new(Sphere {
+        center: Vec3::zero(),
+        radius: 1.0,
+        material: Box::new(glass)
+    })];
+    let test_scene = Scene {
+        lights: vec!(),
+        accelerator: prims.into_iter().collect(),
+        background: Vec3::one(),
+        skybox: None,
+        medium: Some(Arc::new(fog))
+    };
+
+    let options = RenderOptions {
+        sampler: SamplerKind::Independent,
+        ..Default::default()
+    };
+
+    let up = Vec3 { x: 0.0, y: 3.0, z: 0.0 };
+    let mut sampler = options.sampler.build(1, 0);
+    let expected = (-1.0f64).exp();
+
+    let shadow = Renderer::transmission(&test_scene, &Vec3::zero(), &up, &mut *sampler);
+    assert!((shadow.x - expected).abs() < 1e-6, "shadow {}", shadow.x);
+
+    let ray = Ray::new(Vec3::zero(), Vec3 { x: 0.0, y: 1.0, z: 0.0 });
+    let traced = Renderer::trace(&test_scene, &ray, options, true, &mut *sampler);
+    assert!((traced.x - expected).abs() < 1e-6, "traced {}", traced.x);
+}
+
+#[test]
+fn it_stops_sampling_smooth_pixels() {
+    let camera = Camera::new(
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+        45.0,
+        8,
+        8
+    );
+
+    let test_scene = Scene {
+        lights: vec!(),
+        accelerator: vec!().into_iter().collect(),
+        background: Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+        skybox: None,
+        medium: None
+    };
+
+    let render_options = RenderOptions {
+        reflect_depth: 1,
+        refract_depth: 1,
+        adaptive_threshold: Some(0.001),
+        max_pixel_samples: 16,
+        filter: FilterKind::Gaussian,
+        filter_radius: 1.5,
+        ..Default::default()
+    };
+
+    let renderer = Renderer {
+        options: render_options,
+        tasks: 2,
+    };
+
+    // A flat background needs a second sample to show it has no variance, and no more
+    let (image_data, sample_map) = renderer.render_with_sample_map(camera, Arc::new(test_scene));
+    for (color, samples) in image_data.buffer.iter().zip(sample_map.buffer.iter()) {
+        assert!((color.b - 1.0).abs() < 1e-9);
+        assert_eq!(samples.r, 2.0);
+    }
+}
+
+#[test]
+fn it_jitters_progressive_passes_of_one_sample() {
+    use geometry::prim::Prim;
+    use geometry::prims::Sphere;
+    use material::materials::FlatMaterial;
+
+    let camera = Camera::new(
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+        Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        45.0,
+        16,
+        16
+    );
+
+    // Edges of an unlit sphere against the sky only come out antialiased if
+    // every pass samples elsewhere
+    let prims: Vec<Box<Prim+Send+Sync>> = vec![Box::new(Sphere {
+        center: Vec3 { x: 0.0, y: 0.0, z: 4.0 }, radius: 1.0,
+        material: Box::new(FlatMaterial { color: Vec3::one() })
+    })];
+    let test_scene = Scene {
+        lights: vec!(),
+        accelerator: prims.into_iter().collect(),
+        background: Vec3::one(),
+        skybox: None,
+        medium: None
+    };
+
+    let renderer = Renderer {
+        options: RenderOptions {
+            reflect_depth: 1,
+            refract_depth: 1,
+            ..Default::default()
+        },
+        tasks: 2,
+    };
+    let progressive_options = ProgressiveOptions {
+        max_passes: Some(3),
+        time_limit: None,
+        noise_threshold: None,
+        update_every: 1
+    };
+
+    let mut updates = Vec::new();
+    renderer.render_progressive(camera, Arc::new(test_scene), progressive_options, |_, image| {
+        updates.push(image.buffer.clone());
+    });
+
+    assert_eq!(updates.len(), 2);
+    assert!(updates[0].iter().zip(updates[1].iter()).any(|(first, second)| first.r != second.r));
+}
+
+#[test]
+fn it_renders_identically_for_a_seed() {
+    use geometry::prim::Prim;
+    use geometry::prims::Sphere;
+    use light::lights::SphereLight;
+    use material::materials::FlatMaterial;
+
+    let camera = Camera::new(
+        Vec3 { x: 0.0, y: 0.0, z: 0.0 },
+        Vec3 { x: 0.0, y: 0.0, z: 1.0 },
+        Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+        45.0,
+        24,
+        24
+    );
+
+    // Soft shadow of a small sphere on a big one
+    let new_scene = || {
+        let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
+        prims.push(Box::new(Sphere {
+            center: Vec3 { x: 0.0, y: 0.0, z: 6.0 }, radius: 2.0,
+            material: Box::new(FlatMaterial { color: Vec3::one() })
+        }));
+        prims.push(Box::new(Sphere {
+            center: Vec3 { x: 0.0, y: 1.0, z: 3.5 }, radius: 0.5,
+            material: Box::new(FlatMaterial { color: Vec3::one() })
+        }));
+        let lights: Vec<Box<Light+Send+Sync>> = vec![Box::new(SphereLight {
+            position: Vec3 { x: 0.0, y: 3.0, z: 2.0 }, color: Vec3::one(), radius: 2.0
+        })];
+
+        Arc::new(Scene {
+            lights: lights,
+            accelerator: prims.into_iter().collect(),
+            background: Vec3::zero(),
+            skybox: None,
+            medium: None
+        })
+    };
+
+    let render_options = RenderOptions {
+        reflect_depth: 1,
+        refract_depth: 1,
+        shadow_samples: 4,
+        pixel_samples: 2,
+        max_pixel_samples: 4,
+        filter: FilterKind::Gaussian,
+        filter_radius: 1.5,
+        sampler: SamplerKind::Sobol,
+        seed: 42,
+        ..Default::default()
+    };
+
+    let render = |tasks, seed| {
+        let renderer = Renderer { options: RenderOptions { seed: seed, ..render_options }, tasks: tasks };
+        let image = renderer.render(camera.clone(), new_scene());
+        image.buffer.iter().map(|color| (color.r, color.g, color.b)).collect::<Vec<_>>()
+    };
+
+    // The same bits whatever order the tiles finish in, and a different seed gives different noise
+    let image = render(1, 42);
+    assert!(image == render(4, 42));
+    assert!(image != render(1, 43));
+}
+
+#[test]
+fn it_keeps_the_expected_throughput_through_russian_roulette() {
+    use raytracer::sampler::SamplerKind;
+
+    let throughput = Vec3 { x: 0.5, y: 0.25, z: 0.1 };
+    let mut sampler = SamplerKind::Stratified.build(32, 0);
+    let mut total = Vec3::zero();
+    let mut survived = 0;
+    for index in 0..1024 {
+        sampler.start_pixel_sample(0, 0, index);
+        if let Some(kept) = Renderer::russian_roulette(throughput, &mut *sampler) {
+            total = total + kept.scale(1.0 / 1024.0);
+            survived += 1;
+        }
+    }
+
+    assert!(survived > 480 && survived < 544);
+    assert!((total - throughput).len() < 0.02);
+    assert!(Renderer::russian_roulette(Vec3::zero(), &mut *sampler).is_none());
+}
@@ -0,0 +1,459 @@
+use std::cmp::Ordering;
+use std::f64::EPSILON;
+use std::f64::consts::PI;
+use std::u32;
+
+/// Supplies the numbers behind every random decision made while rendering a
+/// pixel: camera jitter, light positions, glossy and path directions. Each
+/// pixel sample is a point with as many [0, 1) dimensions as the renderer asks
+/// for. Low-discrepancy samplers spread those points more evenly across a
+/// pixel's samples than independent random numbers, so there is less noise at
+/// the same sample count.
+pub trait Sampler {
+    /// Moves to the `index`th sample of pixel (x, y), starting again at dimension 0
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32);
+
+    /// Next dimension of the current sample, in [0, 1)
+    fn next_1d(&mut self) -> f64;
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let u = self.next_1d();
+        (u, self.next_1d())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable, RustcEncodable)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    /// `pixel_samples` is the square root of the samples taken per pixel, as in RenderOptions
+    pub fn build(&self, pixel_samples: u32, seed: u64) -> Box<Sampler> {
+        let pixel = PixelSample::new(seed);
+        match *self {
+            SamplerKind::Independent => Box::new(IndependentSampler { pixel: pixel }),
+            SamplerKind::Stratified => Box::new(StratifiedSampler { pixel: pixel, strata: pixel_samples.max(1) }),
+            SamplerKind::Halton => Box::new(HaltonSampler { pixel: pixel, primes: primes(HALTON_DIMENSIONS) }),
+            SamplerKind::Sobol => Box::new(SobolSampler { pixel: pixel, directions: sobol_directions() }),
+        }
+    }
+}
+
+/// Where a sampler is: which sample of which pixel, and how many dimensions of
+/// it have been used
+#[derive(Clone, Copy)]
+struct PixelSample {
+    seed: u64,
+    pixel_key: u64, // Hash of the seed and pixel
+    index: u32,
+    dimension: u32,
+}
+
+impl PixelSample {
+    fn new(seed: u64) -> PixelSample {
+        PixelSample { seed: seed, pixel_key: seed, index: 0, dimension: 0 }
+    }
+
+    fn start(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_key = hash(self.seed, &[x as u64, y as u64]);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    /// Random bits for the current dimension, the same for every sample of the pixel
+    fn pixel_hash(&self, salt: u64) -> u64 {
+        hash(self.pixel_key, &[self.dimension as u64, salt])
+    }
+
+    /// Random number for the current dimension of this sample alone
+    fn random(&self) -> f64 {
+        to_unit(hash(self.pixel_key, &[self.dimension as u64, self.index as u64 + (1 << 32)]))
+    }
+}
+
+/// Combines a seed and some values into well distributed bits, using
+/// MurmurHash3's 64-bit finalizer
+fn hash(seed: u64, values: &[u64]) -> u64 {
+    let mut h = seed;
+    for &value in values.iter() {
+        h = mix(h ^ mix(value.wrapping_add(0x9e3779b97f4a7c15)));
+    }
+    h
+}
+
+fn mix(mut h: u64) -> u64 {
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^ (h >> 33)
+}
+
+/// Top 53 bits as a float in [0, 1)
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Uncorrelated random numbers for every dimension
+pub struct IndependentSampler {
+    pixel: PixelSample,
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let u = self.pixel.random();
+        self.pixel.dimension += 1;
+        u
+    }
+}
+
+/// Jittered strata, strata² of them in every dimension (strata × strata grid
+/// cells for pairs). Each batch of strata² samples visits every stratum once,
+/// in an order shuffled separately for each pixel and dimension so the
+/// dimensions are not correlated.
+pub struct StratifiedSampler {
+    pixel: PixelSample,
+    strata: u32,
+}
+
+impl StratifiedSampler {
+    /// Stratum of the current sample, out of `count`
+    fn stratum(&self, count: u32) -> u32 {
+        let batch = self.pixel.index / count;
+        permute(self.pixel.index % count, count, self.pixel.pixel_hash(batch as u64) as u32)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let count = self.strata * self.strata;
+        let u = (self.stratum(count) as f64 + self.pixel.random()) / count as f64;
+        self.pixel.dimension += 1;
+        u
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let stratum = self.stratum(self.strata * self.strata);
+        let (stratum_x, stratum_y) = (stratum % self.strata, stratum / self.strata);
+
+        let j_x = self.pixel.random();
+        self.pixel.dimension += 1;
+        let j_y = self.pixel.random();
+        self.pixel.dimension += 1;
+
+        ((stratum_x as f64 + j_x) / self.strata as f64,
+         (stratum_y as f64 + j_y) / self.strata as f64)
+    }
+}
+
+/// Pseudorandom permutation of 0..count, picked by `key`. Kensler, Correlated
+/// Multi-Jittered Sampling (2013).
+fn permute(mut i: u32, count: u32, key: u32) -> u32 {
+    let mut w = count - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    // Scramble within the next power of two, retrying until the result is in range
+    loop {
+        i ^= key;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= key >> 16;
+        i ^= (i & w) >> 4;
+        i ^= key >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= key >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | key >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < count { break; }
+    }
+
+    (i + key) % count
+}
+
+// Dimensions past these get independent random numbers
+static HALTON_DIMENSIONS: usize = 128;
+
+/// Halton sequence, one prime base per dimension. Each digit position of each
+/// dimension gets its own random permutation of the digits for every pixel
+/// (random digit scrambling), which breaks up the correlation between the
+/// higher bases over few samples.
+pub struct HaltonSampler {
+    pixel: PixelSample,
+    primes: Vec<u32>,
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let u = match self.primes.get(self.pixel.dimension as usize) {
+            Some(&base) => self.scrambled_radical_inverse(base),
+            None => self.pixel.random()
+        };
+        self.pixel.dimension += 1;
+        u
+    }
+}
+
+impl HaltonSampler {
+    /// Mirrors the base `base` digits of the sample index around the radix
+    /// point, permuting each. Digits past any a u32 index can have are random.
+    fn scrambled_radical_inverse(&self, base: u32) -> f64 {
+        let key = self.pixel.pixel_hash(0);
+        let inv_base = 1.0 / base as f64;
+        let mut index = self.pixel.index;
+        let mut digit_scale = 1.0;
+        let mut reach = 1u64;
+        let mut result = 0.0;
+
+        while reach <= u32::MAX as u64 {
+            let digit = permute(index % base, base, mix(key.wrapping_add(reach)) as u32);
+            digit_scale *= inv_base;
+            result += digit as f64 * digit_scale;
+            index /= base;
+            reach *= base as u64;
+        }
+
+        (result + self.pixel.random() * digit_scale).min(1.0 - EPSILON)
+    }
+}
+
+fn primes(count: usize) -> Vec<u32> {
+    let mut primes: Vec<u32> = Vec::with_capacity(count);
+    let mut candidate = 2;
+
+    while primes.len() < count {
+        if primes.iter().take_while(|&&p| p * p <= candidate).all(|&p| candidate % p != 0) {
+            primes.push(candidate);
+        }
+        candidate += 1;
+    }
+
+    primes
+}
+
+// Joe and Kuo's primitive polynomials (degree, coefficients) and initial direction
+// numbers for Sobol dimensions 2 to 16. http://web.maths.unsw.edu.au/~fkuo/sobol/
+static SOBOL_POLYNOMIALS: [(u32, u32, [u32; 6]); 15] = [
+    (1, 0, [1, 0, 0, 0, 0, 0]),
+    (2, 1, [1, 3, 0, 0, 0, 0]),
+    (3, 1, [1, 3, 1, 0, 0, 0]),
+    (3, 2, [1, 1, 1, 0, 0, 0]),
+    (4, 1, [1, 1, 3, 3, 0, 0]),
+    (4, 4, [1, 3, 5, 13, 0, 0]),
+    (5, 2, [1, 1, 5, 5, 17, 0]),
+    (5, 4, [1, 1, 5, 5, 5, 0]),
+    (5, 7, [1, 1, 7, 11, 19, 0]),
+    (5, 11, [1, 1, 5, 1, 1, 0]),
+    (5, 13, [1, 1, 1, 3, 11, 0]),
+    (5, 14, [1, 3, 5, 5, 31, 0]),
+    (6, 1, [1, 3, 3, 9, 7, 49]),
+    (6, 13, [1, 1, 1, 15, 21, 21]),
+    (6, 16, [1, 3, 1, 13, 27, 49]),
+];
+
+/// Direction numbers for every bit of the sample index, in 0.32 fixed point
+fn sobol_directions() -> Vec<[u32; 32]> {
+    let mut directions = Vec::with_capacity(SOBOL_POLYNOMIALS.len() + 1);
+
+    // The first dimension is the base 2 radical inverse
+    let mut v = [0u32; 32];
+    for k in 0..32 {
+        v[k] = 1 << (31 - k);
+    }
+    directions.push(v);
+
+    for &(degree, coefficients, ref initial) in SOBOL_POLYNOMIALS.iter() {
+        let s = degree as usize;
+        let mut v = [0u32; 32];
+
+        for k in 0..32 {
+            v[k] = if k < s {
+                initial[k] << (31 - k)
+            } else {
+                let mut x = v[k - s] ^ (v[k - s] >> s);
+                for j in 1..s {
+                    if (coefficients >> (s - 1 - j)) & 1 == 1 {
+                        x ^= v[k - j];
+                    }
+                }
+                x
+            };
+        }
+        directions.push(v);
+    }
+
+    directions
+}
+
+/// Sobol sequence, scrambled for each pixel and dimension by XORing the digits
+/// with random bits, which keeps the sequence's stratification
+pub struct SobolSampler {
+    pixel: PixelSample,
+    directions: Vec<[u32; 32]>,
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel.start(x, y, index);
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let u = match self.directions.get(self.pixel.dimension as usize) {
+            Some(v) => {
+                let mut bits = self.pixel.pixel_hash(0) as u32;
+                let mut index = self.pixel.index;
+                let mut k = 0;
+                while index > 0 {
+                    if index & 1 == 1 {
+                        bits ^= v[k];
+                    }
+                    index >>= 1;
+                    k += 1;
+                }
+                bits as f64 / (1u64 << 32) as f64
+            },
+            None => self.pixel.random()
+        };
+        self.pixel.dimension += 1;
+        u
+    }
+}
+
+/// Maps the unit square to the unit disk keeping areas proportional, and
+/// stratification intact (Shirley and Chiu's concentric mapping)
+pub fn concentric_disk(u1: f64, u2: f64) -> (f64, f64) {
+    let (a, b) = (2.0 * u1 - 1.0, 2.0 * u2 - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Normalised running sum of `values`, uniform if they are all zero
+pub fn cdf(values: &[f64]) -> Vec<f64> {
+    let mut sums = Vec::with_capacity(values.len());
+    let mut total = 0.0;
+    for value in values.iter() {
+        total += *value;
+        sums.push(total);
+    }
+
+    let count = values.len() as f64;
+    sums.iter().enumerate().map(|(index, sum)| {
+        if total > 0.0 { sum / total } else { (index + 1) as f64 / count }
+    }).collect()
+}
+
+/// Picks an index for `u` in [0, 1) from `cdf`, and where `u` fell within it
+pub fn pick(cdf: &[f64], u: f64) -> (usize, f64) {
+    let index = match cdf.binary_search_by(|value| value.partial_cmp(&u).unwrap_or(Ordering::Less)) {
+        Ok(index) => index + 1,
+        Err(index) => index
+    }.min(cdf.len() - 1);
+
+    let start = if index == 0 { 0.0 } else { cdf[index - 1] };
+    let width = cdf[index] - start;
+    let offset = if width > 0.0 { ((u - start) / width).max(0.0).min(1.0) } else { 0.5 };
+    (index, offset)
+}
+
+#[test]
+fn it_permutes() {
+    for &count in [1, 5, 16, 100].iter() {
+        let mut seen = vec![false; count as usize];
+        for i in 0..count {
+            seen[permute(i, count, 0xdeadbeef) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+
+    assert_eq!(vec![2, 3, 5, 7, 11, 13], primes(6));
+}
+
+#[test]
+fn it_stratifies_each_pixels_samples() {
+    for &kind in [SamplerKind::Independent, SamplerKind::Stratified,
+                  SamplerKind::Halton, SamplerKind::Sobol].iter() {
+        let mut sampler = kind.build(4, 7);
+        let mut pairs = Vec::new();
+        let mut singles = Vec::new();
+
+        for index in 0..16 {
+            sampler.start_pixel_sample(3, 5, index);
+            pairs.push(sampler.next_2d());
+            for _ in 0..30 {
+                let u = sampler.next_1d();
+                assert!(u >= 0.0 && u < 1.0);
+            }
+            singles.push(sampler.next_1d());
+        }
+
+        // Same sample, same numbers; another pixel, other numbers
+        sampler.start_pixel_sample(3, 5, 2);
+        assert_eq!(pairs[2], sampler.next_2d());
+        sampler.start_pixel_sample(4, 5, 2);
+        assert!(pairs[2] != sampler.next_2d());
+
+        // Pixel samples spread over a 4 x 4 grid, and for the sequences over
+        // sixteenths of the first dimension too. Halton's first 16 points are
+        // only close to one per grid cell.
+        if kind == SamplerKind::Stratified || kind == SamplerKind::Sobol {
+            let mut cells = vec![0; 16];
+            for &(u, v) in pairs.iter() {
+                cells[(v * 4.0) as usize * 4 + (u * 4.0) as usize] += 1;
+            }
+            assert!(cells.iter().all(|&count| count == 1));
+        }
+
+        if kind == SamplerKind::Halton || kind == SamplerKind::Sobol {
+            let mut cells = vec![0; 16];
+            for &(u, _) in pairs.iter() {
+                cells[(u * 16.0) as usize] += 1;
+            }
+            assert!(cells.iter().all(|&count| count == 1));
+        }
+
+        // Only stratification keeps going past the sequences' tables
+        if kind == SamplerKind::Stratified {
+            let mut cells = vec![0; 16];
+            for &u in singles.iter() {
+                cells[(u * 16.0) as usize] += 1;
+            }
+            assert!(cells.iter().all(|&count| count == 1));
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use raytracer::compositor::Surface;
+use raytracer::compositor::accumulator::luminance;
+use raytracer::sampler::{cdf, concentric_disk, pick};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Shape of a lens opening, which out of focus highlights (bokeh) take on.
+/// Shapes fit within the unit circle and are scaled by the aperture radius.
+#[derive(Clone)]
+pub enum Aperture {
+    Circle,
+    /// Straight `blades` closed down to a regular polygon with its corners on
+    /// the circle, one of them `rotation` degrees clockwise from the top
+    Polygon { blades: u32, rotation: f64 },
+    /// Lets light through as brightly as an image, stretched over the square
+    /// around the circle
+    Image(Arc<ApertureImage>)
+}
+
+/// Brightness of an aperture image, tabulated for picking points in proportion
+/// to it
+pub struct ApertureImage {
+    width: usize,
+    height: usize,
+    // Running sums over the rows, and over the pixels of each row
+    row_cdf: Vec<f64>,
+    column_cdfs: Vec<Vec<f64>>
+}
+
+impl ApertureImage {
+    /// `image` holds linear values, as in an ImageTexture
+    pub fn new(image: &Surface<f64>) -> ApertureImage {
+        let (width, height) = (image.width, image.height);
+        let mut row_sums = Vec::with_capacity(height);
+        let mut column_cdfs = Vec::with_capacity(height);
+
+        for y in 0..height {
+            let row: Vec<f64> = (0..width).map(|x| {
+                let color = image[(x, y)];
+                luminance(color.r, color.g, color.b).max(0.0)
+            }).collect();
+            row_sums.push(row.iter().fold(0.0, |total, value| total + value));
+            column_cdfs.push(cdf(&row));
+        }
+
+        ApertureImage {
+            width: width,
+            height: height,
+            row_cdf: cdf(&row_sums),
+            column_cdfs: column_cdfs
+        }
+    }
+}
+
+impl Aperture {
+    /// Point on the aperture for (u1, u2) in [0, 1)², within the unit circle
+    /// with y up. Areas are kept proportional so stratified samples stay spread.
+    pub fn sample(&self, u1: f64, u2: f64) -> (f64, f64) {
+        match *self {
+            Aperture::Circle => concentric_disk(u1, u2),
+            Aperture::Polygon { blades, rotation } => {
+                // A triangle between the center and two neighbouring corners,
+                // all of them the same size
+                let blades = blades.max(3);
+                let scaled = u1 * blades as f64;
+                let blade = (scaled as u32).min(blades - 1);
+                let corner = |index: u32| {
+                    let angle = rotation.to_radians() + 2.0 * PI * index as f64 / blades as f64;
+                    (angle.sin(), angle.cos())
+                };
+                let (x1, y1) = corner(blade);
+                let (x2, y2) = corner(blade + 1);
+
+                let (mut a, mut b) = (scaled - blade as f64, u2);
+                if a + b > 1.0 {
+                    a = 1.0 - a;
+                    b = 1.0 - b;
+                }
+
+                (a * x1 + b * x2, a * y1 + b * y2)
+            },
+            Aperture::Image(ref image) => {
+                let (row, v) = pick(&image.row_cdf, u1);
+                let (column, u) = pick(&image.column_cdfs[row], u2);
+
+                let x = (column as f64 + u) / image.width as f64;
+                let y = (row as f64 + v) / image.height as f64;
+                (2.0 * x - 1.0, 1.0 - 2.0 * y)
+            }
+        }
+    }
+}
+
+#[test]
+fn it_samples_within_the_aperture() {
+    use raytracer::compositor::ColorRGBA;
+
+    // Hexagon with a flat top, and an image lit in its top right quarter only
+    let hexagon = Aperture::Polygon { blades: 6, rotation: 30.0 };
+    let mut image = Surface::new(4, 4, ColorRGBA::new_rgb(0.0, 0.0, 0.0));
+    for y in 0..2 {
+        for x in 2..4 {
+            image[(x, y)] = ColorRGBA::new_rgb(1.0, 1.0, 1.0);
+        }
+    }
+    let quarter = Aperture::Image(Arc::new(ApertureImage::new(&image)));
+
+    let apothem = (PI / 6.0).cos();
+    let (mut mean_x, mut mean_y) = (0.0, 0.0);
+    for i in 0..32 {
+        for j in 0..32 {
+            let (u1, u2) = ((i as f64 + 0.5) / 32.0, (j as f64 + 0.5) / 32.0);
+
+            let (x, y) = hexagon.sample(u1, u2);
+            assert!(y.abs() <= apothem + 1e-9 && x * x + y * y <= 1.0 + 1e-9);
+            mean_x += x / 1024.0;
+            mean_y += y / 1024.0;
+
+            let (x, y) = quarter.sample(u1, u2);
+            assert!(x >= 0.0 && y >= 0.0);
+
+            let (x, y) = Aperture::Circle.sample(u1, u2);
+            assert!(x * x + y * y <= 1.0 + 1e-9);
+        }
+    }
+
+    // Spread evenly over the hexagon, whose centroid is its center
+    assert!(mean_x.abs() < 0.01 && mean_y.abs() < 0.01, "centroid ({}, {})", mean_x, mean_y);
+}
@@ -1,7 +1,12 @@
 use raytracer::Ray;
 use raytracer::animator::CameraKeyframe;
+use raytracer::sampler::Sampler;
+use scene::Aperture;
 use vec3::Vec3;
 
+/// A thin lens camera. With an `aperture_radius` of 0 (the default) it is a
+/// pinhole and everything is in focus; otherwise only what is `focus_distance`
+/// away along the view direction is sharp.
 #[derive(Clone)]
 pub struct Camera {
     pub position: Vec3,
@@ -11,6 +16,10 @@ pub struct Camera {
     pub image_width: u32,
     pub image_height: u32,
 
+    pub aperture_radius: f64,
+    pub focus_distance: f64, // Defaults to the distance to `look_at`
+    pub aperture: Aperture,
+
     pub eye: Vec3,
     pub right: Vec3,
     pub half_width: f64,
@@ -33,6 +42,9 @@ impl Camera {
             fov_deg: fov_deg,
             image_width: image_width,
             image_height: image_height,
+            aperture_radius: 0.0,
+            focus_distance: (look_at - position).len(),
+            aperture: Aperture::Circle,
             eye: Vec3::zero(),
             right: Vec3::zero(),
             half_width: 0.0,
@@ -58,12 +70,30 @@ impl Camera {
         camera
     }
 
-    pub fn get_ray(&self, x: f64, y: f64) -> Ray {
-        Ray::new(
-            self.position,
-            (self.eye + self.right.scale(x * self.pixel_width - self.half_width) +
-            self.up.scale(y * self.pixel_height - self.half_height)).unit()
-        )
+    /// Ray through image coordinates (x, y). Rays from a lens start at a point
+    /// on the aperture picked with `sampler`.
+    pub fn get_ray(&self, x: f64, y: f64, sampler: &mut Sampler) -> Ray {
+        let direction = (self.eye + self.right.scale(x * self.pixel_width - self.half_width) +
+                         self.up.scale(y * self.pixel_height - self.half_height)).unit();
+        if self.aperture_radius <= 0.0 {
+            return Ray::new(self.position, direction);
+        }
+
+        // Every ray through the lens for this pixel meets on the plane of focus
+        let focus = self.position + direction.scale(self.focus_distance / direction.dot(&self.eye));
+        let (u1, u2) = sampler.next_2d();
+        let (x, y) = self.aperture.sample(u1, u2);
+        let lens_up = self.right.cross(&self.eye).unit();
+        let origin = self.position + self.right.unit().scale(x * self.aperture_radius) +
+                     lens_up.scale(y * self.aperture_radius);
+
+        Ray::new(origin, (focus - origin).unit())
+    }
+
+    /// Aperture radius of a lens with `focal_length` (in scene units) stopped
+    /// down to `f_stop`
+    pub fn aperture_radius_for(focal_length: f64, f_stop: f64) -> f64 {
+        focal_length / (2.0 * f_stop)
     }
 
     /// Add additional keyframes to the camera. The current state of the camera
@@ -74,7 +104,9 @@ impl Camera {
             time: 0.0,
             position: self.position,
             look_at: self.look_at,
-            up: self.up
+            up: self.up,
+            aperture_radius: Some(self.aperture_radius),
+            focus_distance: Some(self.focus_distance)
         };
 
         let mut keyframes = vec![t0_keyframe];
@@ -102,3 +134,29 @@ impl Camera {
         self.pixel_height = camera_height / (self.image_height - 1) as f64;
     }
 }
+
+#[test]
+fn it_focuses_through_the_lens() {
+    use raytracer::sampler::SamplerKind;
+
+    let mut camera = Camera::new(Vec3 { x: 0.0, y: 0.0, z: 10.0 }, Vec3::zero(), Vec3 { x: 0.0, y: 1.0, z: 0.0 },
+                                 45.0, 32, 32);
+    let mut sampler = SamplerKind::Independent.build(1, 0);
+    let pinhole = camera.get_ray(3.0, 20.0, &mut *sampler);
+    assert_eq!(pinhole.origin, camera.position);
+
+    // Rays through the lens spread over the aperture, then cross where the
+    // pinhole ray meets the plane of focus
+    camera.aperture_radius = Camera::aperture_radius_for(1.0, 2.0);
+    camera.focus_distance = 5.0;
+    camera.aperture = Aperture::Polygon { blades: 5, rotation: 0.0 };
+    let focus = pinhole.origin + pinhole.direction.scale(5.0 / pinhole.direction.dot(&camera.eye));
+    for _ in 0..16 {
+        let ray = camera.get_ray(3.0, 20.0, &mut *sampler);
+        let offset = ray.origin - camera.position;
+        assert!(offset.len() <= 0.25 + 1e-9 && offset.dot(&camera.eye).abs() < 1e-9);
+
+        let t = (focus - ray.origin).dot(&camera.eye) / ray.direction.dot(&camera.eye);
+        assert!((ray.origin + ray.direction.scale(t) - focus).len() < 1e-9);
+    }
+}
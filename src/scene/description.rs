@@ -0,0 +1,1011 @@
+use geometry::{BBox, Mesh, Prim};
+use geometry::prims::{Plane, Sphere, TriangleOptions};
+use light::Light;
+use light::lights::{DirectionalLight, DiskLight, EnvironmentLight, PointLight, PrimLight, QuadLight, SphereLight, SpotLight};
+use mat4::{Mat4, Transform};
+use material::{Conductor, Environment, Material, Texture};
+use material::materials::{CookTorranceMaterial, DielectricMaterial, FlatMaterial, GgxMaterial, PhongMaterial,
+                          PrincipledMaterial, SubsurfaceMaterial};
+use material::textures::{CheckerTexture, CubeMap, EquirectMap, ImageTexture, UVTexture};
+use medium::Medium;
+use medium::media::{GridMedium, HomogeneousMedium};
+use raytracer::{Accelerator, AcceleratorKind};
+use raytracer::animator::CameraKeyframe;
+use raytracer::compositor::ColorRGBA;
+use rustc_serialize::json;
+use scene::{Aperture, ApertureImage, Camera, Scene};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::sync::Arc;
+use vec3::Vec3;
+
+/// A declarative scene, decoded from a JSON scene file.
+///
+/// Every object with a `kind` picks its implementation by name and only reads the
+/// fields that implementation needs. Fields left out fall back to the `Default` of
+/// the material in question. Materials, textures and media are named so prims,
+/// meshes and dielectrics can share them. `medium` names the one the camera is in.
+#[derive(RustcDecodable)]
+pub struct SceneDescription {
+    pub camera: CameraDescription,
+    pub lights: Vec<LightDescription>,
+    pub textures: Option<HashMap<String, TextureDescription>>,
+    pub media: Option<HashMap<String, MediumDescription>>,
+    pub materials: HashMap<String, MaterialDescription>,
+    pub prims: Option<Vec<PrimDescription>>,
+    pub meshes: Option<Vec<MeshDescription>>,
+    pub background: Option<(f64, f64, f64)>,
+    pub skybox: Option<SkyboxDescription>,
+    pub medium: Option<String>
+}
+
+/// A pinhole camera unless it has an `aperture_radius`, or an `f_stop` and
+/// the `focal_length` in scene units. Things `focus_distance` away are in
+/// focus, by default those at `look_at`. Keyframes can change the aperture and
+/// focus distance too, focusing on their own `look_at` by default.
+#[derive(RustcDecodable)]
+pub struct CameraDescription {
+    pub position: (f64, f64, f64),
+    pub look_at: (f64, f64, f64),
+    pub up: (f64, f64, f64),
+    pub aperture_radius: Option<f64>,
+    pub f_stop: Option<f64>,
+    pub focal_length: Option<f64>,
+    pub focus_distance: Option<f64>,
+    pub aperture: Option<ApertureDescription>,
+    pub keyframes: Option<Vec<KeyframeDescription>>
+}
+
+#[derive(RustcDecodable)]
+pub struct KeyframeDescription {
+    pub time: f64,
+    pub position: (f64, f64, f64),
+    pub look_at: (f64, f64, f64),
+    pub up: (f64, f64, f64),
+    pub aperture_radius: Option<f64>,
+    pub f_stop: Option<f64>,
+    pub focus_distance: Option<f64>
+}
+
+/// kind: "circle", "polygon" (`blades`, optionally `rotation` in degrees) or
+/// "image" (`filename`, white where light gets through)
+#[derive(RustcDecodable)]
+pub struct ApertureDescription {
+    pub kind: String,
+    pub blades: Option<u32>,
+    pub rotation: Option<f64>,
+    pub filename: Option<String>
+}
+
+/// kind: "point", "spot" (needs `direction` and `cone_angle`, optionally
+/// `falloff_angle`), "directional" (`direction` only, optionally
+/// `angular_diameter`), "environment" (lights the scene with its skybox, no
+/// other fields), "sphere" (needs `radius`), "quad" (`position` is a
+/// corner, needs `edge1` and `edge2`) or "disk" (needs `normal` and `radius`).
+///
+/// `color` is the intensity of point and spot lights, the irradiance of
+/// directional lights and the radiance of the others. Point and spot lights
+/// can give their total `power` instead. Angles are in degrees.
+#[derive(RustcDecodable)]
+pub struct LightDescription {
+    pub kind: String,
+    pub position: Option<(f64, f64, f64)>,
+    pub color: Option<(f64, f64, f64)>,
+    pub power: Option<(f64, f64, f64)>,
+    pub radius: Option<f64>,
+    pub normal: Option<(f64, f64, f64)>,
+    pub direction: Option<(f64, f64, f64)>,
+    pub cone_angle: Option<f64>,
+    pub falloff_angle: Option<f64>,
+    pub angular_diameter: Option<f64>,
+    pub edge1: Option<(f64, f64, f64)>,
+    pub edge2: Option<(f64, f64, f64)>
+}
+
+/// kind: "checker" (`color1`, `color2`, `scale`), "uv" or "image" (`filename`)
+#[derive(RustcDecodable)]
+pub struct TextureDescription {
+    pub kind: String,
+    pub color1: Option<(f64, f64, f64)>,
+    pub color2: Option<(f64, f64, f64)>,
+    pub scale: Option<f64>,
+    pub filename: Option<String>
+}
+
+/// kind: "homogeneous" (`absorption`, `scattering`, optionally `bounds`) or
+/// "grid" (a voxel file in `filename` stretched over `bounds`, whose
+/// coefficients are those at a density of 1). `g` is the Henyey-Greenstein
+/// asymmetry, 0 by default. `bounds` are the min and max corners of a box.
+#[derive(RustcDecodable)]
+pub struct MediumDescription {
+    pub kind: String,
+    pub absorption: Option<(f64, f64, f64)>,
+    pub scattering: Option<(f64, f64, f64)>,
+    pub g: Option<f64>,
+    pub bounds: Option<((f64, f64, f64), (f64, f64, f64))>,
+    pub filename: Option<String>
+}
+
+/// kind: "cook_torrance", "phong", "ggx" (`base_color`, `metalness`,
+/// `roughness`, `ior`), "principled" (those and `specular_level`, `clearcoat`,
+/// `clearcoat_roughness`, `sheen`, `sheen_tint`, `specular_transmission` and
+/// a `<name>_texture` for each of them), "dielectric" (`ior`, `cauchy_b`,
+/// `color`, `absorption` and the `medium` it is filled with), "subsurface"
+/// (`ior`, `scattering` and `absorption` colours, `mean_free_path`, `g`) or
+/// "flat" (`color` only).
+/// `emission` is the radiance a surface other than flat gives off. Cook-Torrance,
+/// Phong and GGX surfaces reflect like a `metal` ("gold", "copper", "aluminium"
+/// or "silver") or a conductor of complex index of refraction `eta` + i`k`.
+#[derive(RustcDecodable)]
+pub struct MaterialDescription {
+    pub kind: String,
+    pub k_a: Option<f64>,
+    pub k_d: Option<f64>,
+    pub k_s: Option<f64>,
+    pub k_sg: Option<f64>,
+    pub k_tg: Option<f64>,
+    pub ambient: Option<(f64, f64, f64)>,
+    pub diffuse: Option<(f64, f64, f64)>,
+    pub transmission: Option<(f64, f64, f64)>,
+    pub specular: Option<(f64, f64, f64)>,
+    pub roughness: Option<f64>,
+    pub shininess: Option<f64>,
+    pub glossiness: Option<f64>,
+    pub gauss_constant: Option<f64>,
+    pub ior: Option<f64>,
+    pub metal: Option<String>,
+    pub eta: Option<(f64, f64, f64)>,
+    pub k: Option<(f64, f64, f64)>,
+    pub base_color: Option<(f64, f64, f64)>,
+    pub metalness: Option<f64>,
+    pub specular_level: Option<f64>,
+    pub clearcoat: Option<f64>,
+    pub clearcoat_roughness: Option<f64>,
+    pub sheen: Option<f64>,
+    pub sheen_tint: Option<f64>,
+    pub specular_transmission: Option<f64>,
+    pub cauchy_b: Option<f64>,
+    pub absorption: Option<(f64, f64, f64)>,
+    pub scattering: Option<(f64, f64, f64)>,
+    pub mean_free_path: Option<f64>,
+    pub g: Option<f64>,
+    pub color: Option<(f64, f64, f64)>,
+    pub medium: Option<String>,
+    pub emission: Option<(f64, f64, f64)>,
+    pub diffuse_texture: Option<String>,
+    pub base_color_texture: Option<String>,
+    pub metalness_texture: Option<String>,
+    pub roughness_texture: Option<String>,
+    pub specular_level_texture: Option<String>,
+    pub clearcoat_texture: Option<String>,
+    pub sheen_texture: Option<String>,
+    pub specular_transmission_texture: Option<String>,
+    pub clearcoat_roughness_texture: Option<String>,
+    pub sheen_tint_texture: Option<String>,
+    pub ior_texture: Option<String>,
+    pub emission_texture: Option<String>
+}
+
+/// kind: "sphere" (`center`, `radius`), "plane" (`normal`, `d`) or
+/// "triangle" (`vertices`, optionally `normals` and `texinfo`)
+#[derive(RustcDecodable)]
+pub struct PrimDescription {
+    pub kind: String,
+    pub material: String,
+    pub center: Option<(f64, f64, f64)>,
+    pub radius: Option<f64>,
+    pub normal: Option<(f64, f64, f64)>,
+    pub d: Option<f64>,
+    pub vertices: Option<Vec<(f64, f64, f64)>>,
+    pub normals: Option<Vec<(f64, f64, f64)>>,
+    pub texinfo: Option<Vec<(f64, f64)>>
+}
+
+/// An OBJ model, every triangle of which gets `material`. Transforms are
+/// applied in order.
+#[derive(RustcDecodable)]
+pub struct MeshDescription {
+    pub filename: String,
+    pub material: String,
+    pub flip_normals: Option<bool>,
+    pub transforms: Option<Vec<TransformDescription>>
+}
+
+/// kind: "translate", "scale" (`vector`), "rotate_x", "rotate_y", "rotate_z"
+/// (`angle` in degrees) or "rotate_axis" (`angle`, `vector`)
+#[derive(RustcDecodable)]
+pub struct TransformDescription {
+    pub kind: String,
+    pub vector: Option<(f64, f64, f64)>,
+    pub angle: Option<f64>
+}
+
+/// Either a latitude-longitude image in `filename` (.hdr keeps its full
+/// range), or the six faces of a cube map
+#[derive(RustcDecodable)]
+pub struct SkyboxDescription {
+    pub filename: Option<String>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub down: Option<String>,
+    pub up: Option<String>,
+    pub front: Option<String>,
+    pub back: Option<String>
+}
+
+/// Materials are kept concrete until they are attached to a prim so they can be
+/// cloned for every prim (and every triangle of a mesh) that uses them.
+#[derive(Clone)]
+enum SceneMaterial {
+    CookTorrance(CookTorranceMaterial),
+    Phong(PhongMaterial),
+    Ggx(GgxMaterial),
+    Principled(PrincipledMaterial),
+    Dielectric(DielectricMaterial),
+    Subsurface(SubsurfaceMaterial),
+    Flat(FlatMaterial)
+}
+
+impl SceneMaterial {
+    fn boxed(&self) -> Box<Material+Send+Sync> {
+        match *self {
+            SceneMaterial::CookTorrance(ref m) => Box::new(m.clone()),
+            SceneMaterial::Phong(ref m) => Box::new(m.clone()),
+            SceneMaterial::Ggx(ref m) => Box::new(m.clone()),
+            SceneMaterial::Principled(ref m) => Box::new(m.clone()),
+            SceneMaterial::Dielectric(ref m) => Box::new(m.clone()),
+            SceneMaterial::Subsurface(ref m) => Box::new(m.clone()),
+            SceneMaterial::Flat(ref m) => Box::new(m.clone())
+        }
+    }
+}
+
+fn to_vec3(v: (f64, f64, f64)) -> Vec3 {
+    Vec3 { x: v.0, y: v.1, z: v.2 }
+}
+
+fn to_color(v: (f64, f64, f64)) -> ColorRGBA<f64> {
+    ColorRGBA::new_rgb(v.0, v.1, v.2)
+}
+
+fn require<T>(value: Option<T>, field: &str, kind: &str) -> Result<T, String> {
+    match value {
+        Some(value) => Ok(value),
+        None => Err(format!("``{}'' requires field ``{}''", kind, field))
+    }
+}
+
+/// Reads and decodes a JSON scene file. Use `SceneDescription::build` to turn it
+/// into something renderable.
+pub fn from_file(filename: &str) -> Result<SceneDescription, String> {
+    let mut file_handle = match File::open(filename) {
+        Ok(f) => f,
+        Err(err) => return Err(format!("{}: {}", filename, err))
+    };
+
+    let mut json_data = String::new();
+    if let Err(err) = file_handle.read_to_string(&mut json_data) {
+        return Err(format!("{}: {}", filename, err));
+    }
+
+    from_str(&json_data).map_err(|err| format!("{}: {}", filename, err))
+}
+
+pub fn from_str(json_data: &str) -> Result<SceneDescription, String> {
+    match json::decode(json_data) {
+        Ok(description) => Ok(description),
+        Err(json::DecoderError::MissingFieldError(field_name)) => {
+            Err(format!("parse failure, missing field ``{}''", field_name))
+        },
+        Err(err) => Err(format!("parse failure: {:?}", err))
+    }
+}
+
+impl SceneDescription {
+    pub fn build(&self, image_width: u32, image_height: u32, fov: f64, animating: bool,
+                 accelerator_kind: AcceleratorKind) -> Result<(Camera, Scene), String> {
+
+        let camera = try!(self.build_camera(image_width, image_height, fov, animating));
+
+        let skybox = match self.skybox {
+            Some(ref skybox) => Some(try!(SceneDescription::build_skybox(skybox))),
+            None => None
+        };
+
+        let mut lights: Vec<Box<Light+Send+Sync>> = Vec::new();
+        for light in self.lights.iter() {
+            lights.push(try!(SceneDescription::build_light(light, skybox.as_ref())));
+        }
+
+        let mut textures: HashMap<String, Arc<Texture+Send+Sync>> = HashMap::new();
+        if let Some(ref texture_descriptions) = self.textures {
+            for (name, texture) in texture_descriptions.iter() {
+                let built = try!(SceneDescription::build_texture(texture)
+                    .map_err(|err| format!("texture ``{}'': {}", name, err)));
+                textures.insert(name.clone(), built);
+            }
+        }
+
+        let mut media: HashMap<String, Arc<Medium+Send+Sync>> = HashMap::new();
+        if let Some(ref medium_descriptions) = self.media {
+            for (name, medium) in medium_descriptions.iter() {
+                let built = try!(SceneDescription::build_medium(medium)
+                    .map_err(|err| format!("medium ``{}'': {}", name, err)));
+                media.insert(name.clone(), built);
+            }
+        }
+
+        let mut materials: HashMap<String, SceneMaterial> = HashMap::new();
+        for (name, material) in self.materials.iter() {
+            let built = try!(SceneDescription::build_material(material, &textures, &media)
+                .map_err(|err| format!("material ``{}'': {}", name, err)));
+            materials.insert(name.clone(), built);
+        }
+
+        let mut prims: Vec<Box<Prim+Send+Sync>> = Vec::new();
+        if let Some(ref prim_descriptions) = self.prims {
+            for prim in prim_descriptions.iter() {
+                let material = match materials.get(&prim.material) {
+                    Some(material) => material.boxed(),
+                    None => return Err(format!("unknown material ``{}''", prim.material))
+                };
+                prims.push(try!(SceneDescription::build_prim(prim, material)));
+            }
+        }
+
+        if let Some(ref mesh_descriptions) = self.meshes {
+            for mesh in mesh_descriptions.iter() {
+                let built = try!(SceneDescription::build_mesh(mesh, &materials));
+                for triangle in built.triangles.into_iter() { prims.push(triangle); }
+            }
+        }
+
+        for prim_light in PrimLight::from_prims(&prims).into_iter() {
+            lights.push(Box::new(prim_light));
+        }
+
+        println!("Generating accelerator...");
+        let accelerator = Accelerator::new(accelerator_kind, prims);
+        println!("Accelerator generated...");
+
+        let scene = Scene {
+            lights: lights,
+            accelerator: accelerator,
+            background: self.background.map(to_vec3).unwrap_or(Vec3::one()),
+            skybox: skybox,
+            medium: try!(SceneDescription::find_medium(&self.medium, &media))
+        };
+
+        Ok((camera, scene))
+    }
+
+    fn build_camera(&self, image_width: u32, image_height: u32, fov: f64,
+                    animating: bool) -> Result<Camera, String> {
+        let description = &self.camera;
+        let mut camera = Camera::new(to_vec3(description.position), to_vec3(description.look_at),
+                                     to_vec3(description.up), fov, image_width, image_height);
+
+        // An f-stop is relative to the focal length
+        let aperture_radius = |aperture_radius: Option<f64>, f_stop: Option<f64>| -> Result<Option<f64>, String> {
+            match (aperture_radius, f_stop) {
+                (Some(radius), _) if !(radius >= 0.0) => {
+                    Err(format!("``aperture_radius'' must not be negative, got {}", radius))
+                },
+                (Some(radius), _) => Ok(Some(radius)),
+                (None, Some(f_stop)) if !(f_stop > 0.0) => Err(format!("``f_stop'' must be positive, got {}", f_stop)),
+                (None, Some(f_stop)) => {
+                    let focal_length = try!(require(description.focal_length, "focal_length", "f_stop"));
+                    if !(focal_length > 0.0) {
+                        return Err(format!("``focal_length'' must be positive, got {}", focal_length));
+                    }
+                    Ok(Some(Camera::aperture_radius_for(focal_length, f_stop)))
+                },
+                (None, None) => Ok(None)
+            }
+        };
+        let focus_distance = |focus_distance: Option<f64>| match focus_distance {
+            Some(distance) if !(distance > 0.0) => {
+                Err(format!("``focus_distance'' must be positive, got {}", distance))
+            },
+            focus_distance => Ok(focus_distance)
+        };
+
+        if let Some(radius) = try!(aperture_radius(description.aperture_radius, description.f_stop)) {
+            camera.aperture_radius = radius;
+        }
+        if let Some(distance) = try!(focus_distance(description.focus_distance)) {
+            camera.focus_distance = distance;
+        }
+        if let Some(ref aperture) = description.aperture {
+            camera.aperture = try!(SceneDescription::build_aperture(aperture));
+        }
+
+        match description.keyframes {
+            Some(ref keyframes) if animating => {
+                let mut built = Vec::with_capacity(keyframes.len());
+                for k in keyframes.iter() {
+                    built.push(CameraKeyframe {
+                        time: k.time,
+                        position: to_vec3(k.position),
+                        look_at: to_vec3(k.look_at),
+                        up: to_vec3(k.up),
+                        aperture_radius: try!(aperture_radius(k.aperture_radius, k.f_stop)),
+                        focus_distance: try!(focus_distance(k.focus_distance))
+                    });
+                }
+
+                camera.insert_keyframes(built);
+            },
+            _ => {}
+        }
+
+        Ok(camera)
+    }
+
+    fn build_aperture(aperture: &ApertureDescription) -> Result<Aperture, String> {
+        let kind: &str = aperture.kind.as_ref();
+        match kind {
+            "circle" => Ok(Aperture::Circle),
+            "polygon" => {
+                let blades = try!(require(aperture.blades, "blades", kind));
+                if blades < 3 {
+                    return Err(format!("``polygon'' needs at least 3 blades, got {}", blades));
+                }
+                Ok(Aperture::Polygon { blades: blades, rotation: aperture.rotation.unwrap_or(0.0) })
+            },
+            "image" => {
+                let filename = try!(require(aperture.filename.as_ref(), "filename", kind));
+                let texture = try!(ImageTexture::load(filename));
+                Ok(Aperture::Image(Arc::new(ApertureImage::new(&texture.image))))
+            },
+            kind => Err(format!("unknown aperture type ``{}''", kind))
+        }
+    }
+
+    fn build_skybox(skybox: &SkyboxDescription) -> Result<Arc<Environment+Send+Sync>, String> {
+        if let Some(ref filename) = skybox.filename {
+            return Ok(Arc::new(try!(EquirectMap::load(filename))));
+        }
+
+        let face = |face: &Option<String>, name: &str| match *face {
+            Some(ref filename) => Ok(filename.clone()),
+            None => Err(format!("skybox requires ``filename'' or field ``{}''", name))
+        };
+
+        Ok(Arc::new(try!(CubeMap::load(&try!(face(&skybox.left, "left")), &try!(face(&skybox.right, "right")),
+                                       &try!(face(&skybox.down, "down")), &try!(face(&skybox.up, "up")),
+                                       &try!(face(&skybox.front, "front")), &try!(face(&skybox.back, "back"))))))
+    }
+
+    fn build_light(light: &LightDescription, skybox: Option<&Arc<Environment+Send+Sync>>)
+                   -> Result<Box<Light+Send+Sync>, String> {
+        let kind: &str = light.kind.as_ref();
+        let color = light.color.map(to_vec3);
+
+        if kind == "environment" {
+            return match skybox {
+                Some(skybox) => Ok(Box::new(EnvironmentLight::new(skybox.clone(), 256, 128))),
+                None => Err("``environment'' light requires a skybox".to_string())
+            };
+        }
+
+        if kind == "directional" {
+            let direction = try!(require(light.direction, "direction", kind));
+            let angular_diameter = light.angular_diameter.unwrap_or(0.0);
+            if !(angular_diameter >= 0.0 && angular_diameter < 180.0) {
+                return Err(format!("``angular_diameter'' must be at least 0 and below 180 degrees, got {}",
+                                   angular_diameter));
+            }
+            return Ok(Box::new(DirectionalLight {
+                direction: to_vec3(direction).unit(),
+                irradiance: try!(require(color, "color", kind)),
+                angular_diameter: angular_diameter.to_radians()
+            }));
+        }
+
+        let position = to_vec3(try!(require(light.position, "position", kind)));
+
+        match kind {
+            "point" => match (color, light.power.map(to_vec3)) {
+                (Some(color), _) => Ok(Box::new(PointLight { position: position, intensity: color })),
+                (None, Some(power)) => Ok(Box::new(PointLight::from_power(position, power))),
+                (None, None) => Err(format!("``{}'' requires field ``color'' or ``power''", kind))
+            },
+            "spot" => {
+                let direction = to_vec3(try!(require(light.direction, "direction", kind))).unit();
+                // Both are measured off the axis, the light fading out between them
+                let cone_angle = try!(require(light.cone_angle, "cone_angle", kind));
+                let falloff_angle = light.falloff_angle.unwrap_or(cone_angle);
+                if !(cone_angle > 0.0 && cone_angle <= 180.0) {
+                    return Err(format!("``cone_angle'' must be between 0 and 180 degrees, got {}", cone_angle));
+                }
+                if !(falloff_angle >= 0.0 && falloff_angle <= cone_angle) {
+                    return Err(format!("``falloff_angle'' must be between 0 and ``cone_angle'' ({}), got {}",
+                                       cone_angle, falloff_angle));
+                }
+                let (cone_angle, falloff_angle) = (cone_angle.to_radians(), falloff_angle.to_radians());
+
+                match (color, light.power.map(to_vec3)) {
+                    (Some(color), _) => Ok(Box::new(SpotLight {
+                        position: position,
+                        direction: direction,
+                        intensity: color,
+                        cone_angle: cone_angle,
+                        falloff_angle: falloff_angle
+                    })),
+                    (None, Some(power)) => Ok(Box::new(SpotLight::from_power(position, direction, power,
+                                                                             cone_angle, falloff_angle))),
+                    (None, None) => Err(format!("``{}'' requires field ``color'' or ``power''", kind))
+                }
+            },
+            "sphere" => {
+                let radius = try!(require(light.radius, "radius", kind));
+                Ok(Box::new(SphereLight { position: position, color: try!(require(color, "color", kind)), radius: radius }))
+            },
+            "quad" => {
+                let edge1 = try!(require(light.edge1, "edge1", kind));
+                let edge2 = try!(require(light.edge2, "edge2", kind));
+                Ok(Box::new(QuadLight {
+                    corner: position,
+                    edge1: to_vec3(edge1),
+                    edge2: to_vec3(edge2),
+                    color: try!(require(color, "color", kind))
+                }))
+            },
+            "disk" => {
+                let normal = try!(require(light.normal, "normal", kind));
+                let radius = try!(require(light.radius, "radius", kind));
+                Ok(Box::new(DiskLight {
+                    position: position,
+                    normal: to_vec3(normal).unit(),
+                    color: try!(require(color, "color", kind)),
+                    radius: radius
+                }))
+            },
+            kind => Err(format!("unknown light type ``{}''", kind))
+        }
+    }
+
+    fn build_texture(texture: &TextureDescription) -> Result<Arc<Texture+Send+Sync>, String> {
+        match texture.kind.as_ref() {
+            "checker" => {
+                let default = CheckerTexture::black_and_white(1.0);
+                Ok(Arc::new(CheckerTexture {
+                    color1: texture.color1.map(to_color).unwrap_or(default.color1),
+                    color2: texture.color2.map(to_color).unwrap_or(default.color2),
+                    scale: texture.scale.unwrap_or(default.scale)
+                }))
+            },
+            "uv" => Ok(Arc::new(UVTexture)),
+            "image" => {
+                let filename = try!(require(texture.filename.as_ref(), "filename", "image"));
+                Ok(Arc::new(try!(ImageTexture::load(filename))))
+            },
+            kind => Err(format!("unknown texture type ``{}''", kind))
+        }
+    }
+
+    fn build_medium(medium: &MediumDescription) -> Result<Arc<Medium+Send+Sync>, String> {
+        let kind: &str = medium.kind.as_ref();
+        let bounds = medium.bounds.map(|(min, max)| BBox { min: to_vec3(min), max: to_vec3(max) });
+        let absorption = medium.absorption.map(to_vec3).unwrap_or(Vec3::zero());
+        let scattering = medium.scattering.map(to_vec3).unwrap_or(Vec3::zero());
+        let g = medium.g.unwrap_or(0.0);
+        if g <= -1.0 || g >= 1.0 {
+            return Err(format!("``g'' must be between -1 and 1, got {}", g));
+        }
+
+        match kind {
+            "homogeneous" => {
+                Ok(Arc::new(HomogeneousMedium {
+                    absorption: absorption,
+                    scattering: scattering,
+                    g: g,
+                    bounds: bounds
+                }))
+            },
+            "grid" => {
+                let filename = try!(require(medium.filename.as_ref(), "filename", kind));
+                let bounds = try!(require(bounds, "bounds", kind));
+                let (size, density) = try!(::util::import::from_voxels(filename));
+                Ok(Arc::new(GridMedium::new(absorption, scattering, g, bounds, size, density)))
+            },
+            kind => Err(format!("unknown medium type ``{}''", kind))
+        }
+    }
+
+    fn find_medium(name: &Option<String>, media: &HashMap<String, Arc<Medium+Send+Sync>>)
+                   -> Result<Option<Arc<Medium+Send+Sync>>, String> {
+        match *name {
+            Some(ref name) => match media.get(name) {
+                Some(medium) => Ok(Some(medium.clone())),
+                None => Err(format!("unknown medium ``{}''", name))
+            },
+            None => Ok(None)
+        }
+    }
+
+    fn build_material(material: &MaterialDescription,
+                      textures: &HashMap<String, Arc<Texture+Send+Sync>>,
+                      media: &HashMap<String, Arc<Medium+Send+Sync>>)
+                      -> Result<SceneMaterial, String> {
+
+        let texture = |name: &Option<String>| match *name {
+            Some(ref name) => match textures.get(name) {
+                Some(texture) => Ok(Some(texture.clone())),
+                None => Err(format!("unknown texture ``{}''", name))
+            },
+            None => Ok(None)
+        };
+        let diffuse_texture = try!(texture(&material.diffuse_texture));
+        let conductor = match (material.metal.as_ref(), material.eta, material.k) {
+            (Some(name), _, _) => match Conductor::named(name) {
+                Some(metal) => Some(metal),
+                None => return Err(format!("unknown metal ``{}''", name))
+            },
+            (None, Some(eta), k) => Some(Conductor { eta: to_vec3(eta), k: to_vec3(try!(require(k, "k", "conductor"))) }),
+            (None, None, _) => None
+        };
+
+        match material.kind.as_ref() {
+            "cook_torrance" => {
+                let default: CookTorranceMaterial = Default::default();
+                Ok(SceneMaterial::CookTorrance(CookTorranceMaterial {
+                    k_a: material.k_a.unwrap_or(default.k_a),
+                    k_d: material.k_d.unwrap_or(default.k_d),
+                    k_s: material.k_s.unwrap_or(default.k_s),
+                    k_sg: material.k_sg.unwrap_or(default.k_sg),
+                    k_tg: material.k_tg.unwrap_or(default.k_tg),
+                    ambient: material.ambient.map(to_vec3).unwrap_or(default.ambient),
+                    diffuse: material.diffuse.map(to_vec3).unwrap_or(default.diffuse),
+                    transmission: material.transmission.map(to_vec3).unwrap_or(default.transmission),
+                    emission: material.emission.map(to_vec3).unwrap_or(default.emission),
+                    specular: material.specular.map(to_vec3).unwrap_or(default.specular),
+                    roughness: material.roughness.unwrap_or(default.roughness),
+                    glossiness: material.glossiness.unwrap_or(default.glossiness),
+                    gauss_constant: material.gauss_constant.unwrap_or(default.gauss_constant),
+                    ior: material.ior.unwrap_or(default.ior),
+                    conductor: conductor,
+                    diffuse_texture: diffuse_texture
+                }))
+            },
+            "phong" => {
+                let default: PhongMaterial = Default::default();
+                Ok(SceneMaterial::Phong(PhongMaterial {
+                    k_a: material.k_a.unwrap_or(default.k_a),
+                    k_d: material.k_d.unwrap_or(default.k_d),
+                    k_s: material.k_s.unwrap_or(default.k_s),
+                    k_sg: material.k_sg.unwrap_or(default.k_sg),
+                    k_tg: material.k_tg.unwrap_or(default.k_tg),
+                    ambient: material.ambient.map(to_vec3).unwrap_or(default.ambient),
+                    diffuse: material.diffuse.map(to_vec3).unwrap_or(default.diffuse),
+                    transmission: material.transmission.map(to_vec3).unwrap_or(default.transmission),
+                    emission: material.emission.map(to_vec3).unwrap_or(default.emission),
+                    specular: material.specular.map(to_vec3).unwrap_or(default.specular),
+                    shininess: material.shininess.unwrap_or(default.shininess),
+                    glossiness: material.glossiness.unwrap_or(default.glossiness),
+                    ior: material.ior.unwrap_or(default.ior),
+                    conductor: conductor,
+                    diffuse_texture: diffuse_texture
+                }))
+            },
+            "ggx" => {
+                let default: GgxMaterial = Default::default();
+                Ok(SceneMaterial::Ggx(GgxMaterial {
+                    base_color: material.base_color.map(to_vec3).unwrap_or(default.base_color),
+                    metalness: material.metalness.unwrap_or(default.metalness),
+                    roughness: material.roughness.unwrap_or(default.roughness),
+                    ior: material.ior.unwrap_or(default.ior),
+                    conductor: conductor,
+                    emission: material.emission.map(to_vec3).unwrap_or(default.emission),
+                    base_color_texture: try!(texture(&material.base_color_texture))
+                }))
+            },
+            "principled" => {
+                let default: PrincipledMaterial = Default::default();
+                Ok(SceneMaterial::Principled(PrincipledMaterial {
+                    base_color: material.base_color.map(to_vec3).unwrap_or(default.base_color),
+                    metalness: material.metalness.unwrap_or(default.metalness),
+                    roughness: material.roughness.unwrap_or(default.roughness),
+                    specular_level: material.specular_level.unwrap_or(default.specular_level),
+                    clearcoat: material.clearcoat.unwrap_or(default.clearcoat),
+                    clearcoat_roughness: material.clearcoat_roughness.unwrap_or(default.clearcoat_roughness),
+                    sheen: material.sheen.unwrap_or(default.sheen),
+                    sheen_tint: material.sheen_tint.unwrap_or(default.sheen_tint),
+                    specular_transmission: material.specular_transmission.unwrap_or(default.specular_transmission),
+                    ior: material.ior.unwrap_or(default.ior),
+                    emission: material.emission.map(to_vec3).unwrap_or(default.emission),
+                    base_color_texture: try!(texture(&material.base_color_texture)),
+                    metalness_texture: try!(texture(&material.metalness_texture)),
+                    roughness_texture: try!(texture(&material.roughness_texture)),
+                    specular_level_texture: try!(texture(&material.specular_level_texture)),
+                    clearcoat_texture: try!(texture(&material.clearcoat_texture)),
+                    sheen_texture: try!(texture(&material.sheen_texture)),
+                    specular_transmission_texture: try!(texture(&material.specular_transmission_texture)),
+                    clearcoat_roughness_texture: try!(texture(&material.clearcoat_roughness_texture)),
+                    sheen_tint_texture: try!(texture(&material.sheen_tint_texture)),
+                    ior_texture: try!(texture(&material.ior_texture)),
+                    emission_texture: try!(texture(&material.emission_texture))
+                }))
+            },
+            "dielectric" => {
+                let default: DielectricMaterial = Default::default();
+                Ok(SceneMaterial::Dielectric(DielectricMaterial {
+                    ior: material.ior.unwrap_or(default.ior),
+                    cauchy_b: material.cauchy_b.unwrap_or(default.cauchy_b),
+                    color: material.color.map(to_vec3).unwrap_or(default.color),
+                    absorption: material.absorption.map(to_vec3).unwrap_or(default.absorption),
+                    medium: try!(SceneDescription::find_medium(&material.medium, media))
+                }))
+            },
+            "subsurface" => {
+                let default: SubsurfaceMaterial = Default::default();
+                let mean_free_path = material.mean_free_path.unwrap_or(default.mean_free_path);
+                if mean_free_path <= 0.0 {
+                    return Err(format!("``mean_free_path'' must be positive, got {}", mean_free_path));
+                }
+
+                Ok(SceneMaterial::Subsurface(SubsurfaceMaterial {
+                    ior: material.ior.unwrap_or(default.ior),
+                    scattering: material.scattering.map(to_vec3).unwrap_or(default.scattering),
+                    absorption: material.absorption.map(to_vec3).unwrap_or(default.absorption),
+                    mean_free_path: mean_free_path,
+                    g: material.g.unwrap_or(default.g)
+                }))
+            },
+            "flat" => {
+                let default: FlatMaterial = Default::default();
+                Ok(SceneMaterial::Flat(FlatMaterial {
+                    color: material.color.map(to_vec3).unwrap_or(default.color)
+                }))
+            },
+            kind => Err(format!("unknown material type ``{}''", kind))
+        }
+    }
+
+    fn build_prim(prim: &PrimDescription, material: Box<Material+Send+Sync>)
+                  -> Result<Box<Prim+Send+Sync>, String> {
+
+        match prim.kind.as_ref() {
+            "sphere" => {
+                let center = try!(require(prim.center, "center", "sphere"));
+                let radius = try!(require(prim.radius, "radius", "sphere"));
+                Ok(Box::new(Sphere { center: to_vec3(center), radius: radius, material: material }))
+            },
+            "plane" => {
+                let normal = try!(require(prim.normal, "normal", "plane"));
+                let d = try!(require(prim.d, "d", "plane"));
+                Ok(Box::new(Plane { a: normal.0, b: normal.1, c: normal.2, d: d, material: material }))
+            },
+            "triangle" => {
+                let v = try!(require(prim.vertices.as_ref(), "vertices", "triangle"));
+                if v.len() != 3 {
+                    return Err(format!("triangle needs 3 vertices, got {}", v.len()));
+                }
+
+                let mut triopts = TriangleOptions::new(to_vec3(v[0]), to_vec3(v[1]), to_vec3(v[2]));
+                if let Some(ref n) = prim.normals {
+                    if n.len() != 3 {
+                        return Err(format!("triangle needs 3 normals, got {}", n.len()));
+                    }
+                    triopts.normals([to_vec3(n[0]), to_vec3(n[1]), to_vec3(n[2])]);
+                }
+                if let Some(ref t) = prim.texinfo {
+                    if t.len() != 3 {
+                        return Err(format!("triangle needs 3 texinfo pairs, got {}", t.len()));
+                    }
+                    triopts.texinfo([t[0], t[1], t[2]]);
+                }
+                triopts.material(material);
+
+                Ok(Box::new(triopts.build()))
+            },
+            kind => Err(format!("unknown prim type ``{}''", kind))
+        }
+    }
+
+    fn build_mesh(mesh: &MeshDescription, materials: &HashMap<String, SceneMaterial>)
+                  -> Result<Mesh, String> {
+
+        let flip_normals = mesh.flip_normals.unwrap_or(false);
+        let filename = &mesh.filename;
+        let mut built = try!(match materials.get(&mesh.material) {
+            Some(&SceneMaterial::CookTorrance(ref m)) => ::util::import::from_obj(m.clone(), flip_normals, filename),
+            Some(&SceneMaterial::Phong(ref m)) => ::util::import::from_obj(m.clone(), flip_normals, filename),
+            Some(&SceneMaterial::Ggx(ref m)) => ::util::import::from_obj(m.clone(), flip_normals, filename),
+            Some(&SceneMaterial::Principled(ref m)) => ::util::import::from_obj(m.clone(), flip_normals, filename),
+            Some(&SceneMaterial::Dielectric(ref m)) => ::util::import::from_obj(m.clone(), flip_normals, filename),
+            Some(&SceneMaterial::Subsurface(ref m)) => ::util::import::from_obj(m.clone(), flip_normals, filename),
+            Some(&SceneMaterial::Flat(ref m)) => ::util::import::from_obj(m.clone(), flip_normals, filename),
+            None => Err(format!("unknown material ``{}''", mesh.material))
+        });
+
+        if let Some(ref transforms) = mesh.transforms {
+            for transform in transforms.iter() {
+                let m = try!(SceneDescription::build_transform(transform));
+                built.mut_transform(&Transform::new(m));
+            }
+        }
+
+        Ok(built)
+    }
+
+    fn build_transform(transform: &TransformDescription) -> Result<Mat4, String> {
+        let kind: &str = transform.kind.as_ref();
+        match kind {
+            "translate" => {
+                let v = try!(require(transform.vector, "vector", kind));
+                Ok(Mat4::translate_matrix(&to_vec3(v)))
+            },
+            "scale" => {
+                let v = try!(require(transform.vector, "vector", kind));
+                Ok(Mat4::scale_matrix(&to_vec3(v)))
+            },
+            "rotate_x" => Ok(Mat4::rotate_x_deg_matrix(try!(require(transform.angle, "angle", kind)))),
+            "rotate_y" => Ok(Mat4::rotate_y_deg_matrix(try!(require(transform.angle, "angle", kind)))),
+            "rotate_z" => Ok(Mat4::rotate_z_deg_matrix(try!(require(transform.angle, "angle", kind)))),
+            "rotate_axis" => {
+                let angle = try!(require(transform.angle, "angle", kind));
+                let axis = try!(require(transform.vector, "vector", kind));
+                Ok(Mat4::rotate_axis_deg_matrix(angle, &to_vec3(axis)))
+            },
+            _ => Err(format!("unknown transform type ``{}''", kind))
+        }
+    }
+}
+
+#[cfg(test)]
+use raytracer::Ray;
+
+#[test]
+fn it_builds_a_scene_from_json() {
+    let json_data = r#"{
+        "camera": { "position": [0.0, 0.0, 10.0], "look_at": [0.0, 0.0, 0.0], "up": [0.0, 1.0, 0.0],
+                    "f_stop": 4.0, "focal_length": 0.8, "aperture": { "kind": "polygon", "blades": 7 } },
+        "lights": [
+            { "kind": "point", "position": [0.0, 10.0, 0.0], "color": [1.0, 1.0, 1.0] },
+            { "kind": "sphere", "position": [0.0, 10.0, 5.0], "color": [0.5, 0.5, 0.5], "radius": 1.0 },
+            { "kind": "quad", "position": [-1.0, 9.0, -1.0], "color": [4.0, 4.0, 4.0],
+              "edge1": [2.0, 0.0, 0.0], "edge2": [0.0, 0.0, 2.0] },
+            { "kind": "disk", "position": [0.0, 9.0, 0.0], "color": [4.0, 4.0, 4.0],
+              "normal": [0.0, -2.0, 0.0], "radius": 1.0 },
+            { "kind": "spot", "position": [0.0, 10.0, 0.0], "power": [100.0, 100.0, 100.0],
+              "direction": [0.0, -1.0, 0.0], "cone_angle": 30.0, "falloff_angle": 20.0 },
+            { "kind": "directional", "direction": [0.0, -1.0, 0.0], "color": [1.0, 1.0, 1.0],
+              "angular_diameter": 0.5 }
+        ],
+        "textures": { "checker": { "kind": "checker", "scale": 2.0 } },
+        "materials": {
+            "red": { "kind": "flat", "color": [1.0, 0.0, 0.0] },
+            "floor": { "kind": "cook_torrance", "k_d": 0.8, "diffuse_texture": "checker" },
+            "copper": { "kind": "phong", "k_sg": 0.9, "metal": "copper" },
+            "mirror": { "kind": "ggx", "roughness": 0.0, "eta": [0.2, 0.2, 0.2], "k": [3.0, 3.0, 3.0] },
+            "gold": { "kind": "ggx", "base_color": [1.0, 0.8, 0.3], "metalness": 1.0, "roughness": 0.3,
+                      "base_color_texture": "checker" },
+            "car_paint": { "kind": "principled", "base_color": [0.6, 0.0, 0.0], "clearcoat": 1.0,
+                           "roughness_texture": "checker", "clearcoat_roughness_texture": "checker",
+                           "emission_texture": "checker" },
+            "prism": { "kind": "dielectric", "ior": 1.6, "cauchy_b": 0.01, "absorption": [0.1, 0.0, 0.1] },
+            "smoke_box": { "kind": "dielectric", "ior": 1.0, "medium": "smoke" },
+            "wax": { "kind": "subsurface", "scattering": [0.99, 0.9, 0.6], "absorption": [0.01, 0.1, 0.4],
+                     "mean_free_path": 0.05, "g": 0.2 }
+        },
+        "media": {
+            "fog": { "kind": "homogeneous", "scattering": [0.01, 0.01, 0.01], "g": 0.3,
+                     "bounds": [[-20.0, -2.0, -20.0], [20.0, 10.0, 20.0]] },
+            "smoke": { "kind": "grid", "filename": "test/res/smoke.vox", "scattering": [2.0, 2.0, 2.0],
+                       "bounds": [[-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]] }
+        },
+        "medium": "fog",
+        "prims": [
+            { "kind": "sphere", "material": "red", "center": [0.0, 0.0, 0.0], "radius": 2.0 },
+            { "kind": "plane", "material": "floor", "normal": [0.0, 1.0, 0.0], "d": 2.0 }
+        ],
+        "meshes": [{ "filename": "test/res/cube.obj", "material": "wax",
+                     "transforms": [{ "kind": "translate", "vector": [5.0, 5.0, -5.0] }] }],
+        "background": [0.0, 0.0, 1.0]
+    }"#;
+
+    let description = from_str(json_data).unwrap();
+    let (camera, scene) = description.build(32, 32, 45.0, false, AcceleratorKind::Octree).unwrap();
+
+    assert_eq!(camera.position, Vec3 { x: 0.0, y: 0.0, z: 10.0 });
+    assert_eq!(camera.aperture_radius, 0.1);
+    assert_eq!(camera.focus_distance, 10.0);
+    assert_eq!(scene.lights.len(), 6);
+    assert_eq!(scene.background, Vec3 { x: 0.0, y: 0.0, z: 1.0 });
+    assert_eq!(scene.medium.as_ref().unwrap().majorant(), 0.01);
+
+    let ray = Ray::new(Vec3 { x: 0.0, y: 0.0, z: 10.0 }, Vec3 { x: 0.0, y: 0.0, z: -1.0 });
+    let hit = ray.get_nearest_hit(&scene).unwrap();
+    assert_eq!(hit.position.z, 2.0);
+    assert_eq!(hit.material.eval(&hit.n, &hit.n, &hit.n, 0.0, 0.0), Vec3 { x: 1.0 / ::std::f64::consts::PI, y: 0.0, z: 0.0 });
+}
+
+#[test]
+fn it_rejects_unknown_materials() {
+    let json_data = r#"{
+        "camera": { "position": [0.0, 0.0, 10.0], "look_at": [0.0, 0.0, 0.0], "up": [0.0, 1.0, 0.0] },
+        "lights": [],
+        "materials": {},
+        "prims": [{ "kind": "sphere", "material": "missing", "center": [0.0, 0.0, 0.0], "radius": 2.0 }]
+    }"#;
+
+    let description = from_str(json_data).unwrap();
+    assert!(description.build(32, 32, 45.0, false, AcceleratorKind::Octree).is_err());
+
+    let json_data = r#"{
+        "camera": { "position": [0.0, 0.0, 10.0], "look_at": [0.0, 0.0, 0.0], "up": [0.0, 1.0, 0.0] },
+        "lights": [],
+        "materials": { "shiny": { "kind": "cook_torrance", "metal": "unobtainium" } },
+        "prims": []
+    }"#;
+
+    let description = from_str(json_data).unwrap();
+    assert!(description.build(32, 32, 45.0, false, AcceleratorKind::Octree).is_err());
+
+    let json_data = r#"{
+        "camera": { "position": [0.0, 0.0, 10.0], "look_at": [0.0, 0.0, 0.0], "up": [0.0, 1.0, 0.0] },
+        "lights": [],
+        "materials": { "glass": { "kind": "dielectric", "medium": "missing" } },
+        "prims": []
+    }"#;
+
+    let description = from_str(json_data).unwrap();
+    assert!(description.build(32, 32, 45.0, false, AcceleratorKind::Octree).is_err());
+}
+
+#[test]
+fn it_rejects_invalid_light_angles() {
+    let light = |fields: &str| {
+        format!(r#"{{
+            "camera": {{ "position": [0.0, 0.0, 10.0], "look_at": [0.0, 0.0, 0.0], "up": [0.0, 1.0, 0.0] }},
+            "lights": [{{ {}, "position": [0.0, 5.0, 0.0], "direction": [0.0, -1.0, 0.0], "color": [1.0, 1.0, 1.0] }}],
+            "materials": {{}}
+        }}"#, fields)
+    };
+    let build = |fields: &str| from_str(&light(fields)).unwrap().build(32, 32, 45.0, false, AcceleratorKind::Octree);
+
+    assert!(build(r#""kind": "spot", "cone_angle": 30.0, "falloff_angle": 20.0"#).is_ok());
+    assert!(build(r#""kind": "spot", "cone_angle": 30.0"#).is_ok());
+    assert!(build(r#""kind": "spot", "cone_angle": 20.0, "falloff_angle": 30.0"#).is_err());
+    assert!(build(r#""kind": "spot", "cone_angle": 30.0, "falloff_angle": -5.0"#).is_err());
+    assert!(build(r#""kind": "spot", "cone_angle": -30.0"#).is_err());
+    assert!(build(r#""kind": "directional", "angular_diameter": -1.0"#).is_err());
+}
+
+#[test]
+fn it_reports_missing_skybox_images() {
+    let json_data = r#"{
+        "camera": { "position": [0.0, 0.0, 10.0], "look_at": [0.0, 0.0, 0.0], "up": [0.0, 1.0, 0.0] },
+        "lights": [],
+        "skybox": { "filename": "./does/not/exist.hdr" },
+        "materials": {}
+    }"#;
+
+    let description = from_str(json_data).unwrap();
+    let err = description.build(32, 32, 45.0, false, AcceleratorKind::Octree).err().unwrap();
+    assert!(err.contains("./does/not/exist.hdr"));
+}
+
+#[test]
+fn it_rejects_invalid_lenses() {
+    let build = |lens: &str| {
+        let json_data = format!(r#"{{
+            "camera": {{ "position": [0.0, 0.0, 10.0], "look_at": [0.0, 0.0, 0.0], "up": [0.0, 1.0, 0.0], {},
+                         "keyframes": [{{ "time": 1.0, "position": [0.0, 0.0, 4.0], "look_at": [0.0, 0.0, 0.0],
+                                          "up": [0.0, 1.0, 0.0] }}] }},
+            "lights": [],
+            "materials": {{}}
+        }}"#, lens);
+        from_str(&json_data).unwrap().build(32, 32, 45.0, true, AcceleratorKind::Octree)
+    };
+
+    assert!(build(r#""f_stop": 2.0, "focal_length": 0.05, "focus_distance": 8.0"#).is_ok());
+    assert!(build(r#""aperture_radius": 0.0"#).is_ok());
+    assert!(build(r#""aperture_radius": -0.1"#).is_err());
+    assert!(build(r#""f_stop": 0.0, "focal_length": 0.05"#).is_err());
+    assert!(build(r#""f_stop": 2.0, "focal_length": -0.05"#).is_err());
+    assert!(build(r#""aperture_radius": 0.1, "focus_distance": 0.0"#).is_err());
+}
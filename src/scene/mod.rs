@@ -1,5 +1,8 @@
+pub use self::aperture::{Aperture, ApertureImage};
 pub use self::camera::Camera;
 pub use self::scene::Scene;
 
+pub mod aperture;
 pub mod camera;
+pub mod description;
 pub mod scene;
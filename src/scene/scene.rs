@@ -1,12 +1,16 @@
 use light::Light;
-use material::textures::CubeMap;
+use material::Environment;
+use medium::Medium;
 use geometry::Prim;
-use raytracer::Octree;
+use raytracer::Accelerator;
+use std::sync::Arc;
 use vec3::Vec3;
 
 pub struct Scene {
     pub lights: Vec<Box<Light+Send+Sync>>,
-    pub octree: Octree<Box<Prim+Send+Sync>>,
+    pub accelerator: Accelerator<Box<Prim+Send+Sync>>,
     pub background: Vec3,
-    pub skybox: Option<CubeMap>
+    pub skybox: Option<Arc<Environment+Send+Sync>>,
+    /// Fog or atmosphere the camera and everything outside closed media are in
+    pub medium: Option<Arc<Medium+Send+Sync>>
 }
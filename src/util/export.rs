@@ -1,21 +1,227 @@
 use std::fs::File;
 use std::io::Write;
-use raytracer::compositor::{Surface, Channel};
+use std::path::Path;
+use raytracer::compositor::{ColorRGBA, Surface};
+use raytracer::compositor::tonemap::{ToneMapOperator, ToneMapping};
 
-#[allow(unused_must_use)]
-pub fn to_ppm(surface: Surface, filename: &str) {
-    let channel_max: u8 = Channel::max_value();
-    let header = format!(
-        "P3 {} {} {}\n", surface.width, surface.height,
-        channel_max);
+/// Writes the rendered image, picking the format from the extension of
+/// `filename`. `.hdr` and `.exr` keep the linear radiance, other formats
+/// (`.png`, `.jpg`, `.tga`, `.ppm`) are tone mapped to 8 bits first.
+pub fn save(image: &Surface<f64>, tone_mapping: &ToneMapping, filename: &str) -> Result<(), String> {
+    match extension(filename).as_ref() {
+        "hdr" => to_hdr(image, filename),
+        "exr" => to_exr(image, filename),
+        _ => to_file(&tone_mapping.apply(image), filename)
+    }
+}
 
-    let mut f = match File::create(filename) {
-        Ok(f)  => f,
-        Err(e) => panic!("File error: {}", e),
+/// Writes a greyscale image of the samples taken per pixel, from black for
+/// none to white for `max_samples`. See `Renderer::render_with_sample_map`.
+pub fn save_sample_map(sample_map: &Surface<f64>, max_samples: u32, filename: &str) -> Result<(), String> {
+    let linear = ToneMapping {
+        exposure: -(max_samples as f64).log2(),
+        operator: ToneMapOperator::Clamp,
+        srgb: false
     };
+    save(sample_map, &linear, filename)
+}
+
+/// Writes an 8-bit surface as PNG, JPEG, TGA or binary (P6) PPM
+pub fn to_file(surface: &Surface, filename: &str) -> Result<(), String> {
+    let mut rgb = Vec::with_capacity(surface.pixel_count() * 3);
+    for pixel in surface.buffer.iter() {
+        rgb.push(pixel.r);
+        rgb.push(pixel.g);
+        rgb.push(pixel.b);
+    }
+
+    match extension(filename).as_ref() {
+        "png" | "jpg" | "jpeg" | "ppm" => {
+            ::image::save_buffer(filename, &rgb, surface.width as u32, surface.height as u32,
+                                 ::image::ColorType::RGB(8))
+                .map_err(|e| format!("cannot write ``{}'': {}", filename, e))
+        },
+        "tga" => to_tga(surface, filename),
+        ext => Err(format!("unsupported output format ``{}''", ext))
+    }
+}
+
+/// Splits off the extension of `filename`, defaulting to PPM when there is none.
+/// `frames/out.png` gives `("frames/out", "png")`.
+pub fn split_extension(filename: &str) -> (String, String) {
+    match Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => (filename[..filename.len() - ext.len() - 1].to_string(), ext.to_lowercase()),
+        None => (filename.to_string(), "ppm".to_string())
+    }
+}
+
+fn extension(filename: &str) -> String {
+    split_extension(filename).1
+}
+
+/// Uncompressed 24-bit Truevision TGA, stored top to bottom
+fn to_tga(surface: &Surface, filename: &str) -> Result<(), String> {
+    let (width, height) = (surface.width as u16, surface.height as u16);
+    let mut data = vec![
+        0, 0, 2,          // No image ID or colour map, uncompressed true colour
+        0, 0, 0, 0, 0,    // Colour map specification
+        0, 0, 0, 0,       // x, y origin
+        width as u8, (width >> 8) as u8,
+        height as u8, (height >> 8) as u8,
+        24, 0x20          // Bits per pixel, top-left origin
+    ];
 
-    f.write_all(header.as_bytes());
     for pixel in surface.buffer.iter() {
-        f.write_all(format!("{} {} {} ", pixel.r, pixel.g, pixel.b).as_bytes());
+        data.push(pixel.b);
+        data.push(pixel.g);
+        data.push(pixel.r);
     }
+
+    write_file(filename, &data)
+}
+
+/// Writes linear radiance as a Radiance RGBE (.hdr) file with flat scanlines
+pub fn to_hdr(surface: &Surface<f64>, filename: &str) -> Result<(), String> {
+    let mut data = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+                           surface.height, surface.width).into_bytes();
+
+    for pixel in surface.buffer.iter() {
+        data.extend(to_rgbe(pixel).iter());
+    }
+
+    write_file(filename, &data)
+}
+
+/// Writes linear radiance as an uncompressed, single-part scanline OpenEXR
+/// file with 32-bit float R, G and B channels.
+pub fn to_exr(surface: &Surface<f64>, filename: &str) -> Result<(), String> {
+    let width = surface.width;
+    let height = surface.height;
+
+    let mut data = Vec::new();
+    push_u32(&mut data, 20000630); // Magic number
+    push_u32(&mut data, 2);        // Version 2, single-part scanline
+
+    // Channels must be listed alphabetically, 2 is FLOAT
+    let mut channels = Vec::new();
+    for name in ["B", "G", "R"].iter() {
+        channels.extend(name.bytes());
+        channels.push(0);
+        push_u32(&mut channels, 2);
+        channels.extend([0, 0, 0, 0].iter()); // pLinear, reserved
+        push_u32(&mut channels, 1);
+        push_u32(&mut channels, 1);
+    }
+    channels.push(0);
+
+    let mut window = Vec::new();
+    for &v in [0, 0, width as u32 - 1, height as u32 - 1].iter() {
+        push_u32(&mut window, v);
+    }
+
+    let mut screen_center = Vec::new();
+    push_f32(&mut screen_center, 0.0);
+    push_f32(&mut screen_center, 0.0);
+    let mut one = Vec::new();
+    push_f32(&mut one, 1.0);
+
+    push_attribute(&mut data, "channels", "chlist", &channels);
+    push_attribute(&mut data, "compression", "compression", &[0]);
+    push_attribute(&mut data, "dataWindow", "box2i", &window);
+    push_attribute(&mut data, "displayWindow", "box2i", &window);
+    push_attribute(&mut data, "lineOrder", "lineOrder", &[0]);
+    push_attribute(&mut data, "pixelAspectRatio", "float", &one);
+    push_attribute(&mut data, "screenWindowCenter", "v2f", &screen_center);
+    push_attribute(&mut data, "screenWindowWidth", "float", &one);
+    data.push(0);
+
+    // Offset table: one uncompressed scanline per block, each holding
+    // y, byte count and then every channel's row of samples in turn.
+    let line_size = 3 * 4 * width;
+    let first_line = data.len() + 8 * height;
+    for y in 0..height {
+        let offset = (first_line + y * (8 + line_size)) as u64;
+        push_u32(&mut data, offset as u32);
+        push_u32(&mut data, (offset >> 32) as u32);
+    }
+
+    for y in 0..height {
+        push_u32(&mut data, y as u32);
+        push_u32(&mut data, line_size as u32);
+
+        let row = &surface.buffer[y * width..(y + 1) * width];
+        for pixel in row.iter() { push_f32(&mut data, pixel.b as f32); }
+        for pixel in row.iter() { push_f32(&mut data, pixel.g as f32); }
+        for pixel in row.iter() { push_f32(&mut data, pixel.r as f32); }
+    }
+
+    write_file(filename, &data)
+}
+
+/// Shared exponent encoding, see Ward's "Real Pixels" (Graphics Gems II)
+fn to_rgbe(pixel: &ColorRGBA<f64>) -> [u8; 4] {
+    let r = pixel.r.max(0.0);
+    let g = pixel.g.max(0.0);
+    let b = pixel.b.max(0.0);
+    let v = r.max(g).max(b);
+
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // v = mantissa * 2^exponent with mantissa in [0.5, 1)
+    let exponent = v.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2.0f64.powi(exponent);
+
+    [(r * scale).min(255.0) as u8,
+     (g * scale).min(255.0) as u8,
+     (b * scale).min(255.0) as u8,
+     (exponent + 128) as u8]
+}
+
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    data.extend([value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8].iter());
+}
+
+fn push_f32(data: &mut Vec<u8>, value: f32) {
+    push_u32(data, value.to_bits());
+}
+
+fn push_attribute(data: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    data.extend(name.bytes());
+    data.push(0);
+    data.extend(kind.bytes());
+    data.push(0);
+    push_u32(data, value.len() as u32);
+    data.extend(value.iter());
+}
+
+fn write_file(filename: &str, data: &[u8]) -> Result<(), String> {
+    let mut f = match File::create(filename) {
+        Ok(f) => f,
+        Err(e) => return Err(format!("cannot create ``{}'': {}", filename, e))
+    };
+
+    f.write_all(data).map_err(|e| format!("cannot write ``{}'': {}", filename, e))
+}
+
+#[test]
+fn it_encodes_rgbe() {
+    assert_eq!([0, 0, 0, 0], to_rgbe(&ColorRGBA::new_rgb(0.0, 0.0, 0.0)));
+    assert_eq!([128, 64, 0, 129], to_rgbe(&ColorRGBA::new_rgb(1.0, 0.5, 0.0)));
+    assert_eq!([128, 0, 0, 133], to_rgbe(&ColorRGBA::new_rgb(16.0, 0.0, -1.0)));
+}
+
+#[test]
+fn it_splits_extensions() {
+    assert_eq!(("frames/out".to_string(), "png".to_string()), split_extension("frames/out.png"));
+    assert_eq!(("test".to_string(), "ppm".to_string()), split_extension("test"));
+    assert_eq!(("render.v2/a".to_string(), "ppm".to_string()), split_extension("render.v2/a"));
+    assert_eq!(("a".to_string(), "jpg".to_string()), split_extension("a.JPG"));
+}
+
+#[test]
+fn it_rejects_unknown_formats() {
+    let surface = Surface::new(1, 1, ColorRGBA::new_rgb(0, 0, 0));
+    assert!(to_file(&surface, "out.xyz").is_err());
 }
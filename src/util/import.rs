@@ -1,16 +1,20 @@
 use geometry::prims::TriangleOptions;
 use geometry::{Mesh, Prim};
 use image::GenericImage;
-use material::materials::CookTorranceMaterial;
+use material::Material;
 use raytracer::compositor::{Surface, ColorRGBA};
 use std::fs::File;
+use std::io::Read;
 use std::path::Path;
 use std::io::{BufRead, BufReader};
 use vec3::Vec3;
 
-/// This is limited to only CookTorranceMaterials, as I couldn't get a Box<Material> to clone
-/// a new material for each triangle primitive in the object model.
-pub fn from_obj(material: CookTorranceMaterial, flip_normals: bool, filename: &str) -> Result<Mesh, String> {
+#[cfg(test)]
+use material::materials::CookTorranceMaterial;
+
+/// Every triangle primitive in the object model gets its own clone of `material`.
+pub fn from_obj<M>(material: M, flip_normals: bool, filename: &str) -> Result<Mesh, String>
+        where M: Material + Clone + Send + Sync + 'static {
     let file_handle = match File::open(&filename) {
         Ok(f) => f,
         Err(err) => return Err(format!("{}", err))
@@ -143,6 +147,151 @@ pub fn from_image<P: AsRef<Path>>(path: P) -> Result<Surface, String> {
     Ok(surface)
 }
 
+/// Reads a density grid from a text file: the number of voxels along x, y
+/// and z, then that many densities with x varying fastest, then y, then z.
+/// Values are separated by whitespace and `#` starts a comment.
+pub fn from_voxels<P: AsRef<Path>>(path: P) -> Result<([usize; 3], Vec<f64>), String> {
+    let mut text = String::new();
+    if let Err(err) = File::open(path).and_then(|mut file| file.read_to_string(&mut text)) {
+        return Err(format!("{}", err));
+    }
+
+    let mut tokens = text.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split_whitespace());
+
+    let mut size = [0; 3];
+    for axis in 0..3 {
+        size[axis] = match tokens.next().map(|token| token.parse()) {
+            Some(Ok(n)) if n > 0 => n,
+            _ => return Err("voxel file should start with the grid's size".to_string())
+        };
+    }
+
+    let mut density = Vec::with_capacity(size[0] * size[1] * size[2]);
+    for token in tokens {
+        match token.parse() {
+            Ok(d) => density.push(d),
+            Err(_) => return Err(format!("bad density ``{}''", token))
+        }
+    }
+
+    if density.len() != size[0] * size[1] * size[2] {
+        return Err(format!("expected {} densities, got {}", size[0] * size[1] * size[2], density.len()));
+    }
+
+    Ok((size, density))
+}
+
+/// Reads a Radiance RGBE (.hdr) file into linear radiance. Scanlines may be
+/// flat or run-length encoded, only the usual -Y +X orientation is supported.
+pub fn from_hdr<P: AsRef<Path>>(path: P) -> Result<Surface<f64>, String> {
+    let mut data = Vec::new();
+    if let Err(err) = File::open(path).and_then(|mut file| file.read_to_end(&mut data)) {
+        return Err(format!("{}", err));
+    }
+
+    // Header lines up to an empty one, then the resolution line
+    let mut lines: Vec<String> = Vec::new();
+    let mut pos = 0;
+    while lines.len() < 2 || !lines[lines.len() - 2].is_empty() {
+        let end = match data[pos..].iter().position(|&byte| byte == b'\n') {
+            Some(end) => pos + end,
+            None => return Err("truncated header".to_string())
+        };
+        lines.push(String::from_utf8_lossy(&data[pos..end]).into_owned());
+        pos = end + 1;
+    }
+
+    if !lines[0].starts_with("#?") {
+        return Err("not a Radiance HDR file".to_string());
+    }
+    if lines.iter().any(|line| line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe") {
+        return Err("only the 32-bit_rle_rgbe format is supported".to_string());
+    }
+
+    let resolution: Vec<&str> = lines[lines.len() - 1].split_whitespace().collect();
+    let (height, width) = match (resolution.get(0), resolution.get(2)) {
+        (Some(&"-Y"), Some(&"+X")) => match (resolution[1].parse(), resolution[3].parse()) {
+            (Ok(height), Ok(width)) => (height, width),
+            _ => return Err(format!("bad resolution ``{}''", lines[lines.len() - 1]))
+        },
+        _ => return Err(format!("unsupported resolution ``{}''", lines[lines.len() - 1]))
+    };
+
+    let mut surface = Surface::new(width, height, ColorRGBA::black());
+    let mut scanline = vec![[0u8; 4]; width];
+
+    for y in 0..height {
+        pos = try!(read_hdr_scanline(&data, pos, &mut scanline));
+
+        for (x, rgbe) in scanline.iter().enumerate() {
+            surface[(x, y)] = if rgbe[3] == 0 {
+                ColorRGBA::black()
+            } else {
+                let scale = 2.0f64.powi(rgbe[3] as i32 - (128 + 8));
+                ColorRGBA::new_rgb((rgbe[0] as f64 + 0.5) * scale,
+                                   (rgbe[1] as f64 + 0.5) * scale,
+                                   (rgbe[2] as f64 + 0.5) * scale)
+            };
+        }
+    }
+
+    Ok(surface)
+}
+
+/// Decodes one scanline starting at `pos`, returns where the next one starts
+fn read_hdr_scanline(data: &[u8], mut pos: usize, scanline: &mut [[u8; 4]]) -> Result<usize, String> {
+    let width = scanline.len();
+    let truncated = || "truncated pixel data".to_string();
+
+    let run_length_encoded = width >= 8 && width < 32768 && data.len() >= pos + 4 &&
+        data[pos] == 2 && data[pos + 1] == 2 && data[pos + 2] & 0x80 == 0;
+
+    if !run_length_encoded {
+        if data.len() < pos + width * 4 { return Err(truncated()) }
+        for (x, pixel) in scanline.iter_mut().enumerate() {
+            pixel.copy_from_slice(&data[pos + x * 4..pos + x * 4 + 4]);
+        }
+        return Ok(pos + width * 4);
+    }
+
+    if ((data[pos + 2] as usize) << 8 | data[pos + 3] as usize) != width {
+        return Err("scanline width mismatch".to_string());
+    }
+    pos += 4;
+
+    // Each channel in turn, as runs (count above 128) or literal bytes
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = *try!(data.get(pos).ok_or_else(&truncated)) as usize;
+            pos += 1;
+
+            if count > 128 {
+                let value = *try!(data.get(pos).ok_or_else(&truncated));
+                pos += 1;
+                if x + count - 128 > width { return Err("run past the end of a scanline".to_string()) }
+                for pixel in scanline[x..x + count - 128].iter_mut() {
+                    pixel[channel] = value;
+                }
+                x += count - 128;
+            } else {
+                if count == 0 || x + count > width || data.len() < pos + count {
+                    return Err("bad literal run in a scanline".to_string());
+                }
+                for (pixel, &value) in scanline[x..x + count].iter_mut().zip(data[pos..pos + count].iter()) {
+                    pixel[channel] = value;
+                }
+                x += count;
+                pos += count;
+            }
+        }
+    }
+
+    Ok(pos)
+}
+
 #[test]
 pub fn test_obj_loads_correct_number_of_triangles() {
     let material: CookTorranceMaterial = Default::default();
@@ -177,3 +326,52 @@ pub fn test_from_png24() {
         }
     }
 }
+
+#[test]
+pub fn test_from_hdr() {
+    use std::env;
+    use util::export::to_hdr;
+
+    let mut surface = Surface::new(9, 2, ColorRGBA::black());
+    surface[(0, 0)] = ColorRGBA::new_rgb(1.0, 0.5, 0.25);
+    surface[(8, 1)] = ColorRGBA::new_rgb(1000.0, 0.0, 0.01);
+
+    let filename = env::temp_dir().join("rust-raytracer-test.hdr");
+    to_hdr(&surface, filename.to_str().unwrap()).unwrap();
+    let flat = from_hdr(&filename).unwrap();
+
+    assert_eq!((9, 2), (flat.width, flat.height));
+    // RGBE keeps 8 bits of mantissa relative to the brightest channel
+    for (a, b) in flat.buffer.iter().zip(surface.buffer.iter()) {
+        let precision = b.r.max(b.g).max(b.b) / 128.0;
+        for &(a, b) in [(a.r, b.r), (a.g, b.g), (a.b, b.b)].iter() {
+            assert!((a - b).abs() <= precision);
+        }
+    }
+
+    // The same top scanline run-length encoded: a literal first pixel, then a run of 8
+    let mut encoded = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 9\n".to_vec();
+    encoded.extend([2, 2, 0, 9].iter());
+    encoded.extend([1, 128, 128 + 8, 0].iter());
+    encoded.extend([1, 64, 128 + 8, 0].iter());
+    encoded.extend([1, 32, 128 + 8, 0].iter());
+    encoded.extend([1, 129, 128 + 8, 0].iter());
+    ::std::fs::File::create(&filename).and_then(|mut file| {
+        use std::io::Write;
+        file.write_all(&encoded)
+    }).unwrap();
+
+    let rle = from_hdr(&filename).unwrap();
+    assert_eq!((9, 1), (rle.width, rle.height));
+    assert!((rle[(0, 0)].r - 1.0).abs() < 0.01 && (rle[(0, 0)].b - 0.25).abs() < 0.01);
+    assert_eq!(0.0, rle[(8, 0)].g);
+}
+
+#[test]
+pub fn test_from_voxels() {
+    let (size, density) = from_voxels("test/res/smoke.vox")
+            .ok().expect("failed to load test voxels `test/res/smoke.vox`");
+
+    assert_eq!([2, 2, 2], size);
+    assert_eq!(vec![0.0, 0.25, 0.5, 0.75, 1.0, 1.0, 0.5, 0.0], density);
+}
@@ -1,8 +1,6 @@
 use std::cmp;
 use std::fmt;
 use std::ops::{Add, Mul, Div, Neg, Sub};
-use rand;
-use rand::{thread_rng, Rng};
 
 #[derive(Clone, Copy, Default)]
 pub struct Vec3 {
@@ -104,6 +102,7 @@ impl Vec3 {
         }
     }
 
+    #[allow(dead_code)]
     pub fn clamp(&self, min: f64, max: f64) -> Vec3 {
         Vec3 {
             x: self.x.max(min).min(max),
@@ -112,21 +111,27 @@ impl Vec3 {
         }
     }
 
-    /// http://stackoverflow.com/questions/5408276/python-uniform-spherical-distribution
-    pub fn random() -> Vec3 {
-        let mut rng = rand::thread_rng();
-        let phi: f64      = rng.gen_range( 0.0, 2.0 * ::std::f64::consts::PI);
-        let costheta: f64 = rng.gen_range(-1.0, 1.0);
-        let u: f64        = rng.gen_range( 0.0, 1.0);
+    /// Two unit vectors perpendicular to this unit vector and to each other
+    pub fn orthonormal_basis(&self) -> (Vec3, Vec3) {
+        let a = if self.x.abs() > 0.9 {
+            Vec3 { x: 0.0, y: 1.0, z: 0.0 }
+        } else {
+            Vec3 { x: 1.0, y: 0.0, z: 0.0 }
+        };
+        let t = self.cross(&a).unit();
+        let b = self.cross(&t);
+
+        (t, b)
+    }
 
-        let theta = costheta.acos();
-        let r = u.powf(1.0 / 3.0);
+    /// Cosine-weighted direction in the hemisphere around the unit vector n,
+    /// given two uniform random numbers in [0, 1). The pdf is cos(theta) / PI.
+    pub fn cosine_hemisphere(n: &Vec3, u1: f64, u2: f64) -> Vec3 {
+        let r = u1.sqrt();
+        let phi = 2.0 * ::std::f64::consts::PI * u2;
+        let (t, b) = n.orthonormal_basis();
 
-        Vec3 {
-            x: r * theta.sin() * phi.cos(),
-            y: r * theta.sin() * phi.sin(),
-            z: r * theta.cos()
-        }
+        t.scale(r * phi.cos()) + b.scale(r * phi.sin()) + n.scale((1.0 - u1).max(0.0).sqrt())
     }
 }
 
@@ -327,6 +332,19 @@ fn it_linearly_interpolates() {
     assert_eq!(Vec3::one(), Vec3::lerp(&Vec3::zero(), &Vec3::one(), 1.0));
 }
 
+#[test]
+fn it_samples_the_cosine_weighted_hemisphere() {
+    let n = Vec3 { x: 0.0, y: 0.0, z: -1.0 };
+
+    for &(u1, u2) in [(0.0, 0.0), (0.5, 0.25), (0.99, 0.75), (0.3, 0.999)].iter() {
+        let d = Vec3::cosine_hemisphere(&n, u1, u2);
+        assert!((d.len() - 1.0).abs() < 1e-9);
+        assert!(d.dot(&n) >= 0.0);
+    }
+
+    assert_eq!(n, Vec3::cosine_hemisphere(&n, 0.0, 0.0));
+}
+
 #[test]
 fn its_macro_definitions_work() {
     let four = 4.0;